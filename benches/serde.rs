@@ -1,10 +1,12 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::ser::{SerializeSeq, Serializer};
 use std::time::Duration;
 use talk_serde_dyn_schema::{
     array_def, fast,
-    flatbin::{Builder, FlatbinBuf},
+    flatbin::FlatbinBuf,
+    options::DeserializeOptions,
     slow, struct_def,
-    ty::Ty,
+    ty::{Field, Ty},
 };
 
 fn criterion_benchmark(c: &mut Criterion) {
@@ -55,9 +57,8 @@ fn criterion_benchmark(c: &mut Criterion) {
     group.measurement_time(Duration::from_secs(30));
     group.bench_function("deserialize_slow", |b| {
         b.iter(|| {
-            buffer.clear();
             let doc = serde_json::from_str(&json).unwrap();
-            slow::deserialize(black_box(&schema), black_box(&doc), Builder::new(&mut buffer))
+            slow::deserialize(black_box(&schema), black_box(&doc), buffer.reset_builder())
         })
     });
     group.bench_function("deserialize_fast", |b| {
@@ -85,6 +86,129 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
     group.finish();
+
+    let Ty::Struct { fields } = struct_def!({
+        "name": Ty::String,
+        "age": Ty::U64,
+        "rustacean": Ty::Bool,
+    }) else {
+        unreachable!()
+    };
+    let rows: Vec<_> = (0..10_000)
+        .map(|i| serde_json::json!({ "name": format!("Person {}", i), "age": i % 90, "rustacean": i % 3 == 0 }))
+        .collect();
+    let rows = serde_json::Value::from(rows);
+    let row_schema = array_def!(Ty::Struct { fields: fields.clone() });
+    let column_schema = Ty::Columnar { fields };
+    let row_binary = slow::deserialize_alloc(&row_schema, &rows).unwrap();
+    let column_binary = slow::deserialize_alloc(&column_schema, &rows).unwrap();
+
+    let mut group = c.benchmark_group("scan");
+    group.bench_function("sum_rows", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            let binary = black_box(&row_binary);
+            row_schema
+                .scan_field(binary, &["age"], |v| total += v.read_u64().unwrap())
+                .unwrap();
+            total
+        })
+    });
+    group.bench_function("sum_columns", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            let binary = black_box(&column_binary);
+            column_schema
+                .scan_field(binary, &["age"], |v| total += v.read_u64().unwrap())
+                .unwrap();
+            total
+        })
+    });
+    group.finish();
+
+    let wide_row = Ty::Struct {
+        fields: (0..64).map(|i| Field::new(format!("field_{}", i), Ty::U64)).collect(),
+    };
+    let wide_schema = array_def!(wide_row.clone());
+    let wide_rows: Vec<_> = (0..100)
+        .map(|row| {
+            // Keys in reverse order, so each is looked up rather than found first
+            let object = (0..64)
+                .rev()
+                .map(|i| (format!("field_{}", i), serde_json::json!(row * i)));
+            serde_json::Value::Object(object.collect())
+        })
+        .collect();
+    let wide_json = serde_json::to_string(&wide_rows).unwrap();
+    let wide_row_json = serde_json::to_string(&wide_rows[0]).unwrap();
+    let wide_binary = fast::deserialize(&wide_schema, &wide_json).unwrap();
+
+    let mut group = c.benchmark_group("wide_struct");
+    group.bench_function("deserialize_fast", |b| {
+        b.iter(|| {
+            buffer.clear();
+            fast::deserialize_into(black_box(&wide_schema), black_box(&wide_json), &mut buffer)
+        })
+    });
+    let prepared = fast::PreparedSchema::new(wide_schema.clone());
+    let options = DeserializeOptions::default();
+    group.bench_function("deserialize_prepared", |b| {
+        b.iter(|| {
+            buffer.clear();
+            fast::deserialize_prepared(black_box(&prepared), black_box(&wide_json), &mut buffer, &options)
+        })
+    });
+    // A single row, as when a schema is used for just one document
+    group.bench_function("deserialize_fast_row", |b| {
+        b.iter(|| {
+            buffer.clear();
+            fast::deserialize_into(black_box(&wide_row), black_box(&wide_row_json), &mut buffer)
+        })
+    });
+    group.bench_function("serialize_full", |b| {
+        b.iter(|| {
+            let mut buffer = vec![];
+            let mut ser = serde_json::Serializer::new(&mut buffer);
+            fast::serialize(&mut ser, black_box(&wide_schema), black_box(&wide_binary)).unwrap();
+            buffer
+        })
+    });
+    // Only `field_40` of each row, skipping the fields before it
+    group.bench_function("serialize_projected", |b| {
+        b.iter(|| {
+            let mut buffer = vec![];
+            let mut ser = serde_json::Serializer::new(&mut buffer);
+            let mut seq = ser.serialize_seq(None).unwrap();
+            black_box(&wide_schema)
+                .scan_field(black_box(&wide_binary), &["field_40"], |v| {
+                    seq.serialize_element(&v.read_u64().unwrap()).unwrap()
+                })
+                .unwrap();
+            seq.end().unwrap();
+            buffer
+        })
+    });
+    group.finish();
+
+    let mut large_doc = doc.clone();
+    large_doc["hobbies"] = (0..10_000).map(|i| format!("Hobby number {}", i)).collect();
+    let large_json = serde_json::to_string(&large_doc).unwrap();
+
+    // A fresh buffer each time, so that growing it is part of the measurement
+    let mut group = c.benchmark_group("large_array");
+    group.bench_function("deserialize_slow", |b| {
+        b.iter(|| slow::deserialize_alloc(black_box(&schema), black_box(&large_doc)))
+    });
+    group.bench_function("deserialize_fast", |b| {
+        b.iter(|| fast::deserialize(black_box(&schema), black_box(&large_json)))
+    });
+    group.bench_function("deserialize_fast_value", |b| {
+        b.iter(|| {
+            let mut buffer = FlatbinBuf::new();
+            fast::deserialize_from(black_box(&schema), black_box(&large_doc), &mut buffer).map(|_| buffer)
+        })
+    });
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
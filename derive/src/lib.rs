@@ -0,0 +1,45 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Derives `Schema` for a struct with named fields, producing a `Ty::Struct` whose fields mirror the struct's.
+#[proc_macro_derive(Schema)]
+pub fn derive_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "Schema requires named fields")),
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Schema can only be derived for structs",
+            ))
+        }
+    };
+
+    let fields = fields.iter().map(|field| {
+        let name = field.ident.as_ref().unwrap().to_string();
+        let ty = &field.ty;
+        quote! {
+            ::talk_serde_dyn_schema::ty::Field::new(#name, <#ty as ::talk_serde_dyn_schema::ty::Schema>::schema())
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::talk_serde_dyn_schema::ty::Schema for #ident #ty_generics #where_clause {
+            fn schema() -> ::talk_serde_dyn_schema::ty::Ty {
+                ::talk_serde_dyn_schema::ty::Ty::Struct {
+                    fields: ::std::vec![#(#fields),*].into(),
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use talk_serde_dyn_schema::{array_def, struct_def, ty::Schema, ty::Ty, Schema};
+
+#[allow(dead_code)]
+#[derive(Schema)]
+struct Language {
+    name: String,
+    liked: bool,
+    experience: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Schema)]
+struct Person {
+    name: String,
+    age: u32,
+    hobbies: Vec<String>,
+    languages: Vec<Language>,
+}
+
+#[test]
+fn derive_matches_struct_def() {
+    let expected = struct_def!({
+        "name": Ty::String,
+        "age": Ty::U64,
+        "hobbies": array_def!(Ty::String),
+        "languages": array_def!(struct_def!({
+            "name": Ty::String,
+            "liked": Ty::Bool,
+            "experience": Ty::U64,
+        }))
+    });
+
+    assert_eq!(Person::schema(), expected);
+}
@@ -0,0 +1,404 @@
+//! Conversion between flatbin tables and Apache Arrow record batches, enabled by the `arrow` feature.
+//!
+//! A table is an array of structs (or a columnar array), whose fields become the columns of the batch.
+//! Arrays map to `List`, structs to `Struct`, maps to a `List` of `Struct`s of each key and value, enums without
+//! fields to the `Utf8` names of their variants, and the primitives to their obvious Arrow counterparts. Arrow
+//! columns may be nullable, but no `Ty` is, so batches containing nulls are rejected.
+
+use crate::{
+    flatbin::{self, Builder, Flatbin, FlatbinBuf, RleBuilder},
+    ty::{Field, Ty},
+};
+use arrow_array::{
+    cast::AsArray,
+    types::{Float64Type, Int64Type, UInt64Type},
+    Array, ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, ListArray, RecordBatch, RecordBatchOptions,
+    StringArray, StructArray, UInt64Array,
+};
+use arrow_buffer::OffsetBuffer;
+use arrow_schema::{ArrowError, DataType, Field as ArrowField, Fields, Schema};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("expected an array of structs or a columnar array")]
+    NotATable,
+    #[error("unsupported arrow type: {0}")]
+    UnsupportedType(DataType),
+    #[error("enums with fields are not supported")]
+    EnumWithFields,
+    #[error("null values are not supported")]
+    Null,
+    #[error(transparent)]
+    Arrow(#[from] ArrowError),
+    #[error(transparent)]
+    Flatbin(#[from] flatbin::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Converts a table of type `ty` into a record batch with one column per field.
+pub fn to_arrow(ty: &Ty, value: &Flatbin) -> Result<RecordBatch> {
+    let (fields, columns, rows) = match ty {
+        Ty::Array { inner } => {
+            let Ty::Struct { fields } = &**inner else {
+                return Err(Error::NotATable);
+            };
+            let rows = value.read_array()?.try_iter().collect::<flatbin::Result<Vec<_>>>()?;
+            let columns = struct_columns(fields, &rows)?;
+            (fields, columns, rows.len())
+        }
+        Ty::Columnar { fields } => {
+            let (rows, columns) = value.read_columns(fields.len())?;
+            let columns = fields
+                .iter()
+                .zip(columns)
+                .map(|(field, column)| to_array(&field.ty, &column.try_iter().collect::<flatbin::Result<Vec<_>>>()?))
+                .collect::<Result<Vec<_>>>()?;
+            (fields, columns, rows)
+        }
+        _ => return Err(Error::NotATable),
+    };
+    let schema = Arc::new(Schema::new(arrow_fields(fields)));
+    let options = RecordBatchOptions::new().with_row_count(Some(rows));
+    Ok(RecordBatch::try_new_with_options(schema, columns, &options)?)
+}
+
+/// Converts a record batch into an array of structs, returning its type and its encoding.
+pub fn from_arrow(batch: &RecordBatch) -> Result<(Ty, FlatbinBuf)> {
+    let fields: Box<[Field]> = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field_from_arrow(field))
+        .collect::<Result<_>>()?;
+
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = Builder::new(&mut buffer).start_vector();
+    for index in 0..batch.num_rows() {
+        let mut tuple = vector.as_builder().start_tuple();
+        for (field, column) in fields.iter().zip(batch.columns()) {
+            write_value(&field.ty, column, index, tuple.as_builder())?;
+        }
+        tuple.end_struct(&fields);
+    }
+    vector.end();
+
+    let ty = Ty::Array {
+        inner: Ty::Struct { fields }.into(),
+    };
+    Ok((ty, buffer))
+}
+
+fn data_type(ty: &Ty) -> DataType {
+    match ty {
+        Ty::Bool => DataType::Boolean,
+        Ty::U64 => DataType::UInt64,
+        Ty::I64 => DataType::Int64,
+        Ty::F64 => DataType::Float64,
+        Ty::FloatVec => DataType::List(list_item(&Ty::F64)),
+        Ty::Bytes | Ty::ChunkedBytes { .. } => DataType::Binary,
+        // Only enums without fields can be converted, to the names of their variants
+        Ty::String | Ty::Enum { .. } => DataType::Utf8,
+        Ty::Array { inner } | Ty::RleArray { inner } => DataType::List(list_item(inner)),
+        Ty::Map { value, .. } => data_type(&map_entries(value)),
+        Ty::Struct { fields } => DataType::Struct(arrow_fields(fields)),
+        Ty::Columnar { fields } => DataType::List(Arc::new(ArrowField::new(
+            "item",
+            DataType::Struct(arrow_fields(fields)),
+            false,
+        ))),
+    }
+}
+
+/// Returns the type of a map's entries as an array of structs, which shares its encoding.
+fn map_entries(value: &Ty) -> Ty {
+    Ty::Array {
+        inner: Ty::Struct {
+            fields: [Field::new("key", Ty::String), Field::new("value", value.clone())].into(),
+        }
+        .into(),
+    }
+}
+
+fn list_item(inner: &Ty) -> Arc<ArrowField> {
+    Arc::new(ArrowField::new("item", data_type(inner), false))
+}
+
+fn arrow_fields(fields: &[Field]) -> Fields {
+    fields
+        .iter()
+        .map(|field| ArrowField::new(&*field.name, data_type(&field.ty), false))
+        .collect()
+}
+
+fn field_from_arrow(field: &ArrowField) -> Result<Field> {
+    Ok(Field::new(field.name().as_str(), ty_from_arrow(field.data_type())?))
+}
+
+fn ty_from_arrow(data_type: &DataType) -> Result<Ty> {
+    Ok(match data_type {
+        DataType::Boolean => Ty::Bool,
+        DataType::UInt64 => Ty::U64,
+        DataType::Int64 => Ty::I64,
+        DataType::Float64 => Ty::F64,
+        DataType::Binary => Ty::Bytes,
+        DataType::Utf8 => Ty::String,
+        DataType::List(item) => Ty::Array {
+            inner: ty_from_arrow(item.data_type())?.into(),
+        },
+        DataType::Struct(fields) => Ty::Struct {
+            fields: fields
+                .iter()
+                .map(|field| field_from_arrow(field))
+                .collect::<Result<_>>()?,
+        },
+        data_type => return Err(Error::UnsupportedType(data_type.clone())),
+    })
+}
+
+/// Builds an arrow array holding `values`, each of which is of type `ty`.
+fn to_array(ty: &Ty, values: &[&Flatbin]) -> Result<ArrayRef> {
+    Ok(match ty {
+        Ty::Bool => Arc::new(BooleanArray::from(read_all(values, Flatbin::read_bool)?)),
+        Ty::U64 => Arc::new(UInt64Array::from(read_all(values, Flatbin::read_u64)?)),
+        Ty::I64 => Arc::new(Int64Array::from(read_all(values, Flatbin::read_i64)?)),
+        Ty::F64 => Arc::new(Float64Array::from(read_all(values, Flatbin::read_f64)?)),
+        Ty::FloatVec => {
+            let arrays = read_all(values, |value| Ok(value.read_float_vec()?.collect::<Vec<_>>()))?;
+            let offsets = OffsetBuffer::from_lengths(arrays.iter().map(Vec::len));
+            Arc::new(ListArray::try_new(
+                list_item(&Ty::F64),
+                offsets,
+                Arc::new(Float64Array::from(arrays.concat())),
+                None,
+            )?)
+        }
+        Ty::Bytes => Arc::new(BinaryArray::from_vec(read_all(values, Flatbin::read_bytes)?)),
+        Ty::ChunkedBytes { .. } => Arc::new(BinaryArray::from_iter_values(read_all(
+            values,
+            Flatbin::read_chunked_bytes,
+        )?)),
+        Ty::String => Arc::new(StringArray::from(read_all(values, Flatbin::read_str)?)),
+        Ty::Array { inner } => {
+            let arrays = read_all(values, Flatbin::read_array)?;
+            let elements = arrays
+                .iter()
+                .flat_map(|array| array.try_iter())
+                .collect::<flatbin::Result<Vec<_>>>()?;
+            let offsets = OffsetBuffer::from_lengths(arrays.iter().map(|array| array.len()));
+            Arc::new(ListArray::try_new(
+                list_item(inner),
+                offsets,
+                to_array(inner, &elements)?,
+                None,
+            )?)
+        }
+        Ty::RleArray { inner } => {
+            let arrays = read_all(values, Flatbin::read_runs)?
+                .into_iter()
+                .map(|runs| {
+                    runs.into_iter()
+                        .flat_map(|(count, value)| (0..count).map(move |_| value))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let offsets = OffsetBuffer::from_lengths(arrays.iter().map(Vec::len));
+            Arc::new(ListArray::try_new(
+                list_item(inner),
+                offsets,
+                to_array(inner, &arrays.concat())?,
+                None,
+            )?)
+        }
+        Ty::Map { value, .. } => to_array(&map_entries(value), values)?,
+        Ty::Struct { fields } => Arc::new(struct_array(fields, struct_columns(fields, values)?, values.len())?),
+        Ty::Enum { variants, .. } => {
+            if variants.iter().any(|variant| !variant.fields.is_empty()) {
+                return Err(Error::EnumWithFields);
+            }
+            let names = values
+                .iter()
+                .map(|value| Ok(&*variants[value.read_variant(variants)?.0].name))
+                .collect::<flatbin::Result<Vec<_>>>()?;
+            Arc::new(StringArray::from(names))
+        }
+        Ty::Columnar { fields } => {
+            // Reassemble the rows of each value, so they can be stored like an array of structs
+            let mut lengths = vec![];
+            let mut rows = vec![];
+            for value in values {
+                let (count, columns) = value.read_columns(fields.len())?;
+                let mut columns: Vec<_> = columns.iter().map(|column| column.iter()).collect();
+                for _ in 0..count {
+                    let row = columns
+                        .iter_mut()
+                        .map(|column| column.next().ok_or(flatbin::Error::UnexpectedEOF));
+                    rows.push(row.collect::<flatbin::Result<Vec<_>>>()?);
+                }
+                lengths.push(count);
+            }
+            let columns = (0..fields.len())
+                .map(|i| rows.iter().map(|row| row[i]).collect::<Vec<_>>())
+                .zip(fields.iter())
+                .map(|(column, field)| to_array(&field.ty, &column))
+                .collect::<Result<Vec<_>>>()?;
+            let item = struct_array(fields, columns, rows.len())?;
+            let item_field = Arc::new(ArrowField::new("item", item.data_type().clone(), false));
+            let offsets = OffsetBuffer::from_lengths(lengths);
+            Arc::new(ListArray::try_new(item_field, offsets, Arc::new(item), None)?)
+        }
+    })
+}
+
+fn struct_array(fields: &[Field], columns: Vec<ArrayRef>, len: usize) -> Result<StructArray> {
+    // Without any columns, the length of the array must be given explicitly
+    match fields.is_empty() {
+        true => Ok(StructArray::new_empty_fields(len, None)),
+        false => Ok(StructArray::try_new(arrow_fields(fields), columns, None)?),
+    }
+}
+
+/// Builds one arrow array per field, from a slice of structs.
+fn struct_columns(fields: &[Field], values: &[&Flatbin]) -> Result<Vec<ArrayRef>> {
+    let tuples = values
+        .iter()
+        .map(|value| Ok(value.read_struct(fields)?.iter().collect::<Vec<_>>()))
+        .collect::<Result<Vec<_>>>()?;
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| to_array(&field.ty, &tuples.iter().map(|tuple| tuple[i]).collect::<Vec<_>>()))
+        .collect()
+}
+
+fn read_all<'a, T>(values: &[&'a Flatbin], read: fn(&'a Flatbin) -> flatbin::Result<T>) -> Result<Vec<T>> {
+    Ok(values.iter().map(|value| read(value)).collect::<flatbin::Result<_>>()?)
+}
+
+/// Writes the value at `index` in `array`, which has type `ty`.
+fn write_value(ty: &Ty, array: &dyn Array, index: usize, builder: Builder) -> Result<()> {
+    if array.is_null(index) {
+        return Err(Error::Null);
+    }
+    match ty {
+        Ty::Bool => builder.write_bool(array.as_boolean().value(index)),
+        Ty::U64 => builder.write_u64(array.as_primitive::<UInt64Type>().value(index)),
+        Ty::I64 => builder.write_i64(array.as_primitive::<Int64Type>().value(index)),
+        Ty::F64 => builder.write_f64(array.as_primitive::<Float64Type>().value(index)),
+        Ty::FloatVec => {
+            let elements = array.as_list::<i32>().value(index);
+            if elements.null_count() > 0 {
+                return Err(Error::Null);
+            }
+            builder.write_float_vec(elements.as_primitive::<Float64Type>().values());
+        }
+        Ty::Bytes => builder.write_bytes(array.as_binary::<i32>().value(index)),
+        Ty::ChunkedBytes { chunk_size } => {
+            builder.write_chunked_bytes(array.as_binary::<i32>().value(index), *chunk_size)
+        }
+        Ty::String => builder.write_str(array.as_string::<i32>().value(index)),
+        Ty::Array { inner } => {
+            let elements = array.as_list::<i32>().value(index);
+            let mut vector = builder.start_vector();
+            for index in 0..elements.len() {
+                write_value(inner, &elements, index, vector.as_builder())?;
+            }
+            vector.end();
+        }
+        Ty::RleArray { inner } => {
+            let elements = array.as_list::<i32>().value(index);
+            if elements.len() > flatbin::MAX_RLE_LEN {
+                return Err(flatbin::Error::RleTooLong.into());
+            }
+            let mut runs = RleBuilder::new(builder);
+            for index in 0..elements.len() {
+                write_value(inner, &elements, index, runs.as_builder())?;
+                runs.push();
+            }
+            runs.end();
+        }
+        Ty::Struct { fields } => {
+            let array = array.as_struct();
+            let mut tuple = builder.start_tuple();
+            for (field, column) in fields.iter().zip(array.columns()) {
+                write_value(&field.ty, column, index, tuple.as_builder())?;
+            }
+            tuple.end_struct(fields);
+        }
+        // Record batches are only ever converted to arrays of structs
+        Ty::Columnar { .. } | Ty::Enum { .. } | Ty::Map { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_arrow, to_arrow, Error};
+    use crate::{
+        array_def,
+        flatbin::{self, Flatbin},
+        slow, struct_def,
+        ty::Ty,
+    };
+
+    #[test]
+    fn roundtrip() {
+        let ty = array_def!(struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "balance": Ty::I64,
+            "height": Ty::F64,
+            "avatar": Ty::Bytes,
+            "rustacean": Ty::Bool,
+            "languages": array_def!(struct_def!({ "name": Ty::String, "experience": Ty::U64 })),
+        }));
+        let value = serde_json::json!([
+            {
+                "name": "Alice", "age": 31, "balance": -20, "height": 1.5, "avatar": [1, 2], "rustacean": true,
+                "languages": [{ "name": "Rust", "experience": 5 }, { "name": "C", "experience": 9 }]
+            },
+            {
+                "name": "Bob", "age": 27, "balance": 400, "height": 1.75, "avatar": [], "rustacean": false,
+                "languages": []
+            }
+        ]);
+        let bytes = slow::deserialize_alloc(&ty, &value).unwrap();
+
+        let batch = to_arrow(&ty, &bytes).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 7);
+
+        let (arrow_ty, arrow_bytes) = from_arrow(&batch).unwrap();
+        assert_eq!(arrow_ty, ty);
+        assert_eq!(arrow_bytes.as_bytes(), bytes.as_bytes());
+
+        // Columnar tables convert to the same batch
+        let Ty::Array { inner } = &ty else { unreachable!() };
+        let Ty::Struct { fields } = &**inner else {
+            unreachable!()
+        };
+        let columnar = Ty::Columnar { fields: fields.clone() };
+        let columnar_bytes = slow::deserialize_alloc(&columnar, &value).unwrap();
+        assert_eq!(to_arrow(&columnar, &columnar_bytes).unwrap(), batch);
+    }
+
+    #[test]
+    fn huge_count() {
+        // A count far beyond the size of the data fails rather than being trusted
+        let huge_count = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        let table = array_def!(struct_def!({ "a": Ty::U64 }));
+        assert!(matches!(
+            to_arrow(&table, huge_count),
+            Err(Error::Flatbin(flatbin::Error::MissingElements { .. }))
+        ));
+        let nested = array_def!(struct_def!({ "a": array_def!(Ty::U64) }));
+        // One row, whose elided field is an array with a huge count
+        let huge_inner = Flatbin::from_bytes(&[1, 0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        assert!(matches!(
+            to_arrow(&nested, huge_inner),
+            Err(Error::Flatbin(flatbin::Error::MissingElements { .. }))
+        ));
+    }
+}
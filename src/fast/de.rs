@@ -1,11 +1,14 @@
+use super::index::FieldIndex;
 use crate::{
-    flatbin::{Builder, Flatbin, FlatbinBuf},
-    ty::{Field, Ty},
+    flatbin::{canonical_f64, Builder, Flatbin, FlatbinBuf, RleBuilder, TupleBuilder},
+    options::{Budget, BytesEncoding, DeserializeOptions},
+    ty::{Field, MapLayout, Ty, Variant},
 };
 use serde::{
-    de::{DeserializeSeed, MapAccess, SeqAccess, Visitor},
-    Deserializer,
+    de::{DeserializeSeed, Expected, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
 };
+use std::{borrow::Cow, collections::HashSet, num::NonZeroUsize, str::FromStr};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,6 +19,16 @@ pub enum Error {
     NotAByte,
     #[error("missing field: {name}")]
     MissingField { name: Box<str> },
+    #[error(transparent)]
+    Flatbin(#[from] crate::flatbin::Error),
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
 }
 
 // pub type Result<T> = std::result::Result<T, Error>;
@@ -27,56 +40,294 @@ pub fn deserialize(ty: &Ty, value: &str) -> serde_json::Result<FlatbinBuf> {
 }
 
 pub fn deserialize_into(ty: &Ty, value: &str, buffer: &mut FlatbinBuf) -> serde_json::Result<()> {
+    deserialize_into_with(ty, value, buffer, &DeserializeOptions::default())
+}
+
+pub fn deserialize_into_with(
+    ty: &Ty,
+    value: &str,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+) -> serde_json::Result<()> {
     let mut de = serde_json::Deserializer::from_str(value);
-    let builder = Builder::new(buffer);
-    TypedBuilder { ty, builder }.deserialize(&mut de)?;
-    Ok(())
+    deserialize_from_with(ty, &mut de, buffer, options)
 }
 
-struct TypedBuilder<'a> {
-    pub ty: &'a Ty,
-    pub builder: Builder<'a>,
+/// Deserializes JSON text held in a byte slice, returning the encoded flatbin bytes.
+///
+/// Unlike [`deserialize`], trailing characters after the JSON value are rejected.
+pub fn deserialize_bytes(ty: &Ty, json: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let mut buffer = FlatbinBuf::new();
+    let mut de = serde_json::Deserializer::from_slice(json);
+    deserialize_from(ty, &mut de, &mut buffer)?;
+    de.end()?;
+    Ok(buffer.into_vec())
+}
+
+/// Deserializes a value of type `ty` from any serde `Deserializer`, appending the encoded bytes to `buffer`.
+pub fn deserialize_from<'de, D: Deserializer<'de>>(
+    ty: &Ty,
+    deserializer: D,
+    buffer: &mut FlatbinBuf,
+) -> Result<(), D::Error> {
+    deserialize_from_with(ty, deserializer, buffer, &DeserializeOptions::default())
+}
+
+pub fn deserialize_from_with<'de, D: Deserializer<'de>>(
+    ty: &Ty,
+    deserializer: D,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+) -> Result<(), D::Error> {
+    DeserializeInto::new(ty, Builder::new(buffer), options).deserialize(deserializer)
+}
+
+pub fn deserialize_into_budgeted(
+    ty: &Ty,
+    value: &str,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+    budget: &Budget,
+) -> serde_json::Result<()> {
+    let mut de = serde_json::Deserializer::from_str(value);
+    deserialize_from_budgeted(ty, &mut de, buffer, options, budget)
+}
+
+pub fn deserialize_from_budgeted<'de, D: Deserializer<'de>>(
+    ty: &Ty,
+    deserializer: D,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+    budget: &Budget,
+) -> Result<(), D::Error> {
+    DeserializeInto::new(ty, Builder::new(buffer), options)
+        .with_budget(budget)
+        .deserialize(deserializer)
+}
+
+/// A `DeserializeSeed` which deserializes a single value of type `ty` into `builder`.
+///
+/// This allows schema-typed values to be decoded from within other serde code, e.g. via
+/// `SeqAccess::next_element_seed`. The seed holds the `Builder` (and through it, a mutable borrow of the
+/// parent buffer or `VectorBuilder`/`TupleBuilder`) for `'a`, so a fresh seed must be created from
+/// `as_builder()` for every value, and the parent is only usable again once the seed has been consumed.
+///
+/// No schema type is nullable, so every visitor rejects `null` (which deserializers report through
+/// `visit_unit` or `visit_none`) with serde's standard "invalid type" error.
+pub struct DeserializeInto<'a> {
+    ty: &'a Ty,
+    builder: Builder<'a>,
+    ctx: Context<'a>,
+}
+
+/// The state shared by every value in a document.
+#[derive(Clone, Copy)]
+struct Context<'a> {
+    options: &'a DeserializeOptions,
+    budget: Option<&'a Budget>,
+    index: Option<&'a FieldIndex>,
+    depth: usize,
+}
+
+impl<'a> DeserializeInto<'a> {
+    pub fn new(ty: &'a Ty, builder: Builder<'a>, options: &'a DeserializeOptions) -> Self {
+        let ctx = Context {
+            options,
+            budget: None,
+            index: None,
+            depth: 0,
+        };
+        Self { ty, builder, ctx }
+    }
+
+    /// Spends `budget` on the values written by this seed.
+    pub fn with_budget(mut self, budget: &'a Budget) -> Self {
+        self.ctx.budget = Some(budget);
+        self
+    }
+
+    /// Looks up the fields of wide structs in `index`, which must have been built from this seed's `ty`.
+    pub(crate) fn with_index(mut self, index: &'a FieldIndex) -> Self {
+        self.ctx.index = Some(index);
+        self
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Finds the position of the field matching `key`.
+    fn find_field(&self, fields: &[Field], key: &str) -> Option<usize> {
+        if self.options.case_insensitive {
+            return fields.iter().position(|f| f.matches_key_ignore_case(key));
+        }
+        match self.index.and_then(|index| index.find(fields, key)) {
+            Some(position) => position,
+            None => fields.iter().position(|f| f.matches_key(key)),
+        }
+    }
+
+    /// Parses a number given as a string, if [`DeserializeOptions::accept_stringified_numbers`] is set.
+    fn parse_stringified<T: FromStr, E: serde::de::Error>(&self, value: &str, exp: &dyn Expected) -> Result<T, E> {
+        let unexpected = serde::de::Unexpected::Str(value);
+        if !self.options.accept_stringified_numbers {
+            return Err(E::invalid_type(unexpected, exp));
+        }
+        value.parse().map_err(|_| E::invalid_value(unexpected, exp))
+    }
+
+    /// Checks `len` against [`DeserializeOptions::max_array_len`].
+    fn check_array_len<E: serde::de::Error>(&self, len: usize) -> Result<(), E> {
+        match self.options.max_array_len {
+            Some(max) if len > max => Err(E::custom(format!("array exceeds maximum length of {}", max))),
+            _ => Ok(()),
+        }
+    }
+
+    fn spend_node<E: serde::de::Error>(&self) -> Result<(), E> {
+        match self.budget {
+            Some(budget) => budget.spend_node().map_err(E::custom),
+            None => Ok(()),
+        }
+    }
+
+    fn spend_bytes<E: serde::de::Error>(&self, count: usize) -> Result<(), E> {
+        match self.budget {
+            Some(budget) => budget.spend_bytes(count).map_err(E::custom),
+            None => Ok(()),
+        }
+    }
 }
 
-impl<'de, 'a> DeserializeSeed<'de> for TypedBuilder<'a> {
+impl<'de, 'a> DeserializeSeed<'de> for DeserializeInto<'a> {
     type Value = ();
 
     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
-        let TypedBuilder { ty, builder } = self;
+        let DeserializeInto { ty, builder, mut ctx } = self;
+        if matches!(
+            ty,
+            Ty::Array { .. }
+                | Ty::RleArray { .. }
+                | Ty::Map { .. }
+                | Ty::Struct { .. }
+                | Ty::Columnar { .. }
+                | Ty::Enum { .. }
+        ) && ctx.depth >= ctx.options.max_depth
+        {
+            return Err(serde::de::Error::custom(DEPTH_EXCEEDED));
+        }
+        ctx.spend_node()?;
+        ctx.depth += 1;
         match ty {
-            Ty::Bool => deserializer.deserialize_bool(BoolVisitor { builder }),
-            Ty::U64 => deserializer.deserialize_u64(UIntVisitor { builder }),
-            Ty::I64 => deserializer.deserialize_i64(IntVisitor { builder }),
-            Ty::F64 => deserializer.deserialize_f64(FloatVisitor { builder }),
-            Ty::Bytes => deserializer.deserialize_bytes(BytesVisitor { builder }),
-            Ty::String => deserializer.deserialize_str(StringVisitor { builder }),
-            Ty::Array { inner } => deserializer.deserialize_seq(ArrayVisitor { inner, builder }),
-            Ty::Struct { fields } => deserializer.deserialize_map(StructVisitor { fields, builder }),
+            Ty::Bool => match ctx.options.bools_as_ints || ctx.options.lenient_bools {
+                true => deserializer.deserialize_any(BoolVisitor { builder, ctx }),
+                false => deserializer.deserialize_bool(BoolVisitor { builder, ctx }),
+            },
+            Ty::U64 => match ctx.options.accept_stringified_numbers {
+                true => deserializer.deserialize_any(UIntVisitor { builder, ctx }),
+                false => deserializer.deserialize_u64(UIntVisitor { builder, ctx }),
+            },
+            Ty::I64 => match ctx.options.accept_stringified_numbers {
+                true => deserializer.deserialize_any(IntVisitor { builder, ctx }),
+                false => deserializer.deserialize_i64(IntVisitor { builder, ctx }),
+            },
+            Ty::F64 => {
+                let value = FloatVisitor { ctx }.deserialize(deserializer)?;
+                match ctx.options.canonical_floats {
+                    true => builder.write_f64_canonical(value),
+                    false => builder.write_f64(value),
+                }
+                Ok(())
+            }
+            Ty::FloatVec => deserializer.deserialize_seq(FloatVecVisitor { builder, ctx }),
+            Ty::Bytes => BytesVisitor::deserialize(deserializer, builder, None, ctx),
+            Ty::ChunkedBytes { chunk_size } => BytesVisitor::deserialize(deserializer, builder, Some(*chunk_size), ctx),
+            Ty::String => deserializer.deserialize_str(StringVisitor { builder, ctx }),
+            Ty::Array { inner } => deserializer.deserialize_seq(ArrayVisitor { inner, builder, ctx }),
+            Ty::RleArray { inner } => deserializer.deserialize_seq(RleArrayVisitor { inner, builder, ctx }),
+            Ty::Map { value, layout } => deserializer.deserialize_map(MapVisitor {
+                value,
+                layout: *layout,
+                builder,
+                ctx,
+            }),
+            Ty::Struct { fields } => match Field::only_field(fields).filter(|_| ctx.options.unwrap_single_fields) {
+                Some(field) => DeserializeInto {
+                    ty: &field.ty,
+                    builder,
+                    ctx,
+                }
+                .deserialize(deserializer),
+                None => StructVisitor { fields, builder, ctx }.deserialize(deserializer),
+            },
+            Ty::Enum { variants, tag: None } => deserializer.deserialize_any(EnumVisitor { variants, builder, ctx }),
+            Ty::Enum {
+                variants,
+                tag: Some(tag),
+            } => deserializer.deserialize_map(TaggedEnumVisitor {
+                variants,
+                tag,
+                builder,
+                ctx,
+            }),
+            Ty::Columnar { fields } => deserializer.deserialize_seq(ColumnarVisitor { fields, builder, ctx }),
         }
     }
 }
 
 struct BoolVisitor<'a> {
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
 }
 
 impl<'a, 'de> Visitor<'de> for BoolVisitor<'a> {
     type Value = ();
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "a boolean")
+        write!(formatter, "a boolean")?;
+        if self.ctx.options.bools_as_ints {
+            write!(formatter, ", 0 or 1")?;
+        }
+        if self.ctx.options.lenient_bools {
+            write!(formatter, ", or \"true\" or \"false\" as a string")?;
+        }
+        Ok(())
     }
 
     fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<(), E> {
         self.builder.write_bool(value);
         Ok(())
     }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<(), E> {
+        match (self.ctx.options.bools_as_ints, value) {
+            (true, 0 | 1) => self.visit_bool(value == 1),
+            (true, _) => Err(E::invalid_value(serde::de::Unexpected::Unsigned(value), &self)),
+            (false, _) => Err(E::invalid_type(serde::de::Unexpected::Unsigned(value), &self)),
+        }
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<(), E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::invalid_type(serde::de::Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        match (self.ctx.options.lenient_bools, value) {
+            (true, "true" | "1") => self.visit_bool(true),
+            (true, "false" | "0") => self.visit_bool(false),
+            (true, _) => Err(E::invalid_value(serde::de::Unexpected::Str(value), &self)),
+            (false, _) => Err(E::invalid_type(serde::de::Unexpected::Str(value), &self)),
+        }
+    }
 }
 
 const OUT_OF_RANGE: &str = "value is outside numeric range for type";
+const DEPTH_EXCEEDED: &str = "maximum nesting depth exceeded";
 
 struct UIntVisitor<'a> {
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
 }
 
 impl<'a, 'de> Visitor<'de> for UIntVisitor<'a> {
@@ -96,10 +347,16 @@ impl<'a, 'de> Visitor<'de> for UIntVisitor<'a> {
         self.builder.write_u64(value);
         Ok(())
     }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        let value = self.ctx.parse_stringified(value, &self)?;
+        self.visit_u64(value)
+    }
 }
 
 struct IntVisitor<'a> {
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
 }
 
 impl<'a, 'de> Visitor<'de> for IntVisitor<'a> {
@@ -119,62 +376,187 @@ impl<'a, 'de> Visitor<'de> for IntVisitor<'a> {
         self.builder.write_i64(value);
         Ok(())
     }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        let value = self.ctx.parse_stringified(value, &self)?;
+        self.visit_i64(value)
+    }
 }
 
+#[derive(Clone, Copy)]
 struct FloatVisitor<'a> {
-    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for FloatVisitor<'a> {
+    type Value = f64;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<f64, D::Error> {
+        match self.ctx.options.accept_stringified_numbers {
+            true => deserializer.deserialize_any(self),
+            false => deserializer.deserialize_f64(self),
+        }
+    }
 }
 
 impl<'a, 'de> Visitor<'de> for FloatVisitor<'a> {
-    type Value = ();
+    type Value = f64;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(formatter, "a number")
     }
 
-    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<(), E> {
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<f64, E> {
         let value = value as f64;
         if value.is_infinite() {
             return Err(E::custom(OUT_OF_RANGE));
         }
-        self.builder.write_f64(value);
-        Ok(())
+        self.visit_f64(value)
     }
 
-    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<(), E> {
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<f64, E> {
         let value = value as f64;
         if value.is_infinite() {
             return Err(E::custom(OUT_OF_RANGE));
         }
-        self.builder.write_f64(value);
-        Ok(())
+        self.visit_f64(value)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<f64, E> {
+        Ok(value)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<f64, E> {
+        match self.ctx.parse_stringified::<f64, E>(value, &self)? {
+            number if number.is_finite() => self.visit_f64(number),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+struct FloatVecVisitor<'a> {
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> Visitor<'de> for FloatVecVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an array of numbers")
     }
 
-    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<(), E> {
-        self.builder.write_f64(value);
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element_seed(FloatVisitor { ctx: self.ctx })? {
+            self.ctx.spend_bytes(8)?;
+            values.push(match self.ctx.options.canonical_floats {
+                true => canonical_f64(value),
+                false => value,
+            });
+            if let Some(max) = self.ctx.options.max_array_len {
+                if values.len() > max {
+                    let msg = format!("array exceeds maximum length of {}", max);
+                    return Err(serde::de::Error::custom(msg));
+                }
+            }
+        }
+        self.builder.write_float_vec(&values);
         Ok(())
     }
 }
 
 struct BytesVisitor<'a> {
     pub builder: Builder<'a>,
+    pub chunk_size: Option<NonZeroUsize>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a> BytesVisitor<'a> {
+    fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        builder: Builder<'a>,
+        chunk_size: Option<NonZeroUsize>,
+        ctx: Context<'a>,
+    ) -> Result<(), D::Error> {
+        let visitor = BytesVisitor {
+            builder,
+            chunk_size,
+            ctx,
+        };
+        match ctx.options.bytes_encoding {
+            // Asking for a sequence rather than bytes, as JSON would otherwise hand over a string's bytes
+            BytesEncoding::NumberArray => deserializer.deserialize_seq(visitor),
+            _ => deserializer.deserialize_str(visitor),
+        }
+    }
+
+    fn write(self, bytes: &[u8]) {
+        match self.chunk_size {
+            Some(chunk_size) => self.builder.write_chunked_bytes(bytes, chunk_size),
+            None => self.builder.write_bytes(bytes),
+        }
+    }
+
+    fn check_len<E: serde::de::Error>(&self, len: usize) -> Result<(), E> {
+        match self.ctx.options.max_bytes_len {
+            Some(max) if len > max => Err(E::custom(format!("byte array exceeds maximum length of {}", max))),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<'a, 'de> Visitor<'de> for BytesVisitor<'a> {
     type Value = ();
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "a byte array")
+        match self.ctx.options.bytes_encoding {
+            BytesEncoding::NumberArray => write!(formatter, "a byte array"),
+            encoding => write!(formatter, "a {} string", encoding),
+        }
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        let encoding = self.ctx.options.bytes_encoding;
+        if encoding == BytesEncoding::NumberArray {
+            return Err(E::invalid_type(serde::de::Unexpected::Str(value), &self));
+        }
+        match encoding.decode(value) {
+            Some(bytes) => self.visit_byte_buf(bytes),
+            None => Err(E::invalid_value(serde::de::Unexpected::Str(value), &self)),
+        }
     }
 
     fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> Result<(), E> {
-        self.builder.write_bytes(value);
+        self.check_len(value.len())?;
+        self.ctx.spend_bytes(value.len())?;
+        self.write(value);
+        Ok(())
+    }
+
+    fn visit_borrowed_bytes<E: serde::de::Error>(self, value: &'de [u8]) -> Result<(), E> {
+        self.visit_bytes(value)
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, value: Vec<u8>) -> Result<(), E> {
+        self.visit_bytes(&value)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            self.check_len(bytes.len() + 1)?;
+            self.ctx.spend_bytes(1)?;
+            bytes.push(byte);
+        }
+        self.write(&bytes);
         Ok(())
     }
 }
 
 struct StringVisitor<'a> {
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
 }
 
 impl<'a, 'de> Visitor<'de> for StringVisitor<'a> {
@@ -185,14 +567,24 @@ impl<'a, 'de> Visitor<'de> for StringVisitor<'a> {
     }
 
     fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        self.ctx.spend_bytes(value.len())?;
         self.builder.write_str(value);
         Ok(())
     }
+
+    fn visit_borrowed_str<E: serde::de::Error>(self, value: &'de str) -> Result<(), E> {
+        self.visit_str(value)
+    }
+
+    fn visit_string<E: serde::de::Error>(self, value: String) -> Result<(), E> {
+        self.visit_str(&value)
+    }
 }
 
 struct ArrayVisitor<'a> {
     pub inner: &'a Ty,
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
 }
 
 impl<'a, 'de> Visitor<'de> for ArrayVisitor<'a> {
@@ -204,14 +596,149 @@ impl<'a, 'de> Visitor<'de> for ArrayVisitor<'a> {
 
     fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
         let mut vector = self.builder.start_vector();
-        loop {
-            let ctx = TypedBuilder {
+        if let Some(len) = seq.size_hint() {
+            vector.reserve(self.inner.array_reservation(len));
+        }
+        for index in 0.. {
+            let seed = DeserializeInto {
                 ty: self.inner,
                 builder: vector.as_builder(),
+                ctx: self.ctx,
             };
-            if seq.next_element_seed(ctx)?.is_none() {
+            let element = seq
+                .next_element_seed(seed)
+                .map_err(|err| match self.ctx.options.strict_arrays {
+                    true => {
+                        let msg = format!("array element {} does not match the element type: {}", index, err);
+                        serde::de::Error::custom(msg)
+                    }
+                    false => err,
+                })?;
+            if element.is_none() {
                 break;
             }
+            if let Some(max) = self.ctx.options.max_array_len {
+                if vector.count() > max {
+                    let msg = format!("array exceeds maximum length of {}", max);
+                    return Err(serde::de::Error::custom(msg));
+                }
+            }
+        }
+        vector.end();
+        Ok(())
+    }
+}
+
+struct RleArrayVisitor<'a> {
+    pub inner: &'a Ty,
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> Visitor<'de> for RleArrayVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut runs = RleBuilder::new(self.builder);
+        loop {
+            let seed = DeserializeInto {
+                ty: self.inner,
+                builder: runs.as_builder(),
+                ctx: self.ctx,
+            };
+            if seq.next_element_seed(seed)?.is_none() {
+                break;
+            }
+            runs.push();
+            let max = self.ctx.options.max_rle_len();
+            if runs.count() > max as u64 {
+                let msg = format!("array exceeds maximum length of {}", max);
+                return Err(serde::de::Error::custom(msg));
+            }
+        }
+        runs.end();
+        Ok(())
+    }
+}
+
+/// Deserializes a map. With the insertion layout each entry is written straight into the array, while with the
+/// ordered layout the entries are buffered, and copied into the array once sorted by key.
+struct MapVisitor<'a> {
+    pub value: &'a Ty,
+    pub layout: MapLayout,
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> Visitor<'de> for MapVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let ctx = Context {
+            depth: self.ctx.depth + 1,
+            ..self.ctx
+        };
+        let duplicate = |key: &str| serde::de::Error::custom(format!("duplicate key \"{}\"", key));
+
+        if self.layout == MapLayout::Insertion {
+            let mut keys = HashSet::new();
+            let mut vector = self.builder.start_vector();
+            while let Some(key) = map.next_key::<String>()? {
+                if keys.contains(&key) {
+                    return Err(duplicate(&key));
+                }
+                ctx.spend_node()?;
+                ctx.spend_bytes(key.len())?;
+                let mut entry = vector.as_builder().start_tuple();
+                entry.as_builder().write_str(&key);
+                let seed = DeserializeInto {
+                    ty: self.value,
+                    builder: entry.as_builder(),
+                    ctx,
+                };
+                map.next_value_seed(seed)?;
+                entry.end();
+                keys.insert(key);
+                ctx.check_array_len(vector.count())?;
+            }
+            vector.end();
+            return Ok(());
+        }
+
+        let mut buffer = FlatbinBuf::new();
+        let mut entries = vec![];
+        while let Some(key) = map.next_key::<String>()? {
+            ctx.spend_node()?;
+            ctx.spend_bytes(key.len())?;
+            let start = buffer.len();
+            let seed = DeserializeInto {
+                ty: self.value,
+                builder: Builder::new(&mut buffer),
+                ctx,
+            };
+            map.next_value_seed(seed)?;
+            entries.push((key, start..buffer.len()));
+            ctx.check_array_len(entries.len())?;
+        }
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(duplicate(&pair[0].0));
+        }
+
+        let mut vector = self.builder.start_vector();
+        for (key, range) in entries {
+            let mut entry = vector.as_builder().start_tuple();
+            entry.as_builder().write_str(&key);
+            entry.as_builder().copy(Flatbin::from_bytes(&buffer.as_bytes()[range]));
+            entry.end();
         }
         vector.end();
         Ok(())
@@ -221,53 +748,430 @@ impl<'a, 'de> Visitor<'de> for ArrayVisitor<'a> {
 struct StructVisitor<'a> {
     pub fields: &'a [Field],
     pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for StructVisitor<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.ctx.options.struct_as_array {
+            true => deserializer.deserialize_seq(self),
+            false => deserializer.deserialize_map(self),
+        }
+    }
 }
 
 impl<'a, 'de> Visitor<'de> for StructVisitor<'a> {
     type Value = ();
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "an object")
+        match self.ctx.options.struct_as_array {
+            true => Arity(self.fields.len()).fmt(formatter),
+            false => write!(formatter, "an object"),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut tuple = self.builder.start_tuple();
+        for (index, field) in self.fields.iter().enumerate() {
+            let seed = DeserializeInto {
+                ty: &field.ty,
+                builder: tuple.as_builder(),
+                ctx: self.ctx,
+            };
+            if seq.next_element_seed(seed)?.is_none() {
+                return Err(serde::de::Error::invalid_length(index, &Arity(self.fields.len())));
+            }
+        }
+        Arity(self.fields.len()).end(seq)?;
+        tuple.end_struct(self.fields);
+        Ok(())
     }
 
     fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
-        let mut fields: Vec<(&Field, Option<(usize, usize)>)> = self.fields.iter().map(|f| (f, None)).collect();
+        // Values whose keys arrive in schema order are written straight into the tuple. Any other value is buffered,
+        // and copied into the tuple in order once the map has been read.
+        let mut tuple = self.builder.start_tuple();
+        let mut written = 0;
+        let mut buffered: Vec<Option<(usize, usize)>> = vec![None; self.fields.len()];
         let mut buffer = FlatbinBuf::new();
 
-        while let Some(key) = map.next_key::<&str>()? {
+        while let Some(key) = map.next_key::<Key>()? {
+            let key = &*key;
             // Find the struct field
-            let Some((field, value)) = fields.iter_mut().find(|f| &*f.0.name == key) else {
+            let Some(index) = self.ctx.find_field(self.fields, key) else {
                 let msg = format!("unknown field \"{}\"", key);
                 return Err(serde::de::Error::custom(msg));
             };
+            let field = &self.fields[index];
 
             // Check for duplication
-            if value.is_some() {
+            if index < written || buffered[index].is_some() {
                 let msg = format!("duplicate field \"{}\"", key);
                 return Err(serde::de::Error::custom(msg));
             }
 
             // Deserialize the value
-            let start = buffer.len();
-            let ctx = TypedBuilder {
-                ty: &field.ty,
-                builder: Builder::new(&mut buffer),
+            if index == written {
+                let seed = DeserializeInto {
+                    ty: &field.ty,
+                    builder: tuple.as_builder(),
+                    ctx: self.ctx,
+                };
+                map.next_value_seed(seed)?;
+                written += 1;
+            } else {
+                let start = buffer.len();
+                let seed = DeserializeInto {
+                    ty: &field.ty,
+                    builder: Builder::new(&mut buffer),
+                    ctx: self.ctx,
+                };
+                map.next_value_seed(seed)?;
+                buffered[index] = Some((start, buffer.len()));
+            }
+        }
+
+        // Write out the buffered fields
+        let buffer = buffer.as_bytes();
+        for (field, offsets) in self.fields.iter().zip(buffered).skip(written) {
+            match offsets {
+                Some((start, end)) => tuple.as_builder().copy(Flatbin::from_bytes(&buffer[start..end])),
+                // An optional field takes the zero value of its type, which is encoded as an empty body
+                None if field.omittable() => tuple.as_builder().write_void(),
+                None => {
+                    let msg = format!("missing field \"{}\"", field.json_key());
+                    return Err(serde::de::Error::custom(msg));
+                }
+            }
+        }
+        tuple.end_struct(self.fields);
+
+        Ok(())
+    }
+}
+
+/// Deserializes an externally tagged enum: the name of a variant without fields, or an object whose single key is
+/// the name of the variant and whose value holds its fields.
+struct EnumVisitor<'a> {
+    pub variants: &'a [Variant],
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "a string or an object with a single key")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        let index = find_variant(self.variants, value)?;
+        // Only a variant without fields may be given by its name alone
+        if let Some(field) = self.variants[index].fields.first() {
+            let msg = format!("missing field \"{}\"", field.json_key());
+            return Err(serde::de::Error::custom(msg));
+        }
+        let mut tuple = self.builder.start_tuple();
+        tuple.write(index as u64);
+        tuple.as_builder().start_tuple().end();
+        tuple.end();
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let Some(name) = map.next_key::<Key>()? else {
+            return Err(serde::de::Error::invalid_length(0, &self));
+        };
+        let index = find_variant(self.variants, &name)?;
+        let mut tuple = self.builder.start_tuple();
+        tuple.write(index as u64);
+        map.next_value_seed(StructVisitor {
+            fields: &self.variants[index].fields,
+            builder: tuple.as_builder(),
+            ctx: Context {
+                depth: self.ctx.depth + 1,
+                ..self.ctx
+            },
+        })?;
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            let msg = format!(
+                "expected a single key, the name of a variant, but found more after \"{}\"",
+                &*name
+            );
+            return Err(serde::de::Error::custom(msg));
+        }
+        tuple.end();
+        Ok(())
+    }
+}
+
+/// Deserializes an internally tagged enum: an object holding the name of the variant under the `tag` key, alongside
+/// the variant's fields.
+struct TaggedEnumVisitor<'a> {
+    pub variants: &'a [Variant],
+    pub tag: &'a str,
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a> TaggedEnumVisitor<'a> {
+    /// Writes the variant named `name`, whose fields are read by `fields`.
+    fn write<E: serde::de::Error>(
+        self,
+        name: &str,
+        fields: impl FnOnce(StructVisitor) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let index = find_variant(self.variants, name)?;
+        let mut tuple = self.builder.start_tuple();
+        tuple.write(index as u64);
+        fields(StructVisitor {
+            fields: &self.variants[index].fields,
+            builder: tuple.as_builder(),
+            ctx: Context {
+                depth: self.ctx.depth + 1,
+                ..self.ctx
+            },
+        })?;
+        tuple.end();
+        Ok(())
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for TaggedEnumVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        // When the tag comes first the fields are read straight from the map, and otherwise the entries before it are
+        // buffered until the variant is known.
+        let mut buffered = serde_json::Map::new();
+        while let Some(key) = map.next_key::<Key>()? {
+            if *key != *self.tag {
+                buffered.insert(key.0.into_owned(), map.next_value()?);
+                continue;
+            }
+            let name = map.next_value::<Key>()?;
+            if buffered.is_empty() {
+                return self.write(&name, |fields| fields.visit_map(map));
+            }
+            while let Some((key, value)) = map.next_entry::<Key, serde_json::Value>()? {
+                if buffered.insert(key.0.to_string(), value).is_some() {
+                    let msg = format!("duplicate field \"{}\"", &*key);
+                    return Err(serde::de::Error::custom(msg));
+                }
+            }
+            let buffered = serde_json::Value::Object(buffered);
+            return self.write(&name, |fields| {
+                (&buffered).deserialize_map(fields).map_err(serde::de::Error::custom)
+            });
+        }
+        let msg = format!("missing field \"{}\"", self.tag);
+        Err(serde::de::Error::custom(msg))
+    }
+}
+
+/// A map key or variant name, borrowed from the input where the deserializer allows it, and otherwise owned, as when
+/// reading from an `io::Read` or when a JSON key contains escapes.
+struct Key<'de>(Cow<'de, str>);
+
+impl std::ops::Deref for Key<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key<'de>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "a string")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(value.to_owned())))
+    }
+
+    fn visit_borrowed_str<E: serde::de::Error>(self, value: &'de str) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Borrowed(value)))
+    }
+
+    fn visit_string<E: serde::de::Error>(self, value: String) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(value)))
+    }
+}
+
+fn find_variant<E: serde::de::Error>(variants: &[Variant], name: &str) -> Result<usize, E> {
+    variants
+        .iter()
+        .position(|variant| *variant.name == *name)
+        .ok_or_else(|| {
+            let msg = format!("unknown variant \"{}\"", name);
+            serde::de::Error::custom(msg)
+        })
+}
+
+struct ColumnarVisitor<'a> {
+    pub fields: &'a [Field],
+    pub builder: Builder<'a>,
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'de> Visitor<'de> for ColumnarVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an array of objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        // Each column is built up in its own buffer, one row at a time
+        let mut buffers: Vec<FlatbinBuf> = self.fields.iter().map(|_| FlatbinBuf::new()).collect();
+        let mut columns: Vec<TupleBuilder> = buffers.iter_mut().map(|b| Builder::new(b).start_tuple()).collect();
+        let ctx = Context {
+            depth: self.ctx.depth + 1,
+            ..self.ctx
+        };
+
+        let mut rows = 0;
+        loop {
+            let seed = RowVisitor {
+                fields: self.fields,
+                columns: &mut columns,
+                ctx,
             };
-            map.next_value_seed(ctx)?;
-            *value = Some((start, buffer.len()));
+            if seq.next_element_seed(seed)?.is_none() {
+                break;
+            }
+            rows += 1;
+            if let Some(max) = self.ctx.options.max_array_len {
+                if rows > max {
+                    let msg = format!("array exceeds maximum length of {}", max);
+                    return Err(serde::de::Error::custom(msg));
+                }
+            }
         }
+        drop(columns);
 
-        // Write out the struct
+        // Write out the row count followed by the columns
         let mut tuple = self.builder.start_tuple();
-        let buffer = buffer.as_bytes();
-        for (field, offsets) in fields {
-            let Some((start, end)) = offsets else {
-                let msg = format!("missing field \"{}\"", field.name);
+        tuple.write(rows);
+        for buffer in buffers.iter() {
+            tuple.as_builder().copy(buffer);
+        }
+        tuple.end();
+
+        Ok(())
+    }
+}
+
+/// The expected length of a struct given as an array of its field values.
+struct Arity(usize);
+
+impl Arity {
+    /// Checks that `seq` holds no more elements.
+    fn end<'de, A: SeqAccess<'de>>(&self, mut seq: A) -> Result<(), A::Error> {
+        match seq.next_element::<IgnoredAny>()? {
+            Some(_) => Err(serde::de::Error::invalid_length(self.0 + 1, self)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Expected for Arity {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an array of {} field values", self.0)
+    }
+}
+
+/// Deserializes a single row of a columnar array, appending each field's value to its column.
+struct RowVisitor<'a, 'b> {
+    pub fields: &'a [Field],
+    pub columns: &'a mut [TupleBuilder<'b>],
+    pub ctx: Context<'a>,
+}
+
+impl<'a, 'b, 'de> DeserializeSeed<'de> for RowVisitor<'a, 'b> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.ctx.options.struct_as_array {
+            true => deserializer.deserialize_seq(self),
+            false => deserializer.deserialize_map(self),
+        }
+    }
+}
+
+impl<'a, 'b, 'de> Visitor<'de> for RowVisitor<'a, 'b> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.ctx.options.struct_as_array {
+            true => Arity(self.fields.len()).fmt(formatter),
+            false => write!(formatter, "an object"),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        for (index, (field, column)) in self.fields.iter().zip(self.columns.iter_mut()).enumerate() {
+            let seed = DeserializeInto {
+                ty: &field.ty,
+                builder: column.as_builder(),
+                ctx: self.ctx,
+            };
+            if seq.next_element_seed(seed)?.is_none() {
+                return Err(serde::de::Error::invalid_length(index, &Arity(self.fields.len())));
+            }
+        }
+        Arity(self.fields.len()).end(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut seen = vec![false; self.fields.len()];
+
+        while let Some(key) = map.next_key::<Key>()? {
+            let key = &*key;
+            // Find the column
+            let Some(index) = self.ctx.find_field(self.fields, key) else {
+                let msg = format!("unknown field \"{}\"", key);
                 return Err(serde::de::Error::custom(msg));
             };
-            tuple.as_builder().copy(Flatbin::from_bytes(&buffer[start..end]))
+
+            // Check for duplication
+            if std::mem::replace(&mut seen[index], true) {
+                let msg = format!("duplicate field \"{}\"", key);
+                return Err(serde::de::Error::custom(msg));
+            }
+
+            // Deserialize the value straight into its column
+            let seed = DeserializeInto {
+                ty: &self.fields[index].ty,
+                builder: self.columns[index].as_builder(),
+                ctx: self.ctx,
+            };
+            map.next_value_seed(seed)?;
+        }
+
+        // Every row must contain every field, or the columns would fall out of step
+        if let Some(index) = seen.iter().position(|seen| !seen) {
+            let msg = format!("missing field \"{}\"", self.fields[index].json_key());
+            return Err(serde::de::Error::custom(msg));
         }
-        tuple.end();
 
         Ok(())
     }
@@ -0,0 +1,302 @@
+use super::Error;
+use crate::{
+    flatbin::{self, Flatbin, SequenceIter},
+    ty::{Field, Ty},
+};
+use serde::{
+    de::{
+        value::{BorrowedStrDeserializer, MapAccessDeserializer, SeqDeserializer},
+        DeserializeSeed, MapAccess, SeqAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+
+/// Deserializes a Rust value of type `T` directly from a flatbin document of type `ty`.
+pub fn from_flatbin<'a, T: Deserialize<'a>>(ty: &'a Ty, value: &'a Flatbin) -> Result<T, Error> {
+    T::deserialize(FlatbinDeserializer::new(ty, value))
+}
+
+/// A serde `Deserializer` which reads a flatbin value, guided by its type.
+///
+/// Since the type says what every node holds, the format is effectively self-describing: everything is
+/// read through `deserialize_any`, which calls the visitor method matching the node's `Ty` (`visit_bool`,
+/// `visit_seq` for arrays, `visit_map` for structs, and so on). This makes it usable with targets such as
+/// `serde_json::Value` and `#[serde(untagged)]` enums. Strings are borrowed from the document, as are byte
+/// arrays when requested with `deserialize_bytes` (otherwise they are visited as sequences of numbers).
+#[derive(Clone, Copy)]
+pub struct FlatbinDeserializer<'a> {
+    ty: &'a Ty,
+    value: &'a Flatbin,
+}
+
+impl<'a> FlatbinDeserializer<'a> {
+    pub fn new(ty: &'a Ty, value: &'a Flatbin) -> Self {
+        Self { ty, value }
+    }
+}
+
+impl<'de> Deserializer<'de> for FlatbinDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let FlatbinDeserializer { ty, value } = self;
+        match ty {
+            Ty::Bool => visitor.visit_bool(value.read_bool()?),
+            Ty::U64 => visitor.visit_u64(value.read_u64()?),
+            Ty::I64 => visitor.visit_i64(value.read_i64()?),
+            Ty::F64 => visitor.visit_f64(value.read_f64()?),
+            Ty::FloatVec => visitor.visit_seq(SeqDeserializer::new(value.read_float_vec()?)),
+            // Byte arrays are represented as arrays of numbers in JSON, so are visited the same way here
+            Ty::Bytes => visitor.visit_seq(SeqDeserializer::new(value.read_bytes()?.iter().copied())),
+            Ty::ChunkedBytes { .. } => visitor.visit_seq(SeqDeserializer::new(value.read_chunked_bytes()?.into_iter())),
+            Ty::String => visitor.visit_borrowed_str(value.read_str()?),
+            Ty::Array { inner } => {
+                // The count is checked against the size of the data before it's given as a size hint
+                value.array_len()?;
+                visitor.visit_seq(ArrayAccess {
+                    inner,
+                    elements: value.read_array()?.try_iter(),
+                })
+            }
+            Ty::RleArray { inner } => visitor.visit_seq(RunsAccess {
+                inner,
+                runs: value.read_runs()?.into_iter(),
+                run: None,
+            }),
+            Ty::Map { value: inner, .. } => {
+                value.array_len()?;
+                visitor.visit_map(EntriesAccess {
+                    inner,
+                    entries: value.read_array()?.try_iter(),
+                    value: None,
+                })
+            }
+            Ty::Struct { fields } => visitor.visit_map(StructAccess {
+                fields: fields.iter(),
+                values: value.read_struct(fields)?.iter(),
+                value: None,
+            }),
+            Ty::Enum { variants, tag } => {
+                let (index, values) = value.read_variant(variants)?;
+                let variant = &variants[index];
+                let fields = StructAccess {
+                    fields: variant.fields.iter(),
+                    values: values.iter(),
+                    value: None,
+                };
+                match tag {
+                    None if variant.fields.is_empty() => visitor.visit_borrowed_str(&variant.name),
+                    None => visitor.visit_map(PrefixedAccess {
+                        key: Some(&*variant.name),
+                        value: Some(MapAccessDeserializer::new(fields)),
+                        rest: StructAccess {
+                            fields: [].iter(),
+                            values: std::iter::empty(),
+                            value: None,
+                        },
+                    }),
+                    Some(tag) => visitor.visit_map(PrefixedAccess {
+                        key: Some(&**tag),
+                        value: Some(BorrowedStrDeserializer::new(&variant.name)),
+                        rest: fields,
+                    }),
+                }
+            }
+            Ty::Columnar { fields } => {
+                let (rows, columns) = value.read_columns(fields.len())?;
+                visitor.visit_seq(RowsAccess {
+                    fields,
+                    rows,
+                    columns: columns.iter().map(|column| column.iter()).collect(),
+                })
+            }
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.ty {
+            Ty::Bytes => visitor.visit_borrowed_bytes(self.value.read_bytes()?),
+            Ty::ChunkedBytes { .. } => visitor.visit_byte_buf(self.value.read_chunked_bytes()?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// The elements of an array, read with [`Sequence::try_iter`](flatbin::Sequence::try_iter) so that a count running
+/// past the end of the data fails rather than yielding empty elements.
+struct ArrayAccess<'de, I> {
+    inner: &'de Ty,
+    elements: I,
+}
+
+impl<'de, I: Iterator<Item = flatbin::Result<&'de Flatbin>>> SeqAccess<'de> for ArrayAccess<'de, I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        let Some(value) = self.elements.next() else {
+            return Ok(None);
+        };
+        seed.deserialize(FlatbinDeserializer::new(self.inner, value?)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.elements.size_hint().1
+    }
+}
+
+struct RunsAccess<'de> {
+    inner: &'de Ty,
+    runs: std::vec::IntoIter<(u64, &'de Flatbin)>,
+    run: Option<(u64, &'de Flatbin)>,
+}
+
+impl<'de> SeqAccess<'de> for RunsAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        let value = loop {
+            match &mut self.run {
+                Some((count, value)) if *count > 0 => {
+                    *count -= 1;
+                    break *value;
+                }
+                _ => match self.runs.next() {
+                    Some(run) => self.run = Some(run),
+                    None => return Ok(None),
+                },
+            }
+        };
+        seed.deserialize(FlatbinDeserializer::new(self.inner, value)).map(Some)
+    }
+}
+
+struct StructAccess<'de, I> {
+    fields: std::slice::Iter<'de, Field>,
+    values: I,
+    value: Option<FlatbinDeserializer<'de>>,
+}
+
+impl<'de, I: Iterator<Item = &'de Flatbin>> MapAccess<'de> for StructAccess<'de, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some(field) = self.fields.next() else {
+            return Ok(None);
+        };
+        let value = self.values.next().ok_or(flatbin::Error::UnexpectedEOF)?;
+        self.value = Some(FlatbinDeserializer::new(&field.ty, value));
+        seed.deserialize(BorrowedStrDeserializer::new(field.json_key()))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self.value.take().expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+/// The entries of a map, read as [`ArrayAccess`] reads elements.
+struct EntriesAccess<'de, I> {
+    inner: &'de Ty,
+    entries: I,
+    value: Option<FlatbinDeserializer<'de>>,
+}
+
+impl<'de, I: Iterator<Item = flatbin::Result<&'de Flatbin>>> MapAccess<'de> for EntriesAccess<'de, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some(entry) = self.entries.next() else {
+            return Ok(None);
+        };
+        let (key, value) = entry?.read_entry()?;
+        self.value = Some(FlatbinDeserializer::new(self.inner, value));
+        seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self.value.take().expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.entries.size_hint().1
+    }
+}
+
+/// A map of a single entry from `key` to `value`, followed by the entries of `rest`.
+struct PrefixedAccess<'de, D, M> {
+    key: Option<&'de str>,
+    value: Option<D>,
+    rest: M,
+}
+
+impl<'de, D: Deserializer<'de, Error = Error>, M: MapAccess<'de, Error = Error>> MapAccess<'de>
+    for PrefixedAccess<'de, D, M>
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.key.take() {
+            Some(key) => seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some),
+            None => self.rest.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => self.rest.next_value_seed(seed),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.value.is_some() as usize + self.rest.size_hint()?)
+    }
+}
+
+struct RowsAccess<'de> {
+    fields: &'de [Field],
+    rows: usize,
+    columns: Vec<SequenceIter<'de>>,
+}
+
+impl<'de> SeqAccess<'de> for RowsAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        if self.rows == 0 {
+            return Ok(None);
+        }
+        self.rows -= 1;
+        let values = self
+            .columns
+            .iter_mut()
+            .map(|column| column.next().ok_or(flatbin::Error::UnexpectedEOF))
+            .collect::<flatbin::Result<Vec<_>>>()?;
+        let row = StructAccess {
+            fields: self.fields.iter(),
+            values: values.into_iter(),
+            value: None,
+        };
+        seed.deserialize(MapAccessDeserializer::new(row)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.rows)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::ty::{Field, Ty};
+use std::collections::HashMap;
+
+/// Structs with at least this many fields are looked up by key through a hash map, rather than a linear scan.
+const MIN_INDEXED_FIELDS: usize = 8;
+
+/// Key-to-field lookup tables for the wide structs of a schema.
+///
+/// Each table is keyed by the address of the struct's fields, so an index is only valid for the `Ty` it was
+/// built from, for as long as that `Ty` is neither dropped nor modified.
+#[derive(Default)]
+pub(crate) struct FieldIndex {
+    structs: HashMap<usize, HashMap<Box<str>, usize>>,
+}
+
+impl FieldIndex {
+    pub(crate) fn new(ty: &Ty) -> Self {
+        let mut index = Self::default();
+        index.add(ty);
+        index
+    }
+
+    fn add(&mut self, ty: &Ty) {
+        match ty {
+            Ty::Array { inner } | Ty::RleArray { inner } | Ty::Map { value: inner, .. } => self.add(inner),
+            Ty::Struct { fields } | Ty::Columnar { fields } => self.add_fields(fields),
+            Ty::Enum { variants, .. } => {
+                for variant in variants.iter() {
+                    self.add_fields(&variant.fields);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_fields(&mut self, fields: &[Field]) {
+        if fields.len() >= MIN_INDEXED_FIELDS {
+            let mut keys = HashMap::new();
+            for (i, field) in fields.iter().enumerate() {
+                for key in field.keys() {
+                    // As with a linear scan, the first field with a given key takes precedence
+                    keys.entry(key.into()).or_insert(i);
+                }
+            }
+            self.structs.insert(fields.as_ptr() as usize, keys);
+        }
+        for field in fields.iter() {
+            self.add(&field.ty);
+        }
+    }
+
+    /// Finds the position of the field matching `key`, or `None` if the struct isn't indexed.
+    pub(crate) fn find(&self, fields: &[Field], key: &str) -> Option<Option<usize>> {
+        let keys = self.structs.get(&(fields.as_ptr() as usize))?;
+        Some(keys.get(key).copied())
+    }
+}
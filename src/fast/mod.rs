@@ -1,13 +1,22 @@
 pub use de::*;
+pub use from_flatbin::*;
+pub use ndjson::*;
+pub use prepared::*;
 pub use ser::*;
 
 mod de;
+mod from_flatbin;
+mod index;
+mod ndjson;
+mod prepared;
 mod ser;
 
 #[cfg(test)]
 mod test {
-    use super::{deserialize, serialize};
-    use crate::{array_def, struct_def, ty::Ty, JsonValue};
+    use super::{
+        deserialize, deserialize_from, deserialize_into_with, deserialize_prepared, serialize, PreparedSchema,
+    };
+    use crate::{array_def, flatbin::FlatbinBuf, options::DeserializeOptions, struct_def, ty::Ty, JsonValue};
 
     #[test]
     fn bool_roundtrip() {
@@ -43,4 +52,409 @@ mod test {
         let new_value = serialize(serde_json::value::Serializer, &ty, &bytes).unwrap();
         assert_eq!(value, new_value);
     }
+
+    #[test]
+    fn deserialize_from_custom_deserializer() {
+        let ty = array_def!(Ty::U64);
+        let json = b"[1, 2, 3]";
+
+        let mut buffer = FlatbinBuf::new();
+        let mut de = serde_json::Deserializer::from_slice(json);
+        deserialize_from(&ty, &mut de, &mut buffer).unwrap();
+        de.end().unwrap();
+
+        let value = serialize(serde_json::value::Serializer, &ty, &buffer).unwrap();
+        assert_eq!(value, serde_json::json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn deserialize_from_reader() {
+        use crate::ty::{Field, Variant};
+
+        let shape = |tag: Option<&str>| Ty::Enum {
+            variants: [
+                Variant::new("circle", [Field::new("radius", Ty::U64)]),
+                Variant::unit("point"),
+            ]
+            .into(),
+            tag: tag.map(Into::into),
+        };
+        let ty = struct_def!({
+            "name": Ty::String,
+            "shape": shape(None),
+            "tagged": shape(Some("kind")),
+            "rows": Ty::Columnar { fields: [Field::new("a", Ty::U64)].into() },
+        });
+        let json = r#"{
+            "shape": { "circle": { "radius": 2 } },
+            "name": "Ada",
+            "tagged": { "radius": 3, "kind": "circle" },
+            "rows": [{ "a": 1 }, { "a": 2 }]
+        }"#;
+        let expected = deserialize(&ty, json).unwrap();
+
+        // A reader can't lend out strings, so every key is handed over owned
+        let mut buffer = FlatbinBuf::new();
+        let mut de = serde_json::Deserializer::from_reader(json.as_bytes());
+        deserialize_from(&ty, &mut de, &mut buffer).unwrap();
+        assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+        // As are keys with escapes
+        let escaped = json
+            .replace("\"name\"", "\"n\\u0061me\"")
+            .replace("\"circle\"", "\"\\u0063ircle\"");
+        assert_eq!(deserialize(&ty, &escaped).unwrap().as_bytes(), expected.as_bytes());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let ty = struct_def!({ "a": array_def!(array_def!(Ty::U64)) });
+        let json = r#"{ "a": [[1], [2, 3]] }"#;
+
+        let mut buffer = FlatbinBuf::new();
+        let options = DeserializeOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let err = deserialize_into_with(&ty, json, &mut buffer, &options).unwrap_err();
+        assert!(err.to_string().contains("maximum nesting depth exceeded"));
+
+        let mut buffer = FlatbinBuf::new();
+        let options = DeserializeOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+        deserialize_into_with(&ty, json, &mut buffer, &options).unwrap();
+    }
+
+    #[test]
+    fn non_json_deserializers() {
+        use serde::de::value::{BorrowedBytesDeserializer, Error, StringDeserializer, UnitDeserializer};
+
+        let mut buffer = FlatbinBuf::new();
+        let de = StringDeserializer::<Error>::new("Hello".into());
+        deserialize_from(&Ty::String, de, &mut buffer).unwrap();
+        assert_eq!(buffer.read_str().unwrap(), "Hello");
+
+        let mut buffer = FlatbinBuf::new();
+        let de = BorrowedBytesDeserializer::<Error>::new(&[1, 2, 3]);
+        deserialize_from(&Ty::Bytes, de, &mut buffer).unwrap();
+        assert_eq!(buffer.read_bytes().unwrap(), &[1, 2, 3]);
+
+        let mut buffer = FlatbinBuf::new();
+        let de = UnitDeserializer::<Error>::new();
+        assert!(deserialize_from(&Ty::String, de, &mut buffer).is_err());
+    }
+
+    #[test]
+    fn nested_serialize_as() {
+        use crate::flatbin::SerializeAs;
+        use std::collections::BTreeMap;
+
+        let ty = array_def!(Ty::String);
+        let a = deserialize(&ty, r#"["x", "y"]"#).unwrap();
+        let b = deserialize(&Ty::U64, "42").unwrap();
+
+        let mut docs = BTreeMap::new();
+        docs.insert("a", SerializeAs::new(&ty, &a));
+        docs.insert("b", SerializeAs::new(&Ty::U64, &b));
+
+        let json = serde_json::to_value(&docs).unwrap();
+        assert_eq!(json, serde_json::json!({ "a": ["x", "y"], "b": 42 }));
+    }
+
+    #[test]
+    fn nested_deserialize_into() {
+        use crate::flatbin::{Builder, DeserializeInto};
+        use serde::de::{Deserializer, SeqAccess, Visitor};
+
+        // Splits a JSON array of `[name, count]` pairs into two separately encoded columns
+        struct Columns<'a>(&'a mut FlatbinBuf, &'a mut FlatbinBuf);
+
+        impl<'de> Visitor<'de> for Columns<'_> {
+            type Value = ();
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "a pair")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+                let options = DeserializeOptions::default();
+                seq.next_element_seed(DeserializeInto::new(&Ty::String, Builder::new(self.0), &options))?;
+                seq.next_element_seed(DeserializeInto::new(&Ty::U64, Builder::new(self.1), &options))?;
+                Ok(())
+            }
+        }
+
+        let (mut names, mut counts) = (FlatbinBuf::new(), FlatbinBuf::new());
+        let mut de = serde_json::Deserializer::from_str(r#"["apples", 3]"#);
+        de.deserialize_seq(Columns(&mut names, &mut counts)).unwrap();
+
+        assert_eq!(names.read_str().unwrap(), "apples");
+        assert_eq!(counts.read_u64().unwrap(), 3);
+    }
+
+    #[test]
+    fn byte_slices() {
+        use super::{deserialize_bytes, serialize_bytes};
+
+        let ty = struct_def!({ "name": Ty::String, "scores": array_def!(Ty::U64) });
+        let json = br#"{"name":"Alice","scores":[3,1,4]}"#;
+
+        let bytes = deserialize_bytes(&ty, json).unwrap();
+        assert_eq!(serialize_bytes(&ty, &bytes).unwrap(), json);
+
+        assert!(deserialize_bytes(&ty, br#"{"name":"Alice","scores":[]} x"#).is_err());
+        assert!(serialize_bytes(&Ty::F64, &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn flatbin_deserializer() {
+        use super::{from_flatbin, FlatbinDeserializer};
+        use crate::flatbin::{Error as FlatbinError, Flatbin};
+        use serde::Deserialize;
+        use std::collections::HashMap;
+
+        let ty = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "scores": array_def!(Ty::F64),
+            "avatar": Ty::Bytes,
+            "rustacean": Ty::Bool
+        });
+        let value = serde_json::json!({
+            "name": "Alice",
+            "age": 31,
+            "scores": [1.5, -2.0],
+            "avatar": [1, 2, 3],
+            "rustacean": true
+        });
+        let bytes = deserialize(&ty, &value.to_string()).unwrap();
+
+        // Straight to a `JsonValue`, without going via JSON text
+        let json = JsonValue::deserialize(FlatbinDeserializer::new(&ty, &bytes)).unwrap();
+        assert_eq!(json, value);
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Person<'a> {
+            name: &'a str,
+            age: u8,
+            avatar: &'a [u8],
+        }
+        let person: Person = from_flatbin(&ty, &bytes).unwrap();
+        assert_eq!(
+            person,
+            Person {
+                name: "Alice",
+                age: 31,
+                avatar: &[1, 2, 3]
+            }
+        );
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(untagged)]
+        enum Either {
+            Number(u64),
+            Text(String),
+        }
+        let either: Vec<Either> =
+            from_flatbin(&array_def!(Ty::U64), &deserialize(&array_def!(Ty::U64), "[7]").unwrap()).unwrap();
+        assert_eq!(either, [Either::Number(7)]);
+        let either: Either = from_flatbin(&Ty::String, &deserialize(&Ty::String, "\"x\"").unwrap()).unwrap();
+        assert_eq!(either, Either::Text("x".into()));
+
+        // Counts far beyond the size of the document fail rather than being read
+        let huge_count = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        assert!(matches!(
+            from_flatbin::<Vec<u64>>(&array_def!(Ty::U64), huge_count),
+            Err(super::Error::Flatbin(FlatbinError::UnexpectedLength))
+        ));
+        let ty = Ty::Map {
+            value: Ty::U64.into(),
+            layout: Default::default(),
+        };
+        assert!(matches!(
+            from_flatbin::<HashMap<String, u64>>(&ty, huge_count),
+            Err(super::Error::Flatbin(FlatbinError::UnexpectedLength))
+        ));
+    }
+
+    #[test]
+    fn ndjson() {
+        use super::{deserialize_ndjson, serialize_ndjson, NdjsonError};
+
+        let ty = struct_def!({ "level": Ty::String, "code": Ty::U64 });
+        let input = "{\"level\":\"info\",\"code\":1}\n\n{\"code\":2,\"level\":\"warn\"}\n";
+
+        let mut buffer = FlatbinBuf::new();
+        assert_eq!(deserialize_ndjson(&ty, input.as_bytes(), &mut buffer).unwrap(), 2);
+        let expected = deserialize(
+            &array_def!(ty.clone()),
+            r#"[{"level":"info","code":1},{"level":"warn","code":2}]"#,
+        )
+        .unwrap();
+        assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+        let input = "{\"level\":\"info\",\"code\":1}\n{\"level\":\"info\"}\n";
+        let mut buffer = FlatbinBuf::new();
+        let result = deserialize_ndjson(&ty, input.as_bytes(), &mut buffer);
+        assert!(matches!(result, Err(NdjsonError::Line { line: 2, .. })));
+        assert!(buffer.is_empty());
+
+        // Decoding gives back compact lines, one per element
+        let mut output = vec![];
+        assert_eq!(serialize_ndjson(&ty, &expected, &mut output).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"level\":\"info\",\"code\":1}\n{\"level\":\"warn\",\"code\":2}\n"
+        );
+
+        let mut output = vec![];
+        let corrupt = crate::flatbin::Flatbin::from_bytes(&[2, 0x81, 0xff, 0x81, 0xff]);
+        let result = serialize_ndjson(&Ty::String, corrupt, &mut output);
+        assert!(matches!(result, Err(NdjsonError::Element { index: 0, .. })));
+    }
+
+    #[test]
+    fn canonical_floats() {
+        let ty = array_def!(Ty::F64);
+        let options = DeserializeOptions {
+            canonical_floats: true,
+            ..Default::default()
+        };
+
+        let mut exact = FlatbinBuf::new();
+        deserialize_into_with(&ty, "[-0.0, 1.5]", &mut exact, &DeserializeOptions::default()).unwrap();
+        let mut negative = FlatbinBuf::new();
+        deserialize_into_with(&ty, "[-0.0, 1.5]", &mut negative, &options).unwrap();
+        let mut positive = FlatbinBuf::new();
+        deserialize_into_with(&ty, "[0.0, 1.5]", &mut positive, &options).unwrap();
+
+        assert_ne!(exact.as_bytes(), positive.as_bytes());
+        assert_eq!(negative.as_bytes(), positive.as_bytes());
+    }
+
+    #[test]
+    fn struct_key_order() {
+        let ty = struct_def!({
+            "a": Ty::U64,
+            "b": Ty::String,
+            "c": array_def!(Ty::U64),
+            "d": Ty::String,
+        });
+        let expected = crate::slow::deserialize_alloc(
+            &ty,
+            &serde_json::json!({ "a": 300, "b": "", "c": [1, 2], "d": "hello" }),
+        )
+        .unwrap();
+
+        for json in [
+            r#"{ "a": 300, "b": "", "c": [1, 2], "d": "hello" }"#,
+            r#"{ "d": "hello", "c": [1, 2], "b": "", "a": 300 }"#,
+            r#"{ "b": "", "a": 300, "d": "hello", "c": [1, 2] }"#,
+            r#"{ "a": 300, "c": [1, 2], "b": "", "d": "hello" }"#,
+        ] {
+            assert_eq!(
+                deserialize(&ty, json).unwrap().as_bytes(),
+                expected.as_bytes(),
+                "{}",
+                json
+            );
+        }
+
+        for json in [
+            r#"{ "a": 300, "b": "", "a": 300, "c": [1, 2], "d": "hello" }"#,
+            r#"{ "b": "", "a": 300, "b": "", "c": [1, 2], "d": "hello" }"#,
+        ] {
+            let err = deserialize(&ty, json).unwrap_err();
+            assert!(err.to_string().contains("duplicate field"), "{}", json);
+        }
+        let err = deserialize(&ty, r#"{ "a": 300, "c": [1, 2], "d": "hello" }"#).unwrap_err();
+        assert!(err.to_string().contains("missing field \"b\""));
+    }
+
+    #[test]
+    fn wide_struct() {
+        use crate::ty::Field;
+
+        let mut fields: Vec<_> = (0..20).map(|i| Field::new(format!("f{}", i), Ty::U64)).collect();
+        fields[3].aliases = vec!["three".into()].into();
+        let ty = array_def!(Ty::Struct { fields: fields.into() });
+
+        let object = |key: &str| {
+            let mut object: serde_json::Map<_, _> = (0..20).rev().map(|i| (format!("f{}", i), i.into())).collect();
+            object.remove("f3");
+            object.insert(key.into(), 3.into());
+            JsonValue::Object(object)
+        };
+        // Only a prepared schema looks fields up through its index, and both find the same fields
+        let schema = PreparedSchema::new(ty.clone());
+        let decode = |json: &str| {
+            let mut buffer = FlatbinBuf::new();
+            let prepared = deserialize_prepared(&schema, json, &mut buffer, &DeserializeOptions::default());
+            let unprepared = deserialize(&ty, json);
+            assert_eq!(prepared.is_ok(), unprepared.is_ok());
+            prepared.map(|_| buffer)
+        };
+
+        let value = JsonValue::Array(vec![object("f3"), object("three")]);
+        let expected = crate::slow::deserialize_alloc(&ty, &value).unwrap();
+        assert_eq!(decode(&value.to_string()).unwrap().as_bytes(), expected.as_bytes());
+
+        let err = decode(&format!("[{}]", object("F3"))).unwrap_err();
+        assert!(err.to_string().contains("unknown field \"F3\""));
+        let json = format!("[{}]", object("f3")).replace("\"f0\":0", "\"f0\":0,\"three\":3");
+        let err = decode(&json).unwrap_err();
+        assert!(err.to_string().contains("duplicate field"));
+    }
+
+    #[test]
+    fn prepared_schema() {
+        use crate::ty::Field;
+
+        let fields = (0..10).map(|i| Field::new(format!("f{}", i), Ty::U64));
+        let ty = array_def!(Ty::Struct {
+            fields: fields.collect()
+        });
+        let schema = PreparedSchema::new(ty.clone());
+        assert_eq!(schema.depth(), 2);
+        assert_eq!(schema.size_report(), &ty.size_report());
+
+        let options = DeserializeOptions::default();
+        for row in 0..3 {
+            let object: serde_json::Map<_, _> = (0..10).rev().map(|i| (format!("f{}", i), (row * i).into())).collect();
+            let json = JsonValue::Array(vec![JsonValue::Object(object)]).to_string();
+            let mut buffer = FlatbinBuf::new();
+            deserialize_prepared(&schema, &json, &mut buffer, &options).unwrap();
+            assert_eq!(buffer.as_bytes(), deserialize(&ty, &json).unwrap().as_bytes());
+        }
+        assert!(deserialize_prepared(&schema, r#"[{ "f0": 1 }]"#, &mut FlatbinBuf::new(), &options).is_err());
+        assert_eq!(schema.into_ty(), ty);
+    }
+
+    #[test]
+    fn to_json() {
+        use super::{to_json_string, to_json_vec};
+
+        let ty = struct_def!({ "name": Ty::String, "hobbies": array_def!(Ty::String) });
+        let value = serde_json::json!({ "name": "Alice", "hobbies": ["Chess"] });
+        let bytes = deserialize(&ty, &value.to_string()).unwrap();
+
+        assert_eq!(
+            to_json_string(&ty, &bytes, false).unwrap(),
+            serde_json::to_string(&value).unwrap()
+        );
+        assert_eq!(
+            to_json_string(&ty, &bytes, true).unwrap(),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        assert_eq!(
+            to_json_vec(&ty, &bytes, false).unwrap(),
+            serde_json::to_vec(&value).unwrap()
+        );
+
+        // A struct missing its fields reports the corruption
+        let err = to_json_string(&ty, crate::flatbin::Flatbin::from_bytes(&[]), false).unwrap_err();
+        assert!(err.to_string().contains("corrupt document"));
+    }
 }
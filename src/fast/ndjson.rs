@@ -0,0 +1,69 @@
+use super::{DeserializeInto, SerializeAs};
+use crate::{
+    flatbin::{self, Builder, Flatbin, FlatbinBuf},
+    options::DeserializeOptions,
+    ty::Ty,
+};
+use serde::de::DeserializeSeed;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NdjsonError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("line {line}: {source}")]
+    Line { line: usize, source: serde_json::Error },
+    #[error("element {index}: {source}")]
+    Element { index: usize, source: serde_json::Error },
+    #[error(transparent)]
+    Flatbin(#[from] flatbin::Error),
+}
+
+/// Encodes newline-delimited JSON, with one value of type `inner` per line, as a single array.
+///
+/// The input is read one line at a time, so only the encoded output is held in memory. Blank lines are
+/// skipped. On success, returns the number of elements written; on failure, `buffer` is left as it was.
+pub fn deserialize_ndjson<R: Read>(inner: &Ty, reader: R, buffer: &mut FlatbinBuf) -> Result<usize, NdjsonError> {
+    let start = buffer.len();
+    let result = write_lines(inner, BufReader::new(reader), buffer);
+    if result.is_err() {
+        buffer.truncate(start);
+    }
+    result
+}
+
+fn write_lines<R: BufRead>(inner: &Ty, mut reader: R, buffer: &mut FlatbinBuf) -> Result<usize, NdjsonError> {
+    let options = DeserializeOptions::default();
+    let mut vector = Builder::new(buffer).start_vector();
+    let mut line = String::new();
+    for number in 1.. {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut de = serde_json::Deserializer::from_str(&line);
+        DeserializeInto::new(inner, vector.as_builder(), &options)
+            .deserialize(&mut de)
+            .and_then(|()| de.end())
+            .map_err(|source| NdjsonError::Line { line: number, source })?;
+    }
+    Ok(vector.end())
+}
+
+/// Writes an array whose elements are of type `inner` as newline-delimited JSON, one compact value per line.
+///
+/// Elements are written one at a time as they are decoded, so `writer` should be buffered. On success,
+/// returns the number of lines written.
+pub fn serialize_ndjson<W: Write>(inner: &Ty, value: &Flatbin, mut writer: W) -> Result<usize, NdjsonError> {
+    let array = value.read_array()?;
+    for (index, element) in array.iter().enumerate() {
+        serde_json::to_writer(&mut writer, &SerializeAs::new(inner, element))
+            .map_err(|source| NdjsonError::Element { index, source })?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(array.len())
+}
@@ -0,0 +1,78 @@
+use super::{index::FieldIndex, DeserializeInto};
+use crate::{
+    flatbin::{Builder, FlatbinBuf},
+    options::DeserializeOptions,
+    ty::{SizeReport, Ty},
+};
+use serde::{de::DeserializeSeed, Deserializer};
+
+/// A schema with its preprocessing done up front, for decoding many documents of the same type.
+///
+/// The `Ty` remains the source of truth: a `PreparedSchema` holds a copy of it along with lookup tables for the
+/// fields of wide structs, its [`depth`](Ty::depth), and its [`size_report`](Ty::size_report), all derived from
+/// that copy. If the schema changes, a new `PreparedSchema` must be built from the new `Ty`.
+pub struct PreparedSchema {
+    ty: Ty,
+    index: FieldIndex,
+    depth: usize,
+    size_report: SizeReport,
+}
+
+impl PreparedSchema {
+    pub fn new(ty: Ty) -> Self {
+        Self {
+            index: FieldIndex::new(&ty),
+            depth: ty.depth(),
+            size_report: ty.size_report(),
+            ty,
+        }
+    }
+
+    pub fn ty(&self) -> &Ty {
+        &self.ty
+    }
+
+    /// The smallest [`max_depth`](DeserializeOptions::max_depth) which accepts every document of this type.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn size_report(&self) -> &SizeReport {
+        &self.size_report
+    }
+
+    /// Unwraps the schema's `Ty`, discarding the precomputed data.
+    pub fn into_ty(self) -> Ty {
+        self.ty
+    }
+}
+
+impl From<Ty> for PreparedSchema {
+    fn from(ty: Ty) -> Self {
+        Self::new(ty)
+    }
+}
+
+/// Deserializes JSON text as with [`deserialize_into_with`](super::deserialize_into_with), using the lookup
+/// tables of a prepared schema to look up the fields of wide structs, which are otherwise scanned in order.
+pub fn deserialize_prepared(
+    schema: &PreparedSchema,
+    value: &str,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+) -> serde_json::Result<()> {
+    let mut de = serde_json::Deserializer::from_str(value);
+    deserialize_from_prepared(schema, &mut de, buffer, options)
+}
+
+/// Deserializes a value from any serde `Deserializer`, using the lookup tables of a prepared schema.
+pub fn deserialize_from_prepared<'de, D: Deserializer<'de>>(
+    schema: &PreparedSchema,
+    deserializer: D,
+    buffer: &mut FlatbinBuf,
+    options: &DeserializeOptions,
+) -> Result<(), D::Error> {
+    DeserializeInto::new(&schema.ty, Builder::new(buffer), options)
+        .with_index(&schema.index)
+        .deserialize(deserializer)
+}
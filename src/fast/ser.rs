@@ -1,50 +1,225 @@
 use crate::{
     flatbin::{self, Flatbin},
-    ty::Ty,
+    options::SerializeOptions,
+    ty::{Field, Ty},
 };
 use serde::{ser::SerializeMap, ser::SerializeSeq, Serialize, Serializer};
 
 pub fn serialize<S: Serializer>(serializer: S, ty: &Ty, value: &Flatbin) -> Result<S::Ok, S::Error> {
-    TypedValue { ty, value }.serialize(serializer)
+    SerializeAs::new(ty, value).serialize(serializer)
 }
 
-struct TypedValue<'a> {
+pub fn serialize_with<S: Serializer>(
+    serializer: S,
+    ty: &Ty,
+    value: &Flatbin,
+    options: &SerializeOptions,
+) -> Result<S::Ok, S::Error> {
+    SerializeAs::with_options(ty, value, options).serialize(serializer)
+}
+
+/// Serializes a flatbin document held in a byte slice to JSON text.
+///
+/// The document is validated against `ty` first, so corrupt input is reported as an error.
+pub fn serialize_bytes(ty: &Ty, value: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let value = Flatbin::try_from_bytes(value, ty).map_err(serde::ser::Error::custom)?;
+    let mut buffer = vec![];
+    serialize(&mut serde_json::Serializer::new(&mut buffer), ty, value)?;
+    Ok(buffer)
+}
+
+/// Serializes a value of type `ty` to JSON text, pretty-printed with two-space indentation if `pretty` is set.
+pub fn to_json_vec(ty: &Ty, value: &Flatbin, pretty: bool) -> serde_json::Result<Vec<u8>> {
+    let mut buffer = vec![];
+    match pretty {
+        true => serialize(&mut serde_json::Serializer::pretty(&mut buffer), ty, value)?,
+        false => serialize(&mut serde_json::Serializer::new(&mut buffer), ty, value)?,
+    }
+    Ok(buffer)
+}
+
+/// Serializes a value of type `ty` to a JSON string, as [`to_json_vec`] does.
+pub fn to_json_string(ty: &Ty, value: &Flatbin, pretty: bool) -> serde_json::Result<String> {
+    let buffer = to_json_vec(ty, value, pretty)?;
+    Ok(String::from_utf8(buffer).expect("serde_json writes valid UTF-8"))
+}
+
+/// A flatbin value paired with its type, which can be serialized anywhere serde expects a `Serialize` value.
+#[derive(Clone, Copy)]
+pub struct SerializeAs<'a> {
     pub ty: &'a Ty,
     pub value: &'a Flatbin,
+    pub options: &'a SerializeOptions,
 }
 
-impl<'a> Serialize for TypedValue<'a> {
+const DEFAULT_OPTIONS: &SerializeOptions = &SerializeOptions::new();
+
+impl<'a> SerializeAs<'a> {
+    pub fn new(ty: &'a Ty, value: &'a Flatbin) -> Self {
+        Self::with_options(ty, value, DEFAULT_OPTIONS)
+    }
+
+    pub fn with_options(ty: &'a Ty, value: &'a Flatbin, options: &'a SerializeOptions) -> Self {
+        Self { ty, value, options }
+    }
+}
+
+impl<'a> Serialize for SerializeAs<'a> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let TypedValue { ty, value } = self;
+        let SerializeAs { ty, value, options } = *self;
         match ty {
-            Ty::Bool => serializer.serialize_bool(value.read_bool().map_err(corrupt)?),
-            Ty::U64 => serializer.serialize_u64(value.read_u64().map_err(corrupt)?),
-            Ty::I64 => serializer.serialize_i64(value.read_i64().map_err(corrupt)?),
+            Ty::Bool => match value.read_bool().map_err(corrupt)? {
+                value if options.bools_as_ints => serializer.serialize_u64(value as u64),
+                value => serializer.serialize_bool(value),
+            },
+            Ty::U64 => match value.read_u64().map_err(corrupt)? {
+                value if options.stringify_int(value) => serializer.collect_str(&value),
+                value => serializer.serialize_u64(value),
+            },
+            Ty::I64 => match value.read_i64().map_err(corrupt)? {
+                value if options.stringify_int(value.unsigned_abs()) => serializer.collect_str(&value),
+                value => serializer.serialize_i64(value),
+            },
             Ty::F64 => serializer.serialize_f64(value.read_f64().map_err(corrupt)?),
-            Ty::Bytes => serializer.serialize_bytes(value.read_bytes().map_err(corrupt)?),
+            Ty::FloatVec => serializer.collect_seq(value.read_float_vec().map_err(corrupt)?),
+            Ty::Bytes => {
+                let bytes = value.read_bytes().map_err(corrupt)?;
+                match options.bytes_encoding.encode(bytes) {
+                    Some(encoded) => serializer.serialize_str(&encoded),
+                    None => serializer.serialize_bytes(bytes),
+                }
+            }
+            Ty::ChunkedBytes { .. } => {
+                let bytes = value.read_chunked_bytes().map_err(corrupt)?;
+                match options.bytes_encoding.encode(&bytes) {
+                    Some(encoded) => serializer.serialize_str(&encoded),
+                    None => serializer.serialize_bytes(&bytes),
+                }
+            }
             Ty::String => serializer.serialize_str(value.read_str().map_err(corrupt)?),
             Ty::Array { inner } => {
                 let array = value.read_array().map_err(corrupt)?;
                 let mut seq = serializer.serialize_seq(Some(array.len()))?;
-                for value in array {
-                    let ctx = TypedValue { ty: inner, value };
+                for value in array.try_iter() {
+                    let value = value.map_err(corrupt)?;
+                    let ctx = SerializeAs::with_options(inner, value, options);
                     seq.serialize_element(&ctx)?;
                 }
                 seq.end()
             }
+            Ty::RleArray { inner } => {
+                let runs = value.read_runs().map_err(corrupt)?;
+                let len = value.rle_len().map_err(corrupt)?;
+                let mut seq = serializer.serialize_seq(Some(len))?;
+                for (count, value) in runs {
+                    let ctx = SerializeAs::with_options(inner, value, options);
+                    for _ in 0..count {
+                        seq.serialize_element(&ctx)?;
+                    }
+                }
+                seq.end()
+            }
+            Ty::Map { value: inner, .. } => {
+                let entries = value.read_array().map_err(corrupt)?;
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for entry in entries.try_iter() {
+                    let (key, value) = entry.and_then(|entry| entry.read_entry()).map_err(corrupt)?;
+                    map.serialize_entry(key, &SerializeAs::with_options(inner, value, options))?;
+                }
+                map.end()
+            }
             Ty::Struct { fields } => {
-                let tuple = value.read_tuple(fields.len()).map_err(corrupt)?;
+                if let Some(field) = Field::only_field(fields).filter(|_| options.unwrap_single_fields) {
+                    return SerializeAs::with_options(&field.ty, value, options).serialize(serializer);
+                }
+                let tuple = value.read_struct(fields).map_err(corrupt)?;
+                if options.struct_as_array {
+                    let mut seq = serializer.serialize_seq(Some(fields.len()))?;
+                    for (field, value) in fields.iter().zip(tuple) {
+                        seq.serialize_element(&SerializeAs::with_options(&field.ty, value, options))?;
+                    }
+                    return seq.end();
+                }
                 let mut map = serializer.serialize_map(Some(fields.len()))?;
                 for (field, value) in fields.iter().zip(tuple) {
-                    let ctx = TypedValue { ty: &field.ty, value };
-                    map.serialize_entry(&*field.name, &ctx)?;
+                    let ctx = SerializeAs::with_options(&field.ty, value, options);
+                    map.serialize_entry(field.json_key(), &ctx)?;
                 }
                 map.end()
             }
+            Ty::Enum { variants, tag } => {
+                let (index, values) = value.read_variant(variants).map_err(corrupt)?;
+                let variant = &variants[index];
+                match tag {
+                    None if variant.fields.is_empty() => serializer.serialize_str(&variant.name),
+                    None => {
+                        let fields = RowAs {
+                            fields: &variant.fields,
+                            values: values.iter().collect(),
+                            options,
+                        };
+                        let mut map = serializer.serialize_map(Some(1))?;
+                        map.serialize_entry(&*variant.name, &fields)?;
+                        map.end()
+                    }
+                    Some(tag) => {
+                        let mut map = serializer.serialize_map(Some(1 + variant.fields.len()))?;
+                        map.serialize_entry(&**tag, &*variant.name)?;
+                        for (field, value) in variant.fields.iter().zip(values) {
+                            let ctx = SerializeAs::with_options(&field.ty, value, options);
+                            map.serialize_entry(field.json_key(), &ctx)?;
+                        }
+                        map.end()
+                    }
+                }
+            }
+            Ty::Columnar { fields } => {
+                let (rows, columns) = value.read_columns(fields.len()).map_err(corrupt)?;
+                let mut columns = columns.iter().map(|column| column.iter()).collect::<Vec<_>>();
+                let mut seq = serializer.serialize_seq(Some(rows))?;
+                for _ in 0..rows {
+                    let values = columns
+                        .iter_mut()
+                        .map(|column| column.next().ok_or(flatbin::Error::UnexpectedEOF))
+                        .collect::<flatbin::Result<Vec<_>>>()
+                        .map_err(corrupt)?;
+                    seq.serialize_element(&RowAs {
+                        fields,
+                        values,
+                        options,
+                    })?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// The values of a struct's fields, such as one row of a columnar array or the fields of an enum variant.
+struct RowAs<'a> {
+    fields: &'a [Field],
+    values: Vec<&'a Flatbin>,
+    options: &'a SerializeOptions,
+}
+
+impl<'a> Serialize for RowAs<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.options.struct_as_array {
+            let mut seq = serializer.serialize_seq(Some(self.fields.len()))?;
+            for (field, &value) in self.fields.iter().zip(&self.values) {
+                seq.serialize_element(&SerializeAs::with_options(&field.ty, value, self.options))?;
+            }
+            return seq.end();
+        }
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (field, &value) in self.fields.iter().zip(&self.values) {
+            let ctx = SerializeAs::with_options(&field.ty, value, self.options);
+            map.serialize_entry(field.json_key(), &ctx)?;
         }
+        map.end()
     }
 }
 
-fn corrupt<E: serde::ser::Error>(_: flatbin::Error) -> E {
-    E::custom("corrupt document")
+fn corrupt<E: serde::ser::Error>(err: flatbin::Error) -> E {
+    E::custom(format_args!("corrupt document: {}", err))
 }
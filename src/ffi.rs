@@ -0,0 +1,215 @@
+//! A C ABI for encoding and decoding documents, enabled by the `ffi` feature.
+//!
+//! The crate is built as a Rust library only. To build a shared library exposing these functions, run
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+//!
+//! The declarations for C (or Python's `cffi`) are:
+//!
+//! ```c
+//! typedef struct TsdsSchema TsdsSchema;
+//!
+//! #define TSDS_OK 0
+//! #define TSDS_NULL_POINTER 1
+//! #define TSDS_INVALID_SCHEMA 2
+//! #define TSDS_INVALID_INPUT 3
+//! #define TSDS_BUFFER_TOO_SMALL 4
+//! #define TSDS_PANIC 5
+//!
+//! int tsds_schema_parse(const uint8_t *json, size_t len, TsdsSchema **out);
+//! void tsds_schema_free(TsdsSchema *schema);
+//! int tsds_encode(const TsdsSchema *schema, const uint8_t *json, size_t len,
+//!                 uint8_t *out, size_t out_cap, size_t *out_len);
+//! int tsds_decode(const TsdsSchema *schema, const uint8_t *flatbin, size_t len,
+//!                 uint8_t *out, size_t out_cap, size_t *out_len);
+//! ```
+//!
+//! # Memory ownership
+//!
+//! A schema handle returned by [`tsds_schema_parse`] is owned by the caller, and must be released with exactly
+//! one call to [`tsds_schema_free`]. Every other buffer is owned by the caller: input buffers are only read
+//! during the call, and output is written into the caller's `out` buffer, so there is nothing else to free.
+//!
+//! If `out_cap` is too small, [`TSDS_BUFFER_TOO_SMALL`] is returned, nothing is written to `out`, and
+//! `*out_len` is set to the required capacity so the call can be retried with a larger buffer.
+//!
+//! # Panics
+//!
+//! A panic never unwinds into the caller. A function which panics returns [`TSDS_PANIC`] instead, having written
+//! nothing to its outputs.
+
+use crate::{fast, ty::Ty};
+use std::{ffi::c_int, slice};
+
+pub const TSDS_OK: c_int = 0;
+pub const TSDS_NULL_POINTER: c_int = 1;
+pub const TSDS_INVALID_SCHEMA: c_int = 2;
+pub const TSDS_INVALID_INPUT: c_int = 3;
+pub const TSDS_BUFFER_TOO_SMALL: c_int = 4;
+pub const TSDS_PANIC: c_int = 5;
+
+/// An opaque handle to a parsed schema.
+pub struct TsdsSchema(Ty);
+
+/// Parses a schema from its JSON representation, storing a new handle in `*out`.
+///
+/// # Safety
+///
+/// `json` must point to `len` readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tsds_schema_parse(json: *const u8, len: usize, out: *mut *mut TsdsSchema) -> c_int {
+    if json.is_null() || out.is_null() {
+        return TSDS_NULL_POINTER;
+    }
+    catch_panic(|| match serde_json::from_slice(slice::from_raw_parts(json, len)) {
+        Ok(ty) => {
+            *out = Box::into_raw(Box::new(TsdsSchema(ty)));
+            TSDS_OK
+        }
+        Err(_) => TSDS_INVALID_SCHEMA,
+    })
+}
+
+/// Releases a schema handle. Passing null does nothing.
+///
+/// # Safety
+///
+/// `schema` must be null or a handle from [`tsds_schema_parse`] which has not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn tsds_schema_free(schema: *mut TsdsSchema) {
+    if !schema.is_null() {
+        catch_panic(|| {
+            drop(Box::from_raw(schema));
+            TSDS_OK
+        });
+    }
+}
+
+/// Encodes JSON text as a flatbin document.
+///
+/// # Safety
+///
+/// `schema` must be a live handle, `json` must point to `len` readable bytes, `out` must point to `out_cap`
+/// writable bytes, and `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tsds_encode(
+    schema: *const TsdsSchema,
+    json: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> c_int {
+    convert(schema, json, len, out, out_cap, out_len, fast::deserialize_bytes)
+}
+
+/// Decodes a flatbin document as JSON text, validating it first.
+///
+/// # Safety
+///
+/// `schema` must be a live handle, `flatbin` must point to `len` readable bytes, `out` must point to `out_cap`
+/// writable bytes, and `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tsds_decode(
+    schema: *const TsdsSchema,
+    flatbin: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> c_int {
+    convert(schema, flatbin, len, out, out_cap, out_len, fast::serialize_bytes)
+}
+
+unsafe fn convert(
+    schema: *const TsdsSchema,
+    input: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+    f: fn(&Ty, &[u8]) -> serde_json::Result<Vec<u8>>,
+) -> c_int {
+    if schema.is_null() || input.is_null() || out.is_null() || out_len.is_null() {
+        return TSDS_NULL_POINTER;
+    }
+    catch_panic(|| {
+        let Ok(bytes) = f(&(*schema).0, slice::from_raw_parts(input, len)) else {
+            return TSDS_INVALID_INPUT;
+        };
+        *out_len = bytes.len();
+        if bytes.len() > out_cap {
+            return TSDS_BUFFER_TOO_SMALL;
+        }
+        out.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+        TSDS_OK
+    })
+}
+
+/// Runs `f`, returning [`TSDS_PANIC`] if it panics, as unwinding into a C caller is undefined behaviour.
+fn catch_panic(f: impl FnOnce() -> c_int) -> c_int {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(TSDS_PANIC)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn roundtrip() {
+        let schema_json = br#"{ "struct": { "fields": [{ "name": "a", "ty": "u64" }] } }"#;
+        let json = br#"{"a":300}"#;
+        unsafe {
+            let mut schema = ptr::null_mut();
+            assert_eq!(
+                tsds_schema_parse(schema_json.as_ptr(), schema_json.len(), &mut schema),
+                TSDS_OK
+            );
+
+            let (mut encoded, mut encoded_len) = ([0; 16], 0);
+            let status = tsds_encode(
+                schema,
+                json.as_ptr(),
+                json.len(),
+                encoded.as_mut_ptr(),
+                1,
+                &mut encoded_len,
+            );
+            assert_eq!(status, TSDS_BUFFER_TOO_SMALL);
+            assert_eq!(encoded_len, 2);
+            let status = tsds_encode(
+                schema,
+                json.as_ptr(),
+                json.len(),
+                encoded.as_mut_ptr(),
+                16,
+                &mut encoded_len,
+            );
+            assert_eq!(status, TSDS_OK);
+
+            let (mut decoded, mut decoded_len) = ([0; 16], 0);
+            let status = tsds_decode(
+                schema,
+                encoded.as_ptr(),
+                encoded_len,
+                decoded.as_mut_ptr(),
+                16,
+                &mut decoded_len,
+            );
+            assert_eq!(status, TSDS_OK);
+            assert_eq!(&decoded[..decoded_len], json);
+
+            let status = tsds_encode(schema, b"[]".as_ptr(), 2, decoded.as_mut_ptr(), 16, &mut decoded_len);
+            assert_eq!(status, TSDS_INVALID_INPUT);
+            tsds_schema_free(schema);
+
+            assert_eq!(tsds_schema_parse(b"3".as_ptr(), 1, &mut schema), TSDS_INVALID_SCHEMA);
+        }
+    }
+
+    #[test]
+    fn catch_panic() {
+        assert_eq!(super::catch_panic(|| TSDS_OK), TSDS_OK);
+        assert_eq!(super::catch_panic(|| panic!("in a test")), TSDS_PANIC);
+    }
+}
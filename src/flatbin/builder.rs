@@ -1,6 +1,20 @@
-use super::{util::VarInt, Flatbin, FlatbinBuf};
+use super::{
+    util::{uint_len, VarInt},
+    Flatbin, FlatbinBuf,
+};
+use crate::ty::Field;
 use arrayvec::ArrayVec;
-
+use std::{
+    io::{self, Read},
+    num::NonZeroUsize,
+};
+
+/// A writer of a single value into a buffer.
+///
+/// A builder mutably borrows the buffer it writes to, along with the state of the enclosing tuple or array, so
+/// the buffer can't be read or sent elsewhere until the builder is dropped. The builder itself is `Send` and
+/// `Sync`, but it may only be moved to another thread (or held across an `.await`) within that borrow, e.g. by a
+/// scoped thread.
 pub struct Builder<'a> {
     buffer: &'a mut Vec<u8>,
     last_child: Option<&'a mut Option<usize>>,
@@ -14,6 +28,8 @@ pub struct TupleBuilder<'a> {
 
 pub struct VectorBuilder<'a> {
     start: usize,
+    /// The length of a count already present at `start`, which is replaced when the builder is dropped.
+    count_len: usize,
     count: usize,
     last_child: Option<usize>,
     buffer: &'a mut Vec<u8>,
@@ -61,13 +77,18 @@ impl<'a> Builder<'a> {
 
     pub fn write_u64(mut self, value: u64) {
         self.begin_write();
-        let count = (71 - value.leading_zeros() as usize) / 8;
+        let count = uint_len(value);
         let bytes = value.to_le_bytes();
         self.buffer.extend(&bytes[..count]);
     }
 
     pub fn write_i64(self, value: i64) {
-        let value = if value < 0 { !(value << 1) } else { value << 1 };
+        // Zigzag encoding, which maps `i64::MIN` to `u64::MAX` with the top bit shifted out
+        let value = if value < 0 {
+            !value.wrapping_shl(1)
+        } else {
+            value.wrapping_shl(1)
+        };
         self.write_u64(value as u64)
     }
 
@@ -79,11 +100,50 @@ impl<'a> Builder<'a> {
         self.write_bytes(&value.to_le_bytes())
     }
 
+    /// Writes a float, normalizing `-0.0` to `0.0` and any NaN to `f64::NAN` so equal values have equal bytes.
+    pub fn write_f64_canonical(self, value: f64) {
+        self.write_f64(canonical_f64(value))
+    }
+
+    /// Writes a packed float array, as described by [`Ty::FloatVec`](crate::ty::Ty::FloatVec).
+    pub fn write_float_vec(mut self, values: &[f64]) {
+        self.begin_write();
+        if !values.is_empty() {
+            self.buffer.extend(VarInt::from_usize(values.len()).iter());
+        }
+        for value in values {
+            self.buffer.extend(value.to_le_bytes());
+        }
+    }
+
     pub fn write_bytes(mut self, bytes: &[u8]) {
         self.begin_write();
         self.buffer.extend(bytes);
     }
 
+    /// Writes a byte string as an array of chunks of `chunk_size` bytes, as described by
+    /// [`Ty::ChunkedBytes`](crate::ty::Ty::ChunkedBytes).
+    pub fn write_chunked_bytes(self, bytes: &[u8], chunk_size: NonZeroUsize) {
+        let mut vector = self.start_vector();
+        for chunk in bytes.chunks(chunk_size.get()) {
+            vector.as_builder().write_bytes(chunk);
+        }
+    }
+
+    /// Writes a byte string by streaming it from `reader` directly into the buffer.
+    ///
+    /// The node's length header is not known until the reader is exhausted, so, as with every other node,
+    /// it is backfilled when the next sibling is written (or elided if this is the last child).
+    /// If the reader fails, the partially written bytes are discarded and an empty node is left in their place.
+    pub fn write_bytes_from<R: Read>(mut self, mut reader: R) -> io::Result<()> {
+        self.begin_write();
+        let start = self.buffer.len();
+        reader
+            .read_to_end(self.buffer)
+            .inspect_err(|_| self.buffer.truncate(start))?;
+        Ok(())
+    }
+
     pub fn write_str(self, str: &str) {
         self.write_bytes(str.as_bytes())
     }
@@ -136,6 +196,11 @@ impl<'a> TupleBuilder<'a> {
         self.as_builder().write(value)
     }
 
+    /// Writes each value of a tuple as a child, in order, e.g. `tuple.write_all((40, "Hello", 50))`.
+    pub fn write_all<T: WritableTuple>(&mut self, values: T) {
+        values.write_all(self)
+    }
+
     pub fn start_tuple(&mut self) -> TupleBuilder<'_> {
         self.as_builder().start_tuple()
     }
@@ -145,18 +210,55 @@ impl<'a> TupleBuilder<'a> {
     }
 
     pub fn end(self) {}
+
+    /// Ends the tuple, writing a header for the last element as well, so that readers can tell where it ends (see
+    /// [`Flatbin::read_struct`]).
+    pub fn end_headed(self) {
+        if let Some(offset) = self.last_child {
+            let header = make_header(&self.buffer[offset..]);
+            self.buffer.splice(offset..offset, header);
+        }
+    }
+
+    /// Ends the tuple of a struct's field values, as [`end_headed`](Self::end_headed) does if the struct is
+    /// [extensible](Field::extensible).
+    pub fn end_struct(self, fields: &[Field]) {
+        match Field::extensible(fields) {
+            true => self.end_headed(),
+            false => self.end(),
+        }
+    }
 }
 
 impl<'a> VectorBuilder<'a> {
     fn new(buffer: &'a mut Vec<u8>) -> Self {
         VectorBuilder {
             start: buffer.len(),
+            count_len: 0,
             count: 0,
             last_child: None,
             buffer,
         }
     }
 
+    /// Resumes writing an existing vector whose body (including a count of `count_len` bytes) starts at `start`
+    /// and runs to the end of the buffer, with its last element at `last_child`.
+    pub(crate) fn resume(
+        buffer: &'a mut Vec<u8>,
+        start: usize,
+        count_len: usize,
+        count: usize,
+        last_child: Option<usize>,
+    ) -> Self {
+        VectorBuilder {
+            start,
+            count_len,
+            count,
+            last_child,
+            buffer,
+        }
+    }
+
     pub fn as_builder(&mut self) -> Builder<'_> {
         Builder {
             buffer: self.buffer,
@@ -181,21 +283,109 @@ impl<'a> VectorBuilder<'a> {
         self.count
     }
 
+    /// Reserves capacity for at least `additional` more bytes in the underlying buffer.
+    pub fn reserve(&mut self, additional: usize) {
+        self.buffer.reserve(additional);
+    }
+
     pub fn end(self) -> usize {
         self.count
     }
 }
 
+/// Builds a run-length encoded array (see [`Ty::RleArray`](crate::ty::Ty::RleArray)) one element at a time,
+/// merging each element into the previous run if their encodings are equal.
+pub struct RleBuilder<'a> {
+    vector: VectorBuilder<'a>,
+    run: FlatbinBuf,
+    next: FlatbinBuf,
+    count: u64,
+    flushed: u64,
+}
+
+impl<'a> RleBuilder<'a> {
+    pub fn new(builder: Builder<'a>) -> Self {
+        RleBuilder {
+            vector: builder.start_vector(),
+            run: FlatbinBuf::new(),
+            next: FlatbinBuf::new(),
+            count: 0,
+            flushed: 0,
+        }
+    }
+
+    /// Returns a builder for the next element, which is added once [`push`](Self::push) is called.
+    pub fn as_builder(&mut self) -> Builder<'_> {
+        self.next.reset_builder()
+    }
+
+    /// Adds the element written through [`as_builder`](Self::as_builder) since the last call.
+    pub fn push(&mut self) {
+        if self.count > 0 && self.next == self.run {
+            self.count += 1;
+            return;
+        }
+        self.flush();
+        std::mem::swap(&mut self.run, &mut self.next);
+        self.count = 1;
+    }
+
+    /// Writes an element, exactly as `as_builder().write(value)` followed by `push()` would.
+    pub fn write<T: Writable>(&mut self, value: T) {
+        self.as_builder().write(value);
+        self.push();
+    }
+
+    /// Returns the number of elements added so far.
+    pub fn count(&self) -> u64 {
+        self.flushed + self.count
+    }
+
+    pub fn end(mut self) {
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        if self.count > 0 {
+            let mut run = self.vector.start_tuple();
+            run.write(self.count);
+            run.as_builder().copy(&self.run);
+            run.end();
+            self.flushed += self.count;
+        }
+    }
+}
+
+/// Writes each item as a new element, exactly as `write` would.
+impl<T: Writable> Extend<T> for VectorBuilder<'_> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.write(value);
+        }
+    }
+}
+
 impl Drop for VectorBuilder<'_> {
     fn drop(&mut self) {
+        // An empty vector is canonically encoded as an empty body, so the count is omitted
         if self.count > 0 {
-            let start = self.start;
+            // A resumed vector's count is overwritten in place, unless it has grown by a byte
+            let range = self.start..(self.start + self.count_len);
             let count = VarInt::from_usize(self.count);
-            self.buffer.splice(start..start, count.iter().copied());
+            self.buffer.splice(range, count.iter().copied());
         }
     }
 }
 
+/// Normalizes `-0.0` to `0.0` and any NaN to `f64::NAN`.
+pub(crate) fn canonical_f64(value: f64) -> f64 {
+    match value {
+        _ if value.is_nan() => f64::NAN,
+        0.0 => 0.0,
+        _ => value,
+    }
+}
+
 fn make_header(body: &[u8]) -> ArrayVec<u8, 10> {
     match body {
         // Empty body
@@ -223,6 +413,18 @@ fn make_header(body: &[u8]) -> ArrayVec<u8, 10> {
     }
 }
 
+/// The length of the header [`make_header`] writes for a body of `len` bytes, where a single byte body is assumed
+/// not to be a 7-bit literal.
+pub(crate) fn header_len(len: usize) -> usize {
+    match len {
+        0 | 1 => 1,
+        _ => match ((71 - len.leading_zeros()) / 7) as usize {
+            count if count > 6 => 9,
+            count => count,
+        },
+    }
+}
+
 pub trait Writable {
     fn write(self, builder: Builder);
 }
@@ -237,6 +439,7 @@ macro_rules! impl_writable {
     };
 }
 
+impl_writable!(bool, write_bool);
 impl_writable!(u8, write_u8);
 impl_writable!(u16, write_u64);
 impl_writable!(u32, write_u64);
@@ -262,10 +465,86 @@ impl Writable for &str {
     }
 }
 
+/// A tuple of [`Writable`] values, which [`TupleBuilder::write_all`] writes as consecutive children.
+pub trait WritableTuple {
+    fn write_all(self, tuple: &mut TupleBuilder);
+}
+
+macro_rules! impl_writable_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Writable),+> WritableTuple for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write_all(self, tuple: &mut TupleBuilder) {
+                let ($($name,)+) = self;
+                $(tuple.write($name);)+
+            }
+        }
+    };
+}
+
+impl_writable_tuple!(A);
+impl_writable_tuple!(A, B);
+impl_writable_tuple!(A, B, C);
+impl_writable_tuple!(A, B, C, D);
+impl_writable_tuple!(A, B, C, D, E);
+impl_writable_tuple!(A, B, C, D, E, F);
+impl_writable_tuple!(A, B, C, D, E, F, G);
+impl_writable_tuple!(A, B, C, D, E, F, G, H);
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_header_len() {
+        for len in (0..100_000).chain([(1 << 21) - 1, 1 << 21]) {
+            let body = vec![0xff; len];
+            assert_eq!(header_len(len), make_header(&body).len(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn test_extend() {
+        let values = [0u64, 127, 128, 300, u64::MAX];
+
+        let mut manual = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut manual).start_vector();
+        for value in values {
+            vec.as_builder().write_u64(value);
+        }
+        assert_eq!(vec.end(), 5);
+
+        let mut extended = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut extended).start_vector();
+        vec.extend(values);
+        assert_eq!(vec.end(), 5);
+        assert_eq!(extended.as_bytes(), manual.as_bytes());
+
+        // Extending with nothing still leaves a (canonical) empty vector
+        let mut empty = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut empty).start_vector();
+        vec.extend(Vec::<u64>::new());
+        assert_eq!(vec.end(), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_write_all() {
+        let mut manual = FlatbinBuf::new();
+        let mut tup = Builder::new(&mut manual).start_tuple();
+        tup.as_builder().write_u32(40);
+        tup.as_builder().write_str("Hello");
+        tup.as_builder().write_f64(0.5);
+        tup.end();
+
+        let mut written = FlatbinBuf::new();
+        let mut tup = Builder::new(&mut written).start_tuple();
+        tup.write_all((40u32, "Hello", 0.5));
+        tup.end();
+
+        assert_eq!(written.as_bytes(), manual.as_bytes());
+    }
+
     #[test]
     fn test_builder() {
         let mut buffer = FlatbinBuf::new();
@@ -311,4 +590,23 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn test_write_bytes_from() {
+        let blob: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+        let mut expected = FlatbinBuf::new();
+        let mut tup = Builder::new(&mut expected).start_tuple();
+        tup.as_builder().write_bytes(&blob);
+        tup.as_builder().write_u8(7);
+        tup.end();
+
+        let mut buffer = FlatbinBuf::new();
+        let mut tup = Builder::new(&mut buffer).start_tuple();
+        tup.as_builder().write_bytes_from(&blob[..]).unwrap();
+        tup.as_builder().write_u8(7);
+        tup.end();
+
+        assert_eq!(buffer.data, expected.data);
+    }
 }
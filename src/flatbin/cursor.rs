@@ -0,0 +1,162 @@
+use super::{Error, Flatbin, Result, Sequence, TypedFlatbin};
+use crate::ty::{Field, Ty};
+
+/// A stateful reader which walks the elements of a tuple or array one at a time, for decoding into a custom
+/// representation.
+///
+/// The cursor holds a stack of scopes, each a sequence whose elements are being read. [`read_next`](Self::read_next)
+/// reads the next element of the innermost scope. To read a nested sequence element by element, enter it with
+/// [`push_tuple`](Self::push_tuple) or [`push_array`](Self::push_array), which consumes it as the next element of
+/// the current scope, and leave it with [`pop`](Self::pop), which skips any elements left unread and resumes the
+/// scope it was entered from. A nested sequence can instead be read in one go through the [`TypedFlatbin`] that
+/// `read_next` returns for it.
+///
+/// ```
+/// use talk_serde_dyn_schema::{array_def, flatbin::Cursor, struct_def, ty::Ty};
+///
+/// let ty = struct_def!({ "name": Ty::String, "scores": array_def!(Ty::U64) });
+/// let doc = talk_serde_dyn_schema::fast::deserialize(&ty, r#"{ "name": "Ada", "scores": [3, 4] }"#).unwrap();
+///
+/// let mut cursor = Cursor::new(doc.read_tuple(2).unwrap());
+/// assert_eq!(cursor.read_next(&Ty::String).unwrap().as_str().unwrap(), "Ada");
+/// cursor.push_array().unwrap();
+/// let mut total = 0;
+/// while cursor.remaining() > 0 {
+///     total += cursor.read_next(&Ty::U64).unwrap().as_u64().unwrap();
+/// }
+/// cursor.pop().unwrap();
+/// assert_eq!(total, 7);
+/// ```
+pub struct Cursor<'a> {
+    /// The sequences entered, innermost last.
+    scopes: Vec<Sequence<'a>>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Returns a cursor at the first element of `sequence`.
+    pub fn new(sequence: Sequence<'a>) -> Self {
+        Self { scopes: vec![sequence] }
+    }
+
+    /// Returns the number of elements left to read in the current scope.
+    pub fn remaining(&self) -> usize {
+        self.scopes.last().map_or(0, Sequence::len)
+    }
+
+    /// Returns the number of scopes entered, which is one for a new cursor.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Reads the next element of the current scope as a value of type `ty`, failing with [`Error::UnexpectedEOF`] if
+    /// the scope has no elements left or the data ends early.
+    pub fn read_next(&mut self, ty: &'a Ty) -> Result<TypedFlatbin<'a>> {
+        Ok(TypedFlatbin::new(ty, self.next_node()?))
+    }
+
+    /// Skips the next element of the current scope.
+    pub fn skip(&mut self) -> Result<()> {
+        self.next_node().map(drop)
+    }
+
+    /// Enters the next element of the current scope as a tuple of `count` elements.
+    pub fn push_tuple(&mut self, count: usize) -> Result<()> {
+        let tuple = self.next_node()?.read_tuple(count)?;
+        self.scopes.push(tuple);
+        Ok(())
+    }
+
+    /// Enters the next element of the current scope as the values of a struct with the given fields, as read by
+    /// [`Flatbin::read_struct`].
+    pub fn push_struct(&mut self, fields: &[Field]) -> Result<()> {
+        let values = self.next_node()?.read_struct(fields)?;
+        self.scopes.push(values);
+        Ok(())
+    }
+
+    /// Enters the next element of the current scope as an array.
+    pub fn push_array(&mut self) -> Result<()> {
+        let array = self.next_node()?.read_array()?;
+        self.scopes.push(array);
+        Ok(())
+    }
+
+    /// Leaves the current scope, skipping any elements left in it, and resumes the scope it was entered from. The
+    /// outermost scope can't be left, so this fails with [`Error::TypeMismatch`] at depth one.
+    pub fn pop(&mut self) -> Result<()> {
+        match self.scopes.len() {
+            0 | 1 => Err(Error::TypeMismatch),
+            _ => {
+                self.scopes.pop();
+                Ok(())
+            }
+        }
+    }
+
+    fn next_node(&mut self) -> Result<&'a Flatbin> {
+        let scope = self.scopes.last_mut().ok_or(Error::UnexpectedEOF)?;
+        let node = match scope.count {
+            0 => return Err(Error::UnexpectedEOF),
+            // The optional fields of a struct that weren't written are empty
+            _ if scope.headed && scope.data.is_empty() => Flatbin::from_bytes(&[]),
+            1 if !scope.headed => Flatbin::from_bytes(std::mem::take(&mut scope.data)),
+            _ => Flatbin::read_node(&mut scope.data)?,
+        };
+        scope.count -= 1;
+        Ok(node)
+    }
+}
+
+impl<'a> From<Sequence<'a>> for Cursor<'a> {
+    fn from(sequence: Sequence<'a>) -> Self {
+        Self::new(sequence)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn cursor() {
+        let language = struct_def!({ "name": Ty::String, "liked": Ty::Bool });
+        let ty = struct_def!({
+            "name": Ty::String,
+            "languages": array_def!(language.clone()),
+            "age": Ty::U64,
+        });
+        let value = serde_json::json!({
+            "name": "Alexander",
+            "languages": [{ "name": "Rust", "liked": true }, { "name": "PHP", "liked": false }],
+            "age": 27
+        });
+        let bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+
+        let mut cursor = Cursor::new(bytes.read_tuple(3).unwrap());
+        assert_eq!(cursor.read_next(&Ty::String).unwrap().as_str().unwrap(), "Alexander");
+        cursor.push_array().unwrap();
+        assert_eq!((cursor.depth(), cursor.remaining()), (2, 2));
+        cursor.push_tuple(2).unwrap();
+        assert_eq!(cursor.read_next(&Ty::String).unwrap().as_str().unwrap(), "Rust");
+        assert!(cursor.read_next(&Ty::Bool).unwrap().as_bool().unwrap());
+        assert!(matches!(cursor.read_next(&Ty::Bool), Err(Error::UnexpectedEOF)));
+        cursor.pop().unwrap();
+        // Popping skips whatever is left of the scope
+        let Ty::Struct { fields } = &language else {
+            unreachable!()
+        };
+        cursor.push_struct(fields).unwrap();
+        assert_eq!(cursor.read_next(&Ty::String).unwrap().as_str().unwrap(), "PHP");
+        cursor.pop().unwrap();
+        cursor.pop().unwrap();
+        assert_eq!(cursor.read_next(&Ty::U64).unwrap().as_u64().unwrap(), 27);
+        assert_eq!(cursor.remaining(), 0);
+        assert!(matches!(cursor.pop(), Err(Error::TypeMismatch)));
+
+        // Truncated data fails rather than reading past it
+        let truncated = Flatbin::from_bytes(&bytes.as_bytes()[..3]);
+        let mut cursor = Cursor::new(truncated.read_tuple(3).unwrap());
+        cursor.skip().unwrap_err();
+    }
+}
@@ -0,0 +1,156 @@
+//! A self-describing encoding of arbitrary JSON values, which can be read without a schema.
+//!
+//! Every value is a tuple of two nodes: a type tag, followed by the value itself. Tags are below 0x80, so
+//! each costs exactly one byte. The values are encoded as for the equivalent [`Ty`](crate::ty::Ty), except that
+//! `null` is an empty node, arrays are arrays of tagged values, and objects are arrays of `(key, value)`
+//! tuples of a string and a tagged value.
+
+use super::{Builder, Error, Flatbin, FlatbinBuf, Result};
+use crate::{options::DEFAULT_MAX_DEPTH, JsonValue};
+
+pub const TAG_NULL: u8 = 0;
+pub const TAG_BOOL: u8 = 1;
+pub const TAG_U64: u8 = 2;
+pub const TAG_I64: u8 = 3;
+pub const TAG_F64: u8 = 4;
+pub const TAG_STRING: u8 = 5;
+pub const TAG_ARRAY: u8 = 6;
+pub const TAG_OBJECT: u8 = 7;
+
+/// Encodes a JSON value, tagging every node with its type.
+pub fn encode(value: &JsonValue, builder: Builder) {
+    let mut tuple = builder.start_tuple();
+    match value {
+        JsonValue::Null => {
+            tuple.write(TAG_NULL);
+            tuple.as_builder().write_void();
+        }
+        JsonValue::Bool(value) => tuple.write_all((TAG_BOOL, *value)),
+        JsonValue::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(value), _) => tuple.write_all((TAG_U64, value)),
+            (None, Some(value)) => tuple.write_all((TAG_I64, value)),
+            _ => tuple.write_all((TAG_F64, number.as_f64().unwrap_or(f64::NAN))),
+        },
+        JsonValue::String(value) => tuple.write_all((TAG_STRING, value.as_str())),
+        JsonValue::Array(values) => {
+            tuple.write(TAG_ARRAY);
+            let mut vector = tuple.start_vector();
+            for value in values {
+                encode(value, vector.as_builder());
+            }
+            vector.end();
+        }
+        JsonValue::Object(entries) => {
+            tuple.write(TAG_OBJECT);
+            let mut vector = tuple.start_vector();
+            for (key, value) in entries {
+                let mut entry = vector.start_tuple();
+                entry.write(key.as_str());
+                encode(value, entry.as_builder());
+                entry.end();
+            }
+            vector.end();
+        }
+    }
+    tuple.end();
+}
+
+/// Encodes a JSON value into a new buffer, tagging every node with its type.
+pub fn encode_alloc(value: &JsonValue) -> FlatbinBuf {
+    let mut buffer = FlatbinBuf::new();
+    encode(value, Builder::new(&mut buffer));
+    buffer
+}
+
+/// Decodes a value written by [`encode`].
+///
+/// Values may be nested up to [`DEFAULT_MAX_DEPTH`] arrays and objects deep.
+pub fn decode(value: &Flatbin) -> Result<JsonValue> {
+    decode_inner(value, 0)
+}
+
+fn decode_inner(value: &Flatbin, depth: usize) -> Result<JsonValue> {
+    let mut tuple = value.read_tuple(2)?.iter();
+    let (Some(tag), Some(value)) = (tuple.next(), tuple.next()) else {
+        return Err(Error::UnexpectedEOF);
+    };
+    let tag = tag.read_u64()?;
+    if (tag == TAG_ARRAY as u64 || tag == TAG_OBJECT as u64) && depth >= DEFAULT_MAX_DEPTH {
+        return Err(Error::DepthExceeded);
+    }
+    Ok(match u8::try_from(tag).map_err(|_| Error::UnknownTag(tag))? {
+        TAG_NULL => value.read_void().map(|_| JsonValue::Null)?,
+        TAG_BOOL => value.read_bool()?.into(),
+        TAG_U64 => value.read_u64()?.into(),
+        TAG_I64 => value.read_i64()?.into(),
+        TAG_F64 => value.read_f64()?.into(),
+        TAG_STRING => value.read_str()?.into(),
+        TAG_ARRAY => value
+            .read_array()?
+            .try_iter()
+            .map(|value| decode_inner(value?, depth + 1))
+            .collect::<Result<Vec<_>>>()?
+            .into(),
+        TAG_OBJECT => value
+            .read_array()?
+            .try_iter()
+            .map(|entry| {
+                let mut entry = entry?.read_tuple(2)?.iter();
+                let (Some(key), Some(value)) = (entry.next(), entry.next()) else {
+                    return Err(Error::UnexpectedEOF);
+                };
+                Ok((key.read_str()?.to_string(), decode_inner(value, depth + 1)?))
+            })
+            .collect::<Result<serde_json::Map<_, _>>>()?
+            .into(),
+        _ => return Err(Error::UnknownTag(tag)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode_alloc, TAG_ARRAY, TAG_OBJECT};
+    use crate::{
+        flatbin::{Error, Flatbin},
+        JsonValue,
+    };
+
+    #[test]
+    fn roundtrip() {
+        let value = serde_json::json!({
+            "name": "Alice",
+            "age": 31,
+            "balance": -12,
+            "height": 1.5,
+            "admin": false,
+            "manager": null,
+            "tags": ["a", 1, [true], {}],
+            "empty": [],
+        });
+        let bytes = encode_alloc(&value);
+        assert_eq!(decode(&bytes).unwrap(), value);
+
+        for value in [JsonValue::Null, 0.into(), "".into(), serde_json::json!([[]])] {
+            assert_eq!(decode(&encode_alloc(&value)).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(decode(Flatbin::from_bytes(&[0x7f, 0x80])).is_err());
+        // An array or object whose count runs past the end of the data
+        for tag in [TAG_ARRAY, TAG_OBJECT] {
+            let bytes = [tag, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f, 0];
+            assert!(matches!(
+                decode(Flatbin::from_bytes(&bytes)),
+                Err(Error::MissingElements { .. })
+            ));
+        }
+
+        let mut nested = serde_json::json!(0);
+        for _ in 0..200 {
+            nested = serde_json::json!([nested]);
+        }
+        assert!(decode(&encode_alloc(&nested)).is_err());
+    }
+}
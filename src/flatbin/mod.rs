@@ -1,8 +1,17 @@
+pub use crate::fast::{DeserializeInto, SerializeAs};
+use crate::ty::{Field, MapLayout, Ty, Variant};
 pub use builder::*;
-use std::hint::unreachable_unchecked;
+pub use cursor::*;
+pub use set::*;
+use std::{borrow::Cow, hint::unreachable_unchecked};
 use thiserror::Error;
+pub use typed::*;
 
 mod builder;
+mod cursor;
+pub mod dynamic;
+mod set;
+mod typed;
 mod util;
 
 #[derive(Error, Debug)]
@@ -15,11 +24,61 @@ pub enum Error {
     UnexpectedEOF,
     #[error("a string was not valid UTF-8")]
     InvalidUTF8,
+    #[error("the value's type does not support the requested operation")]
+    TypeMismatch,
+    #[error("no such field: {name}")]
+    NoSuchField { name: Box<str> },
+    #[error("index out of bounds")]
+    IndexOutOfBounds,
+    #[error("unknown type tag {0}")]
+    UnknownTag(u64),
+    #[error("maximum nesting depth exceeded")]
+    DepthExceeded,
+    #[error("expected {expected} elements, but the data ends after {found}")]
+    MissingElements { expected: usize, found: usize },
+    #[error("variant index {0} is out of range")]
+    InvalidVariant(u64),
+    #[error("the data is not aligned for the requested view")]
+    Misaligned,
+    #[error("the data is little-endian, but the target is big-endian")]
+    BigEndian,
+    #[error("duplicate map key: {key}")]
+    DuplicateKey { key: Box<str> },
+    #[error("the keys of an ordered map are not sorted")]
+    UnorderedKeys,
+    #[error("unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("run-length encoded array has more than {} elements", MAX_RLE_LEN)]
+    RleTooLong,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Debug, Default)]
+/// The maximum number of elements in a run-length encoded array (see [`Ty::RleArray`]).
+///
+/// A run's count is a single varint, so a document of a few bytes could otherwise expand to trillions of elements.
+/// Longer arrays fail with [`Error::RleTooLong`] when read, validated or deserialized.
+pub const MAX_RLE_LEN: usize = 1 << 24;
+
+/// The version of the encoding written by this crate, as prefixed to documents by [`FlatbinBuf::with_version`].
+///
+/// The version is bumped whenever the encoding changes such that an existing document would no longer read
+/// correctly, and each bump comes with a migration from the version before it, so that
+/// [`FlatbinBuf::from_versioned`] reads documents of every earlier version. Changes which existing documents
+/// already satisfy, such as new types or new optional struct fields, don't bump it.
+///
+/// Documents are written without a version unless one is asked for, and any byte may begin a document, so whether
+/// stored bytes start with a version can't be detected: it's up to whoever stores them to know. Documents stored
+/// without a version can be read with [`FlatbinBuf::from_unversioned`], which takes them to be of version 0.
+pub const VERSION: u8 = 0;
+
+/// An owned document.
+///
+/// Documents are compared and hashed by their bytes, consistently with [`Flatbin`], so a `HashSet<FlatbinBuf>`
+/// can be queried with a `&Flatbin`.
+///
+/// A buffer is plain bytes, so it is `Send` and `Sync`, and may be moved to or shared with other threads.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct FlatbinBuf {
     data: Vec<u8>,
 }
@@ -40,9 +99,106 @@ impl FlatbinBuf {
     pub fn clear(&mut self) {
         self.data.clear();
     }
+
+    /// Returns a builder which appends elements to the array held in this buffer, which may be empty.
+    ///
+    /// This only works when the array is the root node. Finding the array's last element means skipping over
+    /// the headers of every element, and the count at the front of the array is rewritten once the builder is
+    /// dropped, shifting the whole array by a byte whenever the count's varint grows (e.g. from 127 to 128
+    /// elements). Appending many elements through one builder therefore amortizes these costs.
+    pub fn append_to_array(&mut self) -> Result<VectorBuilder<'_>> {
+        let mut data = self.data.as_slice();
+        let count = if data.is_empty() {
+            0
+        } else {
+            Flatbin::read_varint(&mut data)? as usize
+        };
+        let count_len = self.data.len() - data.len();
+        let last_child = match count {
+            0 if !data.is_empty() => return Err(Error::UnexpectedLength),
+            0 => None,
+            count => {
+                for _ in 1..count {
+                    Flatbin::read_node(&mut data)?;
+                }
+                Some(self.data.len() - data.len())
+            }
+        };
+        Ok(VectorBuilder::resume(&mut self.data, 0, count_len, count, last_child))
+    }
+
+    /// Clears the buffer and returns a builder which writes a new document into it.
+    ///
+    /// As with [`clear`](Self::clear), this discards the previous document, so any `Flatbin` views of it must
+    /// have been dropped. Writing through [`Builder::new`] without clearing first appends a second root node
+    /// instead.
+    pub fn reset_builder(&mut self) -> Builder<'_> {
+        self.clear();
+        Builder::new(self)
+    }
+
+    /// Returns the document's bytes prefixed with the format [`VERSION`], for storing a document which may later be
+    /// read by a newer version of this crate. Read them back with [`from_versioned`](Self::from_versioned).
+    pub fn with_version(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.len());
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Reads a document written by [`with_version`](Self::with_version), migrating it to the current encoding if it
+    /// was written in an earlier version. Fails with [`Error::UnexpectedEOF`] if there is no version byte, or with
+    /// [`Error::UnsupportedVersion`] if the document is of a newer version than this crate writes.
+    pub fn from_versioned(mut bytes: Vec<u8>) -> Result<Self> {
+        let version = *bytes.first().ok_or(Error::UnexpectedEOF)?;
+        bytes.drain(..1);
+        Self::migrate(version, bytes)
+    }
+
+    /// Reads a document stored without a version, as written before versioning was introduced, migrating it from
+    /// version 0 to the current encoding. The bytes are taken to be a document as they are, so a document written
+    /// by [`with_version`](Self::with_version) must be read with [`from_versioned`](Self::from_versioned) instead.
+    pub fn from_unversioned(bytes: Vec<u8>) -> Result<Self> {
+        Self::migrate(0, bytes)
+    }
+
+    fn migrate(version: u8, data: Vec<u8>) -> Result<Self> {
+        match version {
+            // Each new version adds an arm here migrating documents of the version before it
+            0 => Ok(Self { data }),
+            version => Err(Error::UnsupportedVersion(version)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.data.truncate(len)
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
 }
 
+/// A borrowed document, or a node within one.
+///
+/// A `Flatbin` is always a view into some underlying buffer, and every node reached from it (the elements
+/// of [`read_tuple`](Self::read_tuple) and [`read_array`](Self::read_array), or the result of
+/// [`seek`](Self::seek)) is a sub-slice of that same buffer. They therefore borrow the buffer for the same
+/// lifetime as their parent, not just for the lifetime of the intermediate `Sequence`, so references to nodes
+/// at any depth can be collected together (e.g. into an index) and kept for as long as the buffer is.
+///
+/// `Flatbin` is `Sync`, so a `&Flatbin` (and a [`Sequence`] or [`TypedFlatbin`] of one) may be sent to other
+/// threads, e.g. to read the elements of an array in parallel.
 #[repr(transparent)]
+#[derive(PartialEq, Eq, Hash)]
 pub struct Flatbin {
     data: [u8],
 }
@@ -55,18 +211,103 @@ impl core::ops::Deref for FlatbinBuf {
     }
 }
 
+impl AsRef<[u8]> for Flatbin {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for FlatbinBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Takes ownership of the bytes of a document without validating them, as with [`Flatbin::from_bytes`].
+impl From<Vec<u8>> for FlatbinBuf {
+    fn from(data: Vec<u8>) -> Self {
+        FlatbinBuf { data }
+    }
+}
+
+impl From<FlatbinBuf> for Vec<u8> {
+    fn from(buffer: FlatbinBuf) -> Self {
+        buffer.data
+    }
+}
+
+impl std::borrow::Borrow<Flatbin> for FlatbinBuf {
+    fn borrow(&self) -> &Flatbin {
+        self
+    }
+}
+
+impl ToOwned for Flatbin {
+    type Owned = FlatbinBuf;
+
+    fn to_owned(&self) -> FlatbinBuf {
+        FlatbinBuf {
+            data: self.data.to_vec(),
+        }
+    }
+}
+
 impl Flatbin {
+    /// Interprets `bytes` as a document without validating it.
+    ///
+    /// The cast is sound for any byte slice, whatever memory backs it (including a file mapping), and the
+    /// result borrows `bytes` for the same lifetime. Malformed documents make later reads fail.
     pub fn from_bytes(bytes: &[u8]) -> &Self {
         // SAFETY: `Flatdata` has the same layout as `[u8]` via #[repr(transparent)].
         unsafe { std::mem::transmute(bytes) }
     }
 
+    /// Interprets `bytes`, as written by [`FlatbinBuf::with_version`], as a document without copying it. Only
+    /// documents of the current [`VERSION`] can be borrowed; any other fails with [`Error::UnsupportedVersion`],
+    /// and documents of earlier versions must be migrated with [`FlatbinBuf::from_versioned`].
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<&Self> {
+        match bytes.split_first().ok_or(Error::UnexpectedEOF)? {
+            (&VERSION, data) => Ok(Self::from_bytes(data)),
+            (&version, _) => Err(Error::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Interprets `bytes` as a document of type `ty`, validating it first.
+    ///
+    /// Unlike the O(1) `from_bytes`, this walks the entire document, so its cost is linear in its size.
+    /// In exchange, every subsequent read of the document using `ty` is guaranteed to succeed.
+    pub fn try_from_bytes<'a>(bytes: &'a [u8], ty: &Ty) -> Result<&'a Self> {
+        let value = Self::from_bytes(bytes);
+        ty.validate(value)?;
+        Ok(value)
+    }
+
+    /// Interprets a memory-mapped file as a document of type `ty`, validating it first.
+    ///
+    /// The returned reference borrows the mapping, so it can't outlive it, and random access through
+    /// `read_array`/`read_tuple` only touches the pages actually read (after the validation pass).
+    /// As with any `Mmap`, the file must not be modified or truncated, by this or any other process,
+    /// while the mapping exists: the reference assumes the bytes it was validated against never change.
+    #[cfg(feature = "memmap2")]
+    pub fn from_mmap<'a>(mmap: &'a memmap2::Mmap, ty: &Ty) -> Result<&'a Self> {
+        Self::try_from_bytes(mmap, ty)
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         &self.data
     }
 
+    /// Returns the length of this node's body in bytes, which excludes any header written by its parent.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn read_void(&self) -> Result<()> {
-        if self.data.is_empty() {
+        if self.is_empty() {
             Ok(())
         } else {
             Err(Error::UnexpectedLength)
@@ -153,15 +394,99 @@ impl Flatbin {
         Ok(&self.data)
     }
 
+    /// Reads the chunks of a chunked byte string (see [`Ty::ChunkedBytes`]), without copying them.
+    pub fn read_chunks(&self) -> Result<Vec<&[u8]>> {
+        self.read_array()?.iter().map(Flatbin::read_bytes).collect()
+    }
+
+    /// Reads a chunked byte string (see [`Ty::ChunkedBytes`]), concatenating its chunks.
+    pub fn read_chunked_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.read_chunks()?.concat())
+    }
+
+    /// Reads a string, checking that it is valid UTF-8.
+    ///
+    /// This is the safe default. To avoid checking strings on every read, a document can be checked once with
+    /// [`Ty::validate`] and its strings then read with [`read_str_unchecked`](Self::read_str_unchecked).
     pub fn read_str(&self) -> Result<&str> {
         std::str::from_utf8(&self.data).map_err(|_| Error::InvalidUTF8)
     }
 
+    #[deprecated(note = "renamed to `read_str`")]
+    pub fn read_string(&self) -> Result<&str> {
+        self.read_str()
+    }
+
+    /// Reads a string without checking that it is valid UTF-8.
+    ///
+    /// # Safety
+    ///
+    /// The node must be valid UTF-8, e.g. because the document was checked with [`Ty::validate`], or was
+    /// written by this crate from a `&str`.
+    pub unsafe fn read_str_unchecked(&self) -> &str {
+        std::str::from_utf8_unchecked(&self.data)
+    }
+
+    /// Reads a tuple of `count` elements, trusting that the body holds that many.
+    ///
+    /// If it holds fewer, iterating the tuple yields truncated or empty elements; see [`SequenceIter`].
     pub fn read_tuple(&self, count: usize) -> Result<Sequence<'_>> {
         let data = &self.data;
-        Ok(Sequence { count, data })
+        Ok(Sequence {
+            count,
+            data,
+            headed: false,
+        })
+    }
+
+    /// Reads a tuple of `count` elements like [`read_tuple`](Self::read_tuple), but first checks that the body
+    /// holds exactly `count` well-formed nodes, failing with [`Error::MissingElements`] if it holds fewer.
+    ///
+    /// The last element's header is elided, so it takes up the rest of the body; only the headers of the elements
+    /// before it are checked. An empty tuple must have an empty body.
+    pub fn read_tuple_checked(&self, count: usize) -> Result<Sequence<'_>> {
+        let tuple = self.read_tuple(count)?;
+        if count == 0 && !self.is_empty() {
+            return Err(Error::UnexpectedLength);
+        }
+        tuple.try_iter().try_for_each(|node| node.map(drop))?;
+        Ok(tuple)
+    }
+
+    /// Reads the values of a struct's or enum variant's fields, checking the tuple as
+    /// [`read_tuple_checked`](Self::read_tuple_checked) does.
+    ///
+    /// If the last field is [optional](Field::optional), every value is written with a header, so that the values
+    /// can be counted: the writer's schema may have had fewer optional fields at the end than `fields`, or more. Those
+    /// the writer didn't write are read as empty nodes, the zero value of their type, while any it wrote beyond
+    /// `fields` are skipped. If a field it didn't write isn't optional, this fails with [`Error::MissingElements`].
+    pub fn read_struct(&self, fields: &[Field]) -> Result<Sequence<'_>> {
+        if !Field::extensible(fields) {
+            return self.read_tuple_checked(fields.len());
+        }
+        let mut data = &self.data[..];
+        let mut written = 0;
+        while written < fields.len() && !data.is_empty() {
+            Flatbin::read_node(&mut data).map_err(|_| Error::UnexpectedLength)?;
+            written += 1;
+        }
+        if let Some(index) = (written..fields.len()).find(|&i| !fields[i].optional) {
+            return Err(Error::MissingElements {
+                expected: index + 1,
+                found: written,
+            });
+        }
+        Ok(Sequence {
+            count: fields.len(),
+            data: &self.data[..self.data.len() - data.len()],
+            headed: true,
+        })
     }
 
+    /// Reads an array, which is encoded as a varint element count followed by the elements.
+    ///
+    /// The canonical encoding of an empty array is an empty body (no count at all), which is what
+    /// [`VectorBuilder`] writes. An explicit count of zero is also accepted.
     pub fn read_array(&self) -> Result<Sequence<'_>> {
         let mut data = &self.data;
         let count = if data.is_empty() {
@@ -169,20 +494,257 @@ impl Flatbin {
         } else {
             Self::read_varint(&mut data)? as usize
         };
-        Ok(Sequence { count, data })
+        Ok(Sequence {
+            count,
+            data,
+            headed: false,
+        })
+    }
+
+    /// Reads only the element count of an array, without constructing a [`Sequence`].
+    ///
+    /// The count is checked against the size of the document, so that it can be trusted to pre-allocate: every
+    /// element but the last occupies at least one byte, so a count exceeding the remaining bytes by more than one
+    /// fails with [`Error::UnexpectedLength`].
+    pub fn array_len(&self) -> Result<usize> {
+        let mut data = &self.data;
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let count = Self::read_varint(&mut data)?;
+        match usize::try_from(count) {
+            Ok(count) if count <= data.len() + 1 => Ok(count),
+            _ => Err(Error::UnexpectedLength),
+        }
+    }
+
+    /// Reads a packed float array (see [`Ty::FloatVec`]), decoding each float from its little-endian bytes.
+    pub fn read_float_vec(&self) -> Result<impl ExactSizeIterator<Item = f64> + Clone + '_> {
+        let floats = self.float_vec_bytes()?.chunks_exact(8);
+        Ok(floats.map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap())))
+    }
+
+    /// Views a packed float array (see [`Ty::FloatVec`]) as a slice of floats aliasing the document, without copying.
+    ///
+    /// This needs a little-endian target, failing with [`Error::BigEndian`] otherwise, and needs the floats to be
+    /// 8-byte aligned in memory, failing with [`Error::Misaligned`] otherwise. The encoding can't guarantee the
+    /// latter, since a node's position depends on the length headers written before it and on where the buffer
+    /// itself is allocated. [`read_f64_slice`](Self::read_f64_slice) falls back to copying instead.
+    pub fn as_f64_slice(&self) -> Result<&[f64]> {
+        let bytes = self.float_vec_bytes()?;
+        if cfg!(target_endian = "big") {
+            return Err(Error::BigEndian);
+        }
+        // SAFETY: Every bit pattern is a valid `f64`, and `align_to` only returns a correctly aligned middle part
+        match unsafe { bytes.align_to::<f64>() } {
+            ([], floats, []) => Ok(floats),
+            _ => Err(Error::Misaligned),
+        }
+    }
+
+    /// Reads a packed float array (see [`Ty::FloatVec`]) as a slice of floats, borrowing it if
+    /// [`as_f64_slice`](Self::as_f64_slice) can and copying it otherwise.
+    pub fn read_f64_slice(&self) -> Result<Cow<'_, [f64]>> {
+        match self.as_f64_slice() {
+            Ok(floats) => Ok(Cow::Borrowed(floats)),
+            Err(Error::BigEndian | Error::Misaligned) => Ok(Cow::Owned(self.read_float_vec()?.collect())),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the packed floats of a float array, after checking that the body holds exactly as many as its count.
+    fn float_vec_bytes(&self) -> Result<&[u8]> {
+        let mut data = &self.data;
+        let count = if data.is_empty() {
+            0
+        } else {
+            Self::read_varint(&mut data)? as usize
+        };
+        match count.checked_mul(8) == Some(data.len()) {
+            true => Ok(data),
+            false => Err(Error::UnexpectedLength),
+        }
+    }
+
+    /// Reads an enum (see [`Ty::Enum`]) with the given variants, returning the variant's index and the values of its
+    /// fields.
+    pub fn read_variant(&self, variants: &[Variant]) -> Result<(usize, Sequence<'_>)> {
+        let mut tuple = self.read_tuple_checked(2)?.iter();
+        let (Some(index), Some(fields)) = (tuple.next(), tuple.next()) else {
+            return Err(Error::UnexpectedEOF);
+        };
+        let index = index.read_u64()?;
+        let (index, variant) = usize::try_from(index)
+            .ok()
+            .and_then(|i| Some((i, variants.get(i)?)))
+            .ok_or(Error::InvalidVariant(index))?;
+        Ok((index, fields.read_struct(&variant.fields)?))
+    }
+
+    /// Reads an entry of a map (see [`Ty::Map`]), returning its key and value.
+    pub fn read_entry(&self) -> Result<(&str, &Flatbin)> {
+        let mut entry = self.read_tuple_checked(2)?.iter();
+        let (Some(key), Some(value)) = (entry.next(), entry.next()) else {
+            return Err(Error::UnexpectedEOF);
+        };
+        Ok((key.read_str()?, value))
+    }
+
+    /// Finds the value of `key` in a map of type `ty`, which must be a [`Ty::Map`].
+    ///
+    /// For the [`Ordered`](MapLayout::Ordered) layout the entries are located by walking their headers, without
+    /// decoding them, and then the key is found by binary search, so only a logarithmic number of keys are read. The
+    /// result is unspecified if the keys are not sorted. For the [`Insertion`](MapLayout::Insertion) layout every
+    /// key is read in turn until one matches.
+    pub fn map_get(&self, ty: &Ty, key: &str) -> Result<Option<&Flatbin>> {
+        match ty {
+            Ty::Map {
+                layout: MapLayout::Ordered,
+                ..
+            } => self.map_search(key),
+            Ty::Map {
+                layout: MapLayout::Insertion,
+                ..
+            } => {
+                for entry in self.map_entries() {
+                    match entry? {
+                        (entry_key, value) if entry_key == key => return Ok(Some(value)),
+                        _ => continue,
+                    }
+                }
+                Ok(None)
+            }
+            _ => Err(Error::TypeMismatch),
+        }
     }
 
+    /// Returns an iterator over the entries of a map (see [`Ty::Map`]) in the order they are stored, each a key and
+    /// its value. A malformed map or entry is reported in place of the entries which couldn't be read.
+    pub fn map_entries(&self) -> impl Iterator<Item = Result<(&str, &Flatbin)>> {
+        let (entries, error) = match self.read_array() {
+            Ok(entries) => (Some(entries), None),
+            Err(error) => (None, Some(error)),
+        };
+        let entries = entries.into_iter().flat_map(|entries| entries.try_iter());
+        error
+            .map(Err)
+            .into_iter()
+            .chain(entries.map(|entry| entry?.read_entry()))
+    }
+
+    /// Returns an iterator over the keys of a map, as [`map_entries`](Self::map_entries).
+    pub fn map_keys(&self) -> impl Iterator<Item = Result<&str>> {
+        self.map_entries().map(|entry| entry.map(|(key, _)| key))
+    }
+
+    /// Returns an iterator over the values of a map, as [`map_entries`](Self::map_entries).
+    pub fn map_values(&self) -> impl Iterator<Item = Result<&Flatbin>> {
+        self.map_entries().map(|entry| entry.map(|(_, value)| value))
+    }
+
+    /// Finds the value of `key` by binary search over the sorted entries of a map.
+    fn map_search(&self, key: &str) -> Result<Option<&Flatbin>> {
+        let entries = self.read_array()?.try_iter().collect::<Result<Vec<_>>>()?;
+        let (mut low, mut high) = (0, entries.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (entry_key, value) = entries[mid].read_entry()?;
+            match entry_key.as_bytes().cmp(key.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(Some(value)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads a run-length encoded array (see [`Ty::RleArray`]), returning the count and value of each run. Fails with
+    /// [`Error::RleTooLong`] if the runs add up to more than [`MAX_RLE_LEN`] elements.
+    pub fn read_runs(&self) -> Result<Vec<(u64, &Flatbin)>> {
+        let mut len = 0usize;
+        self.read_array()?
+            .try_iter()
+            .map(|run| {
+                let mut run = run?.read_tuple(2)?.iter();
+                let (Some(count), Some(value)) = (run.next(), run.next()) else {
+                    return Err(Error::UnexpectedEOF);
+                };
+                let count = count.read_u64()?;
+                len = usize::try_from(count)
+                    .ok()
+                    .and_then(|count| len.checked_add(count))
+                    .filter(|&len| len <= MAX_RLE_LEN)
+                    .ok_or(Error::RleTooLong)?;
+                Ok((count, value))
+            })
+            .collect()
+    }
+
+    /// Returns the number of elements a run-length encoded array (see [`Ty::RleArray`]) expands to, which is at most
+    /// [`MAX_RLE_LEN`].
+    pub fn rle_len(&self) -> Result<usize> {
+        self.read_runs()?
+            .iter()
+            .try_fold(0usize, |len, &(count, _)| len.checked_add(usize::try_from(count).ok()?))
+            .ok_or(Error::RleTooLong)
+    }
+
+    /// Reads a columnar array (see [`Ty::Columnar`]) with `columns` columns, returning the number of rows
+    /// and the values of each column.
+    pub fn read_columns(&self, columns: usize) -> Result<(usize, Vec<Sequence<'_>>)> {
+        let mut children = self.read_tuple(1 + columns)?.iter();
+        let rows = children.next().ok_or(Error::UnexpectedEOF)?.read_u64()?;
+        let rows = usize::try_from(rows).map_err(|_| Error::NumberTooLarge)?;
+        let columns = children
+            .map(|column| {
+                // Every node except the last occupies at least one byte
+                match rows > column.len() + 1 {
+                    true => Err(Error::UnexpectedLength),
+                    false => column.read_tuple(rows),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((rows, columns))
+    }
+
+    /// Returns the node whose header starts `offset` bytes into this node's body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the body, or the header there is malformed.
     pub fn seek(&self, offset: usize) -> &Flatbin {
         let data = &self.data[offset..];
         let (header_len, body_len) = Flatbin::read_node_header(data).unwrap();
         Flatbin::from_bytes(&data[header_len..][..body_len])
     }
 
+    /// Returns the offset of `node`'s body within this node's body, or `None` if `node` isn't a part of it.
+    ///
+    /// Storing offsets instead of references allows an index of nodes to outlive a particular borrow of
+    /// the buffer, at the cost of slicing it again to get each node back.
+    pub fn offset_of(&self, node: &Flatbin) -> Option<usize> {
+        let start = self.data.as_ptr() as usize;
+        let offset = (node.data.as_ptr() as usize).checked_sub(start)?;
+        (offset + node.len() <= self.len()).then_some(offset)
+    }
+
+    /// Splits the first node off the front of `data`, checking that its body lies within `data`.
+    pub(crate) fn read_node<'a>(data: &mut &'a [u8]) -> Result<&'a Flatbin> {
+        let (header_len, body_len) = Flatbin::read_node_header(data)?;
+        let body = data[header_len..].get(..body_len).ok_or(Error::UnexpectedEOF)?;
+        *data = &data[(header_len + body_len)..];
+        Ok(Flatbin::from_bytes(body))
+    }
+
     fn read_varint(data: &mut &[u8]) -> Result<u64> {
         let mut value = 0;
         let mut index = 0;
         loop {
             let byte = data.get(index).ok_or(Error::UnexpectedEOF)?;
+            // A `u64` takes at most ten bytes, the last of which holds only its top bit
+            if index == 10 || (index == 9 && byte & 0x7f > 1) {
+                return Err(Error::NumberTooLarge);
+            }
             value |= ((byte & 0x7f) as u64) << (7 * index);
             index += 1;
             if byte & 0x80 == 0 {
@@ -228,13 +790,26 @@ impl Flatbin {
 
 #[derive(Clone, Copy)]
 pub struct Sequence<'a> {
-    count: usize,
-    data: &'a [u8],
+    pub(crate) count: usize,
+    pub(crate) data: &'a [u8],
+    /// Whether the last element is written with a header too, as in a struct read by [`Flatbin::read_struct`]. The
+    /// elements after the end of the data are then empty.
+    pub(crate) headed: bool,
 }
 
+/// An iterator over the nodes of a [`Sequence`].
+///
+/// It always yields exactly [`Sequence::len`] nodes, as its [`ExactSizeIterator`] implementation promises. If the
+/// data is truncated or otherwise malformed, the nodes from the point of corruption onwards are truncated or empty,
+/// so reading them may fail or produce wrong values; use [`Ty::validate`](crate::ty::Ty::validate) to rule this out.
+///
+/// Yielding a node only reads its header, to find its length, so elements can be skipped cheaply with `next` or
+/// `nth` however large or deeply nested they are.
+#[derive(Clone)]
 pub struct SequenceIter<'a> {
     count: usize,
     data: &'a [u8],
+    headed: bool,
 }
 
 impl<'a> IntoIterator for Sequence<'a> {
@@ -242,8 +817,8 @@ impl<'a> IntoIterator for Sequence<'a> {
     type IntoIter = SequenceIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let Sequence { count, data } = self;
-        SequenceIter { count, data }
+        let Sequence { count, data, headed } = self;
+        SequenceIter { count, data, headed }
     }
 }
 
@@ -259,6 +834,58 @@ impl<'a> Sequence<'a> {
     pub fn iter(&self) -> SequenceIter<'a> {
         self.into_iter()
     }
+
+    /// Returns an iterator over the elements which, unlike [`iter`](Self::iter), fails with
+    /// [`Error::MissingElements`] if the data ends before every element has been read.
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<&'a Flatbin>> {
+        let Sequence {
+            count,
+            mut data,
+            headed,
+        } = *self;
+        (0..count).map(move |index| match index + 1 == count && !headed {
+            true => Ok(Flatbin::from_bytes(std::mem::take(&mut data))),
+            // The optional fields of a struct that weren't written are empty
+            false if headed && data.is_empty() => Ok(Flatbin::from_bytes(&[])),
+            false => Flatbin::read_node(&mut data).map_err(|_| Error::MissingElements {
+                expected: count,
+                found: index,
+            }),
+        })
+    }
+
+    /// Returns the element at `index`, walking the headers of the elements before it.
+    pub fn get(&self, index: usize) -> Option<&'a Flatbin> {
+        self.iter().nth(index)
+    }
+
+    /// Returns the element `index` places from the end, so that `0` is the last element, walking the headers of the
+    /// elements before it.
+    pub fn get_from_end(&self, index: usize) -> Option<&'a Flatbin> {
+        self.get(self.count.checked_sub(index)?.checked_sub(1)?)
+    }
+
+    /// Returns the last element, or `None` if the sequence is empty. Elements are variable-width, so this walks the
+    /// headers of every element before it, failing as [`try_iter`](Self::try_iter) does if the data ends early.
+    pub fn last(&self) -> Option<Result<&'a Flatbin>> {
+        // An element missing part way through makes the ones after it meaningless
+        self.try_iter().reduce(|previous, node| previous.and(node))
+    }
+
+    /// Returns an iterator over the elements paired with their type `inner`, failing as [`try_iter`](Self::try_iter)
+    /// does if the data ends early.
+    pub fn typed_iter(self, inner: &'a Ty) -> impl Iterator<Item = Result<TypedFlatbin<'a>>> {
+        self.try_iter().map(move |value| Ok(TypedFlatbin::new(inner, value?)))
+    }
+
+    /// Returns an iterator reading every element with `read`, so that a homogeneous array can be collected in one go,
+    /// e.g. `array.map_read(Flatbin::read_u64).collect::<Result<Vec<_>>>()`.
+    pub fn map_read<T>(
+        self,
+        mut read: impl FnMut(&'a Flatbin) -> Result<T> + 'a,
+    ) -> impl Iterator<Item = Result<T>> + 'a {
+        self.try_iter().map(move |value| read(value?))
+    }
 }
 
 impl<'a> Iterator for SequenceIter<'a> {
@@ -267,15 +894,16 @@ impl<'a> Iterator for SequenceIter<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         match self.count {
             0 => None,
-            1 => {
+            1 if !self.headed => {
                 self.count -= 1;
                 Some(Flatbin::from_bytes(self.data))
             }
             _ => {
-                // If `read_node_header` encounters an error, we just return an empty slice,
-                // effectively deferring the error until the value is actually read.
-                let (header_len, body_len) = Flatbin::read_node_header(self.data).unwrap_or((0, 0));
-                let (item, rest) = self.data[header_len..].split_at(body_len);
+                // If the header is malformed or the body is truncated, we return whatever bytes remain and
+                // leave the rest of the nodes empty, effectively deferring the error until the value is read.
+                let (header_len, body_len) = Flatbin::read_node_header(self.data).unwrap_or((self.data.len(), 0));
+                let data = std::mem::take(&mut self.data);
+                let (item, rest) = data[header_len..].split_at(body_len.min(data.len() - header_len));
 
                 self.data = rest;
                 self.count -= 1;
@@ -322,4 +950,356 @@ mod test {
         assert_eq!(b.next().unwrap().read_bool().unwrap(), false);
         a.next().unwrap().read_void().unwrap();
     }
+
+    #[test]
+    fn array_len() {
+        let mut buffer = FlatbinBuf::new();
+        let mut vector = Builder::new(&mut buffer).start_vector();
+        for value in [1u64, 2, 300] {
+            vector.as_builder().write_u64(value);
+        }
+        vector.end();
+        assert_eq!(buffer.array_len().unwrap(), 3);
+        assert_eq!(buffer.array_len().unwrap(), buffer.read_array().unwrap().len());
+        assert_eq!(Flatbin::from_bytes(&[]).array_len().unwrap(), 0);
+
+        // A count far beyond what the remaining bytes could hold
+        assert!(matches!(
+            Flatbin::from_bytes(&[0xff, 0x7f, 1]).array_len(),
+            Err(Error::UnexpectedLength)
+        ));
+    }
+
+    #[test]
+    fn truncated_sequence() {
+        let mut buffer = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut buffer).start_vector();
+        vec.as_builder().write_str("Hello world");
+        vec.as_builder().write_str("Goodbye world");
+        vec.as_builder().write_u64(1000);
+        vec.end();
+
+        for len in 0..buffer.as_bytes().len() {
+            let truncated = Flatbin::from_bytes(&buffer.as_bytes()[..len]);
+            let Ok(array) = truncated.read_array() else {
+                continue;
+            };
+            let mut iter = array.iter();
+            for remaining in (0..array.len()).rev() {
+                assert!(iter.next().is_some());
+                assert_eq!(iter.len(), remaining);
+            }
+            assert!(iter.next().is_none());
+        }
+    }
+
+    #[test]
+    fn last_element() {
+        let mut buffer = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut buffer).start_vector();
+        vec.as_builder().write_str("Hello world");
+        vec.as_builder().write_str("Goodbye world");
+        vec.as_builder().write_u64(1000);
+        vec.end();
+
+        let array = buffer.read_array().unwrap();
+        assert_eq!(array.last().unwrap().unwrap().read_u64().unwrap(), 1000);
+        assert_eq!(array.get_from_end(0).unwrap().read_u64().unwrap(), 1000);
+        assert_eq!(array.get_from_end(2).unwrap().read_str().unwrap(), "Hello world");
+        assert!(array.get_from_end(3).is_none());
+        assert!(array.get_from_end(usize::MAX).is_none());
+        assert!(Flatbin::from_bytes(&[]).read_array().unwrap().last().is_none());
+
+        // The walk to the last element fails if the data ends early
+        let truncated = Flatbin::from_bytes(&buffer.as_bytes()[..4]).read_array().unwrap();
+        assert!(matches!(truncated.last(), Some(Err(Error::MissingElements { .. }))));
+    }
+
+    #[test]
+    fn versioned() {
+        let mut buffer = FlatbinBuf::new();
+        buffer.reset_builder().write_str("Hello");
+
+        let bytes = buffer.with_version();
+        assert_eq!(bytes[0], VERSION);
+        assert_eq!(&bytes[1..], buffer.as_bytes());
+        assert_eq!(
+            Flatbin::from_versioned_bytes(&bytes).unwrap().read_str().unwrap(),
+            "Hello"
+        );
+        assert_eq!(FlatbinBuf::from_versioned(bytes).unwrap(), buffer);
+
+        let mut newer = buffer.with_version();
+        newer[0] = VERSION + 1;
+        assert!(matches!(
+            Flatbin::from_versioned_bytes(&newer),
+            Err(Error::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            FlatbinBuf::from_versioned(newer),
+            Err(Error::UnsupportedVersion(_))
+        ));
+        assert!(matches!(FlatbinBuf::from_versioned(vec![]), Err(Error::UnexpectedEOF)));
+
+        // A document stored without a version is read as it is, even if its first byte looks like one
+        let mut buffer = FlatbinBuf::new();
+        buffer.reset_builder().start_tuple().write_all(("\0", "Hello"));
+        assert_eq!(buffer.as_bytes()[0], VERSION);
+        let unversioned = FlatbinBuf::from_unversioned(buffer.as_bytes().to_vec()).unwrap();
+        assert_eq!(unversioned, buffer);
+        let versioned = FlatbinBuf::from_versioned(buffer.with_version()).unwrap();
+        assert_eq!(versioned, buffer);
+    }
+
+    #[test]
+    fn skip() {
+        let mut buffer = FlatbinBuf::new();
+        buffer
+            .reset_builder()
+            .start_tuple()
+            .write_all((40, "Hello", "a longer string to skip", 50));
+
+        let mut iter = buffer.read_tuple(4).unwrap().iter();
+        assert_eq!(iter.nth(1).unwrap().read_str().unwrap(), "Hello");
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.nth(1).unwrap().read_i64().unwrap(), 50);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn read_tuple_checked() {
+        let mut buffer = FlatbinBuf::new();
+        buffer.reset_builder().start_tuple().write_all((40, "Hello", 50));
+
+        assert_eq!(buffer.read_tuple_checked(3).unwrap().len(), 3);
+        assert!(buffer.read_tuple_checked(2).is_ok());
+        // A missing last element can't be told apart from an empty one, but any earlier missing element can
+        assert!(buffer.read_tuple_checked(4).is_ok());
+        assert!(matches!(
+            buffer.read_tuple_checked(5),
+            Err(Error::MissingElements { expected: 5, found: 3 })
+        ));
+        assert!(matches!(buffer.read_tuple_checked(0), Err(Error::UnexpectedLength)));
+        assert!(Flatbin::from_bytes(&[]).read_tuple_checked(0).is_ok());
+
+        // The body ends partway through the second element
+        let truncated = Flatbin::from_bytes(&buffer.as_bytes()[..3]);
+        assert!(matches!(
+            truncated.read_tuple_checked(3),
+            Err(Error::MissingElements { expected: 3, found: 1 })
+        ));
+    }
+
+    #[test]
+    fn f64_slice() {
+        let floats: [f64; 3] = [1.5, -2.0, 1e300];
+        let mut body = vec![floats.len() as u8];
+        body.extend(floats.iter().flat_map(|float| float.to_le_bytes()));
+
+        // Place the body in 8-byte aligned storage so that the floats after its one-byte count are aligned, or not
+        let place = |offset: usize| {
+            let mut storage = vec![0f64; 1 + floats.len()];
+            // SAFETY: Any bytes are valid for `u8`, and the slice covers exactly the storage
+            let bytes = unsafe { std::slice::from_raw_parts_mut(storage.as_mut_ptr() as *mut u8, 8 * storage.len()) };
+            bytes[offset..offset + body.len()].copy_from_slice(&body);
+            storage
+        };
+        let (aligned, misaligned) = (place(7), place(0));
+        let view = |storage: &[f64], offset: usize| {
+            // SAFETY: As above
+            let bytes = unsafe { std::slice::from_raw_parts(storage.as_ptr() as *const u8, 8 * storage.len()) };
+            Flatbin::from_bytes(&bytes[offset..offset + body.len()])
+        };
+        let (aligned, misaligned) = (view(&aligned, 7), view(&misaligned, 0));
+
+        if cfg!(target_endian = "little") {
+            assert_eq!(aligned.as_f64_slice().unwrap(), floats);
+            assert!(matches!(aligned.read_f64_slice().unwrap(), Cow::Borrowed(_)));
+        }
+        assert!(matches!(
+            misaligned.as_f64_slice(),
+            Err(Error::Misaligned | Error::BigEndian)
+        ));
+        assert!(matches!(misaligned.read_f64_slice().unwrap(), Cow::Owned(_)));
+        assert_eq!(*misaligned.read_f64_slice().unwrap(), floats);
+
+        // An empty array is an empty slice wherever it lies
+        assert!(Flatbin::from_bytes(&[]).read_f64_slice().unwrap().is_empty());
+        assert!(matches!(
+            Flatbin::from_bytes(&body[..5]).read_f64_slice(),
+            Err(Error::UnexpectedLength)
+        ));
+    }
+
+    #[test]
+    fn vec_conversions() {
+        let mut buffer = FlatbinBuf::new();
+        buffer.reset_builder().start_tuple().write_all((40, "Hello"));
+        let bytes = Vec::from(buffer.clone());
+        assert_eq!(bytes, buffer.as_bytes());
+        assert_eq!(FlatbinBuf::from(bytes), buffer);
+
+        // Nothing is validated on the way in
+        let garbage = FlatbinBuf::from(vec![0xff]);
+        assert_eq!(garbage.read_bytes().unwrap(), [0xff]);
+        assert!(garbage.read_array().is_err());
+    }
+
+    #[test]
+    fn empty_array() {
+        let mut buffer = FlatbinBuf::new();
+        Builder::new(&mut buffer).start_vector().end();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.read_array().unwrap().len(), 0);
+
+        // A non-canonical explicit zero count is read the same way
+        assert_eq!(Flatbin::from_bytes(&[0]).read_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn int_boundaries() {
+        let cases = [(0, 0), (-1, 1), (1, 2), (i64::MAX, u64::MAX - 1), (i64::MIN, u64::MAX)];
+        for (value, zigzag) in cases {
+            let mut buffer = FlatbinBuf::new();
+            Builder::new(&mut buffer).write_i64(value);
+            assert_eq!(buffer.read_uint().unwrap(), zigzag);
+            assert_eq!(buffer.read_int().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn uint_narrowing() {
+        let max = Flatbin::from_bytes(&[0xff; 8]);
+        assert_eq!(max.read_uint().unwrap(), u64::MAX);
+        assert_eq!(max.read_u64().unwrap(), u64::MAX);
+        assert!(matches!(max.read_u32(), Err(Error::NumberTooLarge)));
+        assert!(matches!(max.read_u16(), Err(Error::NumberTooLarge)));
+        assert!(matches!(max.read_u8(), Err(Error::NumberTooLarge)));
+
+        // The narrowing readers accept exactly their own range
+        let u32_max = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(u32_max.read_u32().unwrap(), u32::MAX);
+        let u32_over = Flatbin::from_bytes(&[0, 0, 0, 0, 1]);
+        assert!(matches!(u32_over.read_u32(), Err(Error::NumberTooLarge)));
+
+        assert!(matches!(
+            Flatbin::from_bytes(&[0xff; 9]).read_uint(),
+            Err(Error::UnexpectedLength)
+        ));
+    }
+
+    #[test]
+    fn append_to_array() {
+        use crate::{array_def, ty::Ty};
+
+        let ty = array_def!(Ty::String);
+        let elements: Vec<String> = (0..300).map(|i| "x".repeat(i % 5)).collect();
+        let mut buffer = FlatbinBuf::new();
+        for (i, chunk) in elements.chunks(7).enumerate() {
+            let expected = crate::slow::deserialize_alloc(&ty, &elements[..i * 7].into()).unwrap();
+            assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+            let mut vector = buffer.append_to_array().unwrap();
+            for element in chunk {
+                vector.write(element.as_str());
+            }
+            vector.end();
+        }
+        let expected = crate::slow::deserialize_alloc(&ty, &elements.into()).unwrap();
+        assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+        // An explicit count of zero is left alone if nothing is appended
+        let mut buffer = Flatbin::from_bytes(&[0]).to_owned();
+        buffer.append_to_array().unwrap().end();
+        assert_eq!(buffer.as_bytes(), [0]);
+        buffer.append_to_array().unwrap().write(5u8);
+        assert_eq!(buffer.as_bytes(), [1, 5]);
+
+        let mut buffer = Flatbin::from_bytes(&[3, 0x85]).to_owned();
+        assert!(buffer.append_to_array().is_err());
+    }
+
+    #[test]
+    fn reset_builder() {
+        let mut buffer = FlatbinBuf::new();
+        buffer.reset_builder().write_str("first");
+        buffer.reset_builder().write_str("second");
+        assert_eq!(buffer.read_str().unwrap(), "second");
+    }
+
+    #[test]
+    fn sub_node_lifetimes() {
+        let mut buffer = FlatbinBuf::new();
+        let mut vec = Builder::new(&mut buffer).start_vector();
+        for name in ["Alice", "Bob"] {
+            let mut tup = vec.as_builder().start_tuple();
+            tup.as_builder().write_str(name);
+            tup.as_builder().write_u64(name.len() as u64);
+            tup.end();
+        }
+        vec.end();
+
+        // Nodes from nested reads outlive the sequences they were read from
+        let root: &Flatbin = &buffer;
+        let names: Vec<&Flatbin> = root
+            .read_array()
+            .unwrap()
+            .iter()
+            .map(|person| person.read_tuple(2).unwrap().get(0).unwrap())
+            .collect();
+        assert_eq!(names[1].read_str().unwrap(), "Bob");
+
+        let offset = root.offset_of(names[1]).unwrap();
+        assert_eq!(
+            Flatbin::from_bytes(&root.as_bytes()[offset..][..3]).read_str().unwrap(),
+            "Bob"
+        );
+        assert_eq!(root.offset_of(Flatbin::from_bytes(b"Bob")), None);
+    }
+
+    #[test]
+    fn to_owned() {
+        let mut buffer = FlatbinBuf::new();
+        let mut tup = Builder::new(&mut buffer).start_tuple();
+        tup.as_builder().write_str("Hello");
+        tup.as_builder().write_u32(300);
+        tup.end();
+
+        let owned = buffer.read_tuple(2).unwrap().iter().next().unwrap().to_owned();
+        drop(buffer);
+        assert_eq!(owned.read_str().unwrap(), "Hello");
+    }
+
+    #[test]
+    fn read_str_unchecked() {
+        use crate::{array_def, ty::Ty};
+
+        let ty = array_def!(Ty::String);
+        let bytes = crate::fast::deserialize(&ty, r#"["a", "bc", "déf"]"#).unwrap();
+        ty.validate(&bytes).unwrap();
+        for value in bytes.read_array().unwrap() {
+            // SAFETY: The document was validated above.
+            assert_eq!(unsafe { value.read_str_unchecked() }, value.read_str().unwrap());
+        }
+    }
+
+    #[cfg(feature = "memmap2")]
+    #[test]
+    fn from_mmap() {
+        use crate::{array_def, ty::Ty};
+
+        let ty = array_def!(Ty::String);
+        let bytes = crate::fast::deserialize(&ty, r#"["a", "bc", "def"]"#).unwrap();
+        let path = std::env::temp_dir().join(format!("flatbin-mmap-{}", std::process::id()));
+        std::fs::write(&path, bytes.as_bytes()).unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        // SAFETY: The file is private to this test and is not modified while mapped.
+        let mmap = unsafe { memmap2::Mmap::map(&file).unwrap() };
+        let value = Flatbin::from_mmap(&mmap, &ty).unwrap();
+        assert_eq!(value.read_array().unwrap().get(2).unwrap().read_str().unwrap(), "def");
+
+        drop(mmap);
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,91 @@
+use super::{Flatbin, FlatbinBuf};
+use std::collections::HashSet;
+
+/// A set of unique documents, compared by their bytes.
+///
+/// Values are encoded canonically, so equal values of the same type have equal bytes, with the exception of
+/// floats: `-0.0` and `0.0`, or NaNs with different payloads, differ unless they were written with
+/// [`canonical_floats`](crate::options::DeserializeOptions::canonical_floats).
+#[derive(Clone, Debug, Default)]
+pub struct FlatbinSet {
+    documents: HashSet<FlatbinBuf>,
+}
+
+impl FlatbinSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Inserts a copy of `document`, returning `false` without copying it if it is already present.
+    pub fn insert(&mut self, document: &Flatbin) -> bool {
+        if self.contains(document) {
+            return false;
+        }
+        self.documents.insert(document.to_owned())
+    }
+
+    pub fn contains(&self, document: &Flatbin) -> bool {
+        self.documents.contains(document)
+    }
+
+    pub fn remove(&mut self, document: &Flatbin) -> bool {
+        self.documents.remove(document)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Flatbin> {
+        self.documents.iter().map(|document| &**document)
+    }
+}
+
+impl Extend<FlatbinBuf> for FlatbinSet {
+    fn extend<I: IntoIterator<Item = FlatbinBuf>>(&mut self, iter: I) {
+        self.documents.extend(iter)
+    }
+}
+
+impl FromIterator<FlatbinBuf> for FlatbinSet {
+    fn from_iter<I: IntoIterator<Item = FlatbinBuf>>(iter: I) -> Self {
+        Self {
+            documents: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FlatbinSet;
+    use crate::{slow, struct_def, ty::Ty};
+
+    #[test]
+    fn dedupe() {
+        let ty = struct_def!({ "name": Ty::String, "age": Ty::U64 });
+        let encode = |value| slow::deserialize_alloc(&ty, &value).unwrap();
+        let alice = encode(serde_json::json!({ "name": "Alice", "age": 31 }));
+        let bob = encode(serde_json::json!({ "name": "Bob", "age": 27 }));
+
+        let mut set = FlatbinSet::new();
+        assert!(set.insert(&alice));
+        assert!(set.insert(&bob));
+        assert!(!set.insert(&encode(serde_json::json!({ "name": "Alice", "age": 31 }))));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&alice));
+
+        assert!(set.remove(&alice));
+        assert!(!set.contains(&alice));
+        assert_eq!(
+            set.iter().map(|document| document.as_bytes()).collect::<Vec<_>>(),
+            [bob.as_bytes()]
+        );
+
+        let set: FlatbinSet = [alice.clone(), alice, bob].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
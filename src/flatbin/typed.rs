@@ -0,0 +1,182 @@
+use super::{Error, Flatbin, Result};
+use crate::ty::Ty;
+
+/// A flatbin value paired with its type, whose accessors use the type to pick the right reader.
+#[derive(Clone, Copy)]
+pub struct TypedFlatbin<'a> {
+    pub ty: &'a Ty,
+    pub value: &'a Flatbin,
+}
+
+impl<'a> TypedFlatbin<'a> {
+    pub fn new(ty: &'a Ty, value: &'a Flatbin) -> Self {
+        Self { ty, value }
+    }
+
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.ty {
+            Ty::Bool => self.value.read_bool(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64> {
+        match self.ty {
+            Ty::U64 => self.value.read_u64(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn as_i64(&self) -> Result<i64> {
+        match self.ty {
+            Ty::I64 => self.value.read_i64(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64> {
+        match self.ty {
+            Ty::F64 => self.value.read_f64(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&'a [u8]> {
+        match self.ty {
+            Ty::Bytes => self.value.read_bytes(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    /// Returns the chunks of a chunked byte string, or a byte string as a single chunk (or none, if empty).
+    pub fn as_chunks(&self) -> Result<Vec<&'a [u8]>> {
+        match self.ty {
+            Ty::Bytes => Ok(self.value.read_bytes()?.chunks(usize::MAX).collect()),
+            Ty::ChunkedBytes { .. } => self.value.read_chunks(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&'a str> {
+        match self.ty {
+            Ty::String => self.value.read_str(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    /// Returns the number of elements in an array, rows in a columnar array or entries in a map, or the number of
+    /// fields in a struct.
+    pub fn len(&self) -> Result<usize> {
+        match self.ty {
+            Ty::Array { .. } | Ty::Map { .. } => Ok(self.value.read_array()?.len()),
+            Ty::FloatVec => Ok(self.value.read_float_vec()?.len()),
+            Ty::RleArray { .. } => self.value.rle_len(),
+            Ty::Columnar { fields } => Ok(self.value.read_columns(fields.len())?.0),
+            Ty::Struct { fields } => Ok(fields.len()),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns the field of a struct with the given name.
+    pub fn field(&self, name: &str) -> Result<TypedFlatbin<'a>> {
+        let Ty::Struct { fields } = self.ty else {
+            return Err(Error::TypeMismatch);
+        };
+        let index = fields
+            .iter()
+            .position(|f| &*f.name == name)
+            .ok_or_else(|| Error::NoSuchField { name: name.into() })?;
+        let value = self.value.read_struct(fields)?.get(index).ok_or(Error::UnexpectedEOF)?;
+        Ok(TypedFlatbin::new(&fields[index].ty, value))
+    }
+
+    /// Returns the value of the given key in a map, or `None` if the map doesn't contain it.
+    pub fn map_get(&self, key: &str) -> Result<Option<TypedFlatbin<'a>>> {
+        let Ty::Map { value: inner, .. } = self.ty else {
+            return Err(Error::TypeMismatch);
+        };
+        let value = self.value.map_get(self.ty, key)?;
+        Ok(value.map(|value| TypedFlatbin::new(inner, value)))
+    }
+
+    /// Returns the element of an array at the given index.
+    pub fn get(&self, index: usize) -> Result<TypedFlatbin<'a>> {
+        let Ty::Array { inner } = self.ty else {
+            return Err(Error::TypeMismatch);
+        };
+        let value = self.value.read_array()?.get(index).ok_or(Error::IndexOutOfBounds)?;
+        Ok(TypedFlatbin::new(inner, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn typed_access() {
+        let ty = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "languages": array_def!(struct_def!({
+                "name": Ty::String,
+                "liked": Ty::Bool,
+            }))
+        });
+        let value = serde_json::json!({
+            "name": "Alexander",
+            "age": 27,
+            "languages": [{ "name": "Rust", "liked": true }, { "name": "PHP", "liked": false }]
+        });
+        let bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+        let doc = TypedFlatbin::new(&ty, &bytes);
+
+        assert_eq!(doc.field("name").unwrap().as_str().unwrap(), "Alexander");
+        assert_eq!(doc.field("age").unwrap().as_u64().unwrap(), 27);
+        let languages = doc.field("languages").unwrap();
+        assert_eq!(languages.len().unwrap(), 2);
+        let php = languages.get(1).unwrap();
+        assert_eq!(php.field("name").unwrap().as_str().unwrap(), "PHP");
+        assert_eq!(php.field("liked").unwrap().as_bool().unwrap(), false);
+
+        assert!(matches!(doc.field("age").unwrap().as_str(), Err(Error::TypeMismatch)));
+        assert!(matches!(doc.field("email"), Err(Error::NoSuchField { .. })));
+        assert!(matches!(languages.get(2), Err(Error::IndexOutOfBounds)));
+    }
+
+    #[test]
+    fn typed_iter() {
+        let ty = array_def!(Ty::U64);
+        let bytes = crate::fast::deserialize(&ty, "[1, 200, 70000]").unwrap();
+        let array = bytes.read_array().unwrap();
+
+        let values = array.map_read(Flatbin::read_u64).collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(values, [1, 200, 70000]);
+        let Ty::Array { inner } = &ty else { unreachable!() };
+        let values = array
+            .typed_iter(inner)
+            .map(|v| v?.as_u64())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(values, [1, 200, 70000]);
+
+        // Reading with the wrong type fails element by element
+        let results: Vec<_> = array.typed_iter(inner).map(|v| v?.as_i64()).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| matches!(r, Err(Error::TypeMismatch))));
+
+        // A truncated array fails at the first missing element
+        let truncated = Flatbin::from_bytes(&bytes.as_bytes()[..3]).read_array().unwrap();
+        let results: Vec<_> = truncated.map_read(Flatbin::read_u64).collect();
+        assert_eq!(results[0].as_ref().unwrap(), &1);
+        assert!(matches!(
+            results[1],
+            Err(Error::MissingElements { expected: 3, found: 1 })
+        ));
+    }
+}
@@ -92,6 +92,11 @@ impl VarInt {
     }
 }
 
+/// Returns the number of little-endian bytes needed to represent `value`, with zero needing none.
+pub fn uint_len(value: u64) -> usize {
+    (71 - value.leading_zeros() as usize) / 8
+}
+
 impl Deref for VarInt {
     type Target = [u8];
 
@@ -122,4 +127,28 @@ mod test {
             assert_eq!(VarInt::from_slice(a.as_slice()).unwrap().as_u64(), value);
         }
     }
+
+    #[test]
+    fn uint_lengths() {
+        // The length up to and including the most significant non-zero byte
+        let expected = |value: u64| {
+            let bytes = value.to_le_bytes();
+            bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1)
+        };
+
+        for value in 0..100_000 {
+            assert_eq!(uint_len(value), expected(value), "{}", value);
+        }
+        for shift in 0..64 {
+            for value in [(1 << shift) - 1, 1 << shift, (1 << shift) + 1] {
+                assert_eq!(uint_len(value), expected(value), "{}", value);
+            }
+        }
+        assert_eq!(uint_len(0), 0);
+        assert_eq!(uint_len(255), 1);
+        assert_eq!(uint_len(256), 2);
+        assert_eq!(uint_len(65_535), 2);
+        assert_eq!(uint_len(65_536), 3);
+        assert_eq!(uint_len(u64::MAX), 8);
+    }
 }
@@ -1,9 +1,20 @@
 #![allow(clippy::bool_assert_comparison)]
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod fast;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod flatbin;
+pub mod options;
+pub mod registry;
 pub mod slow;
 mod tests;
 pub mod ty;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "derive")]
+pub use talk_serde_dyn_schema_derive::Schema;
 
 pub type JsonValue = serde_json::Value;
@@ -0,0 +1,273 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use thiserror::Error;
+
+/// The default value of [`DeserializeOptions::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Options controlling the deserialization of JSON documents into flatbin.
+#[derive(Clone, Debug)]
+pub struct DeserializeOptions {
+    /// The maximum number of arrays and structs that may be nested inside one another.
+    pub max_depth: usize,
+    /// Whether to write floats in a canonical form, normalizing `-0.0` to `0.0` and every NaN to a single bit pattern.
+    ///
+    /// This makes equal numbers encode to equal bytes, e.g. for hashing or content-addressed storage,
+    /// at the cost of not round-tripping the exact bits of the input. Off by default.
+    pub canonical_floats: bool,
+    /// The maximum number of elements in any single array, or `None` for no limit.
+    pub max_array_len: Option<usize>,
+    /// The maximum length of any single byte array, or `None` for no limit.
+    pub max_bytes_len: Option<usize>,
+    /// How byte arrays are represented in JSON. This must match the encoding they were serialized with.
+    pub bytes_encoding: BytesEncoding,
+    /// Whether struct keys are matched against field names ignoring ASCII case. Off by default.
+    ///
+    /// Keys which differ only in case then refer to the same field, so are reported as duplicates. Matching
+    /// becomes a case-folding comparison against every key of every field, which is slower than an exact match.
+    pub case_insensitive: bool,
+    /// Whether an array element which fails to deserialize is reported with its index, e.g. `array element 3 does
+    /// not match the element type: invalid type: string "x", expected a boolean`. Off by default.
+    pub strict_arrays: bool,
+    /// Whether integers and floats may also be given as strings, e.g. `"12345678901234567890"`, as produced by
+    /// JavaScript for numbers beyond the precision of a double. Strings which don't parse as the expected kind of
+    /// number are still rejected. Off by default.
+    pub accept_stringified_numbers: bool,
+    /// Whether structs (including the rows of columnar arrays) are given as arrays of their field values in
+    /// declaration order, e.g. `["Alice", 31]`, rather than as objects. Such an array must hold exactly one value per
+    /// field. This must match how they were serialized. Off by default.
+    pub struct_as_array: bool,
+    /// Whether booleans may also be given as the integers `0` and `1`, as written by
+    /// [`SerializeOptions::bools_as_ints`]. Either form is then accepted. Off by default.
+    pub bools_as_ints: bool,
+    /// Whether booleans may also be given as the strings `"true"` and `"false"`, or `"1"` and `"0"`, as sent by
+    /// some legacy producers. Any other string is still rejected. Off by default.
+    pub lenient_bools: bool,
+    /// Whether a struct with a single field which isn't optional is given as the value of that field, as written
+    /// by [`SerializeOptions::unwrap_single_fields`], rather than as an object. Documents then take the shape of
+    /// [`Ty::unwrap_single_field`](crate::ty::Ty::unwrap_single_field), and encode to the same bytes as they would
+    /// with the wrapped type. This must match how they were serialized. Off by default.
+    pub unwrap_single_fields: bool,
+}
+
+impl DeserializeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the maximum number of elements in a run-length encoded array, which is capped at
+    /// [`MAX_RLE_LEN`](crate::flatbin::MAX_RLE_LEN) however long [`max_array_len`](Self::max_array_len) allows
+    /// arrays to be.
+    pub(crate) fn max_rle_len(&self) -> usize {
+        let max = crate::flatbin::MAX_RLE_LEN;
+        self.max_array_len.map_or(max, |max_array_len| max_array_len.min(max))
+    }
+}
+
+impl Default for DeserializeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            canonical_floats: false,
+            max_array_len: None,
+            max_bytes_len: None,
+            bytes_encoding: BytesEncoding::default(),
+            case_insensitive: false,
+            strict_arrays: false,
+            accept_stringified_numbers: false,
+            struct_as_array: false,
+            bools_as_ints: false,
+            lenient_bools: false,
+            unwrap_single_fields: false,
+        }
+    }
+}
+
+/// The largest integer which a double, and so a JavaScript number, can represent exactly: 2<sup>53</sup> - 1.
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Options controlling the serialization of flatbin documents into JSON.
+#[derive(Clone, Debug)]
+pub struct SerializeOptions {
+    /// How byte arrays are represented in JSON.
+    pub bytes_encoding: BytesEncoding,
+    /// Whether integers whose magnitude exceeds [`MAX_SAFE_INTEGER`] are written as strings, e.g.
+    /// `"12345678901234567890"`, so that JavaScript consumers don't silently round them. Off by default.
+    ///
+    /// This trades type fidelity for precision: such integers no longer appear as JSON numbers, so must be
+    /// read back with [`DeserializeOptions::accept_stringified_numbers`].
+    pub large_ints_as_strings: bool,
+    /// Whether structs (including the rows of columnar arrays) are written as arrays of their field values in
+    /// declaration order, e.g. `["Alice", 31]`, rather than as objects, leaving out the keys. Off by default.
+    pub struct_as_array: bool,
+    /// Whether booleans are written as the integers `0` and `1` rather than `false` and `true`, e.g. for consumers
+    /// expecting boolean masks as `[0, 1, 1, 0]`. Off by default.
+    pub bools_as_ints: bool,
+    /// Whether a struct with a single field which isn't optional is written as the value of that field rather than
+    /// as an object, e.g. `5` rather than `{ "id": 5 }`. Off by default.
+    ///
+    /// Such a struct is encoded exactly like its field, so this writes the same document as serializing with the
+    /// type given by [`Ty::unwrap_single_field`](crate::ty::Ty::unwrap_single_field), and reading it back with
+    /// [`DeserializeOptions::unwrap_single_fields`] gives the original bytes.
+    pub unwrap_single_fields: bool,
+}
+
+impl SerializeOptions {
+    pub const fn new() -> Self {
+        Self {
+            bytes_encoding: BytesEncoding::NumberArray,
+            large_ints_as_strings: false,
+            struct_as_array: false,
+            bools_as_ints: false,
+            unwrap_single_fields: false,
+        }
+    }
+
+    /// Returns whether an integer of magnitude `value` is written as a string.
+    pub(crate) fn stringify_int(&self, value: u64) -> bool {
+        self.large_ints_as_strings && value > MAX_SAFE_INTEGER
+    }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options controlling [`Ty::simplify_with`](crate::ty::Ty::simplify_with). Each enables a rewrite which changes
+/// the JSON documents a type accepts, so all are off by default.
+#[derive(Clone, Debug, Default)]
+pub struct SimplifyOptions {
+    /// Whether an enum with a single variant is replaced by a struct of that variant's fields, so that documents
+    /// give the fields directly rather than under the variant's name or alongside a tag. This also changes the
+    /// encoding, which no longer holds the variant index. Enums whose only variant has no fields are kept.
+    pub collapse_single_variant_enums: bool,
+    /// Whether a struct with a single field is replaced by the type of its field, as by
+    /// [`Ty::unwrap_single_field`](crate::ty::Ty::unwrap_single_field). The encoding is unchanged.
+    pub unwrap_single_fields: bool,
+}
+
+impl SimplifyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The representation of a [`Ty::Bytes`](crate::ty::Ty::Bytes) value in JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// An array of numbers between 0 and 255.
+    #[default]
+    NumberArray,
+    /// A string of standard, padded base64.
+    Base64,
+    /// A string of lowercase hexadecimal digits, two per byte. Either case, and an optional `0x` prefix, are
+    /// accepted when decoding.
+    Hex,
+    /// A string of lowercase hexadecimal digits prefixed with `0x`, as is conventional for hashes and keys.
+    /// Decoding accepts the same strings as [`BytesEncoding::Hex`], with or without the prefix.
+    PrefixedHex,
+}
+
+impl BytesEncoding {
+    /// Encodes `bytes` as a string, or returns `None` for [`BytesEncoding::NumberArray`].
+    pub(crate) fn encode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            BytesEncoding::NumberArray => None,
+            BytesEncoding::Base64 => Some(BASE64.encode(bytes)),
+            BytesEncoding::Hex => Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect()),
+            BytesEncoding::PrefixedHex => BytesEncoding::Hex.encode(bytes).map(|hex| format!("0x{}", hex)),
+        }
+    }
+
+    /// Decodes a string produced by [`encode`](Self::encode), or returns `None` if it is malformed.
+    pub(crate) fn decode(self, str: &str) -> Option<Vec<u8>> {
+        match self {
+            BytesEncoding::NumberArray => None,
+            BytesEncoding::Base64 => BASE64.decode(str).ok(),
+            BytesEncoding::Hex | BytesEncoding::PrefixedHex => str
+                .strip_prefix("0x")
+                .or_else(|| str.strip_prefix("0X"))
+                .unwrap_or(str)
+                .as_bytes()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [hi, lo] => Some(((*hi as char).to_digit(16)? << 4 | (*lo as char).to_digit(16)?) as u8),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for BytesEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BytesEncoding::NumberArray => write!(f, "number array"),
+            BytesEncoding::Base64 => write!(f, "base64"),
+            BytesEncoding::Hex => write!(f, "hex"),
+            BytesEncoding::PrefixedHex => write!(f, "0x-prefixed hex"),
+        }
+    }
+}
+
+/// A limit on the total size of the documents deserialized with it.
+///
+/// The budget is spent as values are written, and deserialization fails as soon as either limit is exceeded.
+/// Every value counts as one node, including arrays and structs as well as their elements. Bytes count the
+/// contents of strings and byte arrays, the only values whose encoded size isn't already bounded by the number
+/// of nodes. A budget may be reused across several documents to limit their combined size, including documents
+/// deserialized concurrently on several threads.
+#[derive(Debug)]
+pub struct Budget {
+    nodes: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+/// The limit of a [`Budget`] which was exceeded.
+#[derive(Error, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BudgetExceeded {
+    #[error("document exceeds the node budget")]
+    Nodes,
+    #[error("document exceeds the byte budget")]
+    Bytes,
+}
+
+impl Budget {
+    pub fn new(max_nodes: usize, max_bytes: usize) -> Self {
+        Self {
+            nodes: AtomicUsize::new(max_nodes),
+            bytes: AtomicUsize::new(max_bytes),
+        }
+    }
+
+    pub fn remaining_nodes(&self) -> usize {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    pub fn remaining_bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn spend_node(&self) -> Result<(), BudgetExceeded> {
+        spend(&self.nodes, 1).ok_or(BudgetExceeded::Nodes)
+    }
+
+    pub(crate) fn spend_bytes(&self, count: usize) -> Result<(), BudgetExceeded> {
+        spend(&self.bytes, count).ok_or(BudgetExceeded::Bytes)
+    }
+}
+
+/// Subtracts `count` from `remaining`, leaving it unchanged if it is too small.
+fn spend(remaining: &AtomicUsize, count: usize) -> Option<()> {
+    remaining
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| {
+            remaining.checked_sub(count)
+        })
+        .ok()
+        .map(drop)
+}
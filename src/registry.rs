@@ -0,0 +1,141 @@
+//! Numbering schemas so that encoded documents can name their own type.
+//!
+//! A framed document is the 4-byte little-endian ID of its schema in a [`SchemaRegistry`], followed by the
+//! flatbin encoding of the document. Producers and consumers which register the same schemas in the same order
+//! agree on their IDs, which they can check by comparing [`fingerprint`](SchemaRegistry::fingerprint)s.
+
+use crate::{
+    fast,
+    flatbin::{self, Flatbin},
+    ty::Ty,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("unknown schema ID {0}")]
+    UnknownId(u32),
+    #[error("document is too short to contain a schema ID")]
+    MissingId,
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Flatbin(#[from] flatbin::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RegistryError>;
+
+/// Assigns each registered schema a numeric ID, in order of registration starting from zero.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaRegistry {
+    schemas: Vec<(Ty, u64)>,
+    ids: HashMap<u64, u32>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a schema, returning its ID. Registering an equal schema again returns the same ID.
+    pub fn register(&mut self, ty: Ty) -> u32 {
+        let fingerprint = fingerprint(&ty);
+        if let Some(&id) = self.ids.get(&fingerprint) {
+            if self.schemas[id as usize].0 == ty {
+                return id;
+            }
+        }
+        let id = u32::try_from(self.schemas.len()).expect("too many schemas");
+        self.schemas.push((ty, fingerprint));
+        self.ids.entry(fingerprint).or_insert(id);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Ty> {
+        self.schemas.get(id as usize).map(|(ty, _)| ty)
+    }
+
+    /// Returns a hash of the schema with the given ID, which is the same in every process and on every platform.
+    pub fn fingerprint(&self, id: u32) -> Option<u64> {
+        self.schemas.get(id as usize).map(|&(_, fingerprint)| fingerprint)
+    }
+
+    /// Returns the ID of the schema with the given fingerprint.
+    pub fn id_of(&self, fingerprint: u64) -> Option<u32> {
+        self.ids.get(&fingerprint).copied()
+    }
+
+    /// Encodes JSON text as a document of the schema `id`, prefixed with the ID.
+    pub fn encode(&self, id: u32, json: &str) -> Result<Vec<u8>> {
+        let ty = self.get(id).ok_or(RegistryError::UnknownId(id))?;
+        let mut bytes = id.to_le_bytes().to_vec();
+        bytes.extend(fast::deserialize(ty, json)?.as_bytes());
+        Ok(bytes)
+    }
+
+    /// Reads the schema ID from the front of a framed document, returning the ID, its schema and the
+    /// document, which is validated against the schema.
+    pub fn split<'a>(&self, bytes: &'a [u8]) -> Result<(u32, &Ty, &'a Flatbin)> {
+        let (id, value) = bytes.split_first_chunk::<4>().ok_or(RegistryError::MissingId)?;
+        let id = u32::from_le_bytes(*id);
+        let ty = self.get(id).ok_or(RegistryError::UnknownId(id))?;
+        Ok((id, ty, Flatbin::try_from_bytes(value, ty)?))
+    }
+
+    /// Decodes a framed document as JSON text, returning it with its schema ID.
+    pub fn decode(&self, bytes: &[u8]) -> Result<(u32, String)> {
+        let (id, ty, value) = self.split(bytes)?;
+        let mut json = vec![];
+        fast::serialize(&mut serde_json::Serializer::new(&mut json), ty, value)?;
+        let json = String::from_utf8(json).expect("serde_json writes UTF-8");
+        Ok((id, json))
+    }
+}
+
+/// The 64-bit FNV-1a hash of the schema's JSON representation.
+fn fingerprint(ty: &Ty) -> u64 {
+    let json = serde_json::to_vec(ty).expect("types serialize to JSON");
+    json.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RegistryError, SchemaRegistry};
+    use crate::{array_def, struct_def, ty::Ty};
+
+    #[test]
+    fn registry() {
+        let mut registry = SchemaRegistry::new();
+        let person = struct_def!({ "name": Ty::String, "age": Ty::U64 });
+        let tags = array_def!(Ty::String);
+        assert_eq!(registry.register(person.clone()), 0);
+        assert_eq!(registry.register(tags.clone()), 1);
+        assert_eq!(registry.register(person.clone()), 0);
+
+        // Fingerprints are stable, so are compared against a known value
+        let fingerprint = registry.fingerprint(1).unwrap();
+        assert_eq!(fingerprint, 899811284337230077);
+        assert_ne!(fingerprint, registry.fingerprint(0).unwrap());
+        assert_eq!(registry.id_of(fingerprint), Some(1));
+
+        let json = r#"{"name":"Alice","age":31}"#;
+        let bytes = registry.encode(0, json).unwrap();
+        assert_eq!(bytes[..4], [0, 0, 0, 0]);
+        assert_eq!(registry.decode(&bytes).unwrap(), (0, json.to_string()));
+        let (id, ty, _) = registry.split(&bytes).unwrap();
+        assert_eq!((id, ty), (0, &person));
+
+        let bytes = registry.encode(1, r#"["a","b"]"#).unwrap();
+        assert_eq!(registry.decode(&bytes).unwrap(), (1, r#"["a","b"]"#.to_string()));
+
+        assert!(matches!(registry.encode(2, "[]"), Err(RegistryError::UnknownId(2))));
+        assert!(matches!(
+            registry.decode(&[2, 0, 0, 0]),
+            Err(RegistryError::UnknownId(2))
+        ));
+        assert!(matches!(registry.decode(&[0, 0]), Err(RegistryError::MissingId)));
+    }
+}
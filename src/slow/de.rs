@@ -1,8 +1,10 @@
 use crate::{
-    flatbin::{Builder as FlatbinBuilder, FlatbinBuf},
-    ty::Ty,
+    flatbin::{canonical_f64, Builder as FlatbinBuilder, FlatbinBuf, RleBuilder},
+    options::{Budget, BudgetExceeded, BytesEncoding, DeserializeOptions},
+    ty::{Field, MapLayout, PathSegment, Ty, Variant},
     JsonValue,
 };
+use std::str::FromStr;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -13,6 +15,24 @@ pub enum Error {
     NotAByte,
     #[error("missing field: {name}")]
     MissingField { name: Box<str> },
+    #[error("duplicate field: {name}")]
+    DuplicateField { name: Box<str> },
+    #[error("maximum nesting depth exceeded")]
+    DepthExceeded,
+    #[error("array exceeds maximum length of {max}")]
+    ArrayTooLong { max: usize },
+    #[error("byte array exceeds maximum length of {max}")]
+    BytesTooLong { max: usize },
+    #[error("unknown variant: {name}")]
+    UnknownVariant { name: Box<str> },
+    #[error("expected an array of {expected} field values, got {found}")]
+    WrongArity { expected: usize, found: usize },
+    #[error("array element {index} does not match the element type: {source}")]
+    InvalidElement { index: usize, source: Box<Error> },
+    #[error("string is not a valid {encoding} byte array")]
+    InvalidBytes { encoding: BytesEncoding },
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -24,50 +44,404 @@ pub fn deserialize_alloc(ty: &Ty, value: &JsonValue) -> Result<FlatbinBuf> {
 }
 
 pub fn deserialize(ty: &Ty, value: &JsonValue, builder: FlatbinBuilder) -> Result<()> {
+    deserialize_with(ty, value, builder, &DeserializeOptions::default())
+}
+
+pub fn deserialize_with(
+    ty: &Ty,
+    value: &JsonValue,
+    builder: FlatbinBuilder,
+    options: &DeserializeOptions,
+) -> Result<()> {
+    deserialize_inner(ty, value, builder, options, None, 0)
+}
+
+pub fn deserialize_budgeted(
+    ty: &Ty,
+    value: &JsonValue,
+    builder: FlatbinBuilder,
+    options: &DeserializeOptions,
+    budget: &Budget,
+) -> Result<()> {
+    deserialize_inner(ty, value, builder, options, Some(budget), 0)
+}
+
+/// Checks that `value` can be deserialized as `ty`, returning every error found along with the path to the node it
+/// was found in, in document order. An empty result means the value is valid.
+///
+/// Rather than stopping at the first error, this keeps walking the rest of the document, so that e.g. every missing
+/// field of a submitted form can be reported at once. Errors about a struct's fields, such as
+/// [`Error::MissingField`], are reported at the path of the struct. Nothing is written, and each value is converted
+/// into a scratch buffer of its own, so this is slower and allocates more than [`deserialize_with`].
+pub fn collect_errors<'a>(
+    ty: &'a Ty,
+    value: &'a JsonValue,
+    options: &DeserializeOptions,
+) -> Vec<(Vec<PathSegment<'a>>, Error)> {
+    let mut collector = Collector {
+        options,
+        path: vec![],
+        errors: vec![],
+    };
+    collector.check(ty, value, 0);
+    collector.errors
+}
+
+/// The state of [`collect_errors`]: the path to the node being checked, and the errors found so far.
+struct Collector<'a, 'o> {
+    options: &'o DeserializeOptions,
+    path: Vec<PathSegment<'a>>,
+    errors: Vec<(Vec<PathSegment<'a>>, Error)>,
+}
+
+impl<'a> Collector<'a, '_> {
+    fn check(&mut self, ty: &'a Ty, value: &'a JsonValue, depth: usize) {
+        let options = self.options;
+        let compound = matches!(
+            ty,
+            Ty::Array { .. }
+                | Ty::RleArray { .. }
+                | Ty::Map { .. }
+                | Ty::Struct { .. }
+                | Ty::Columnar { .. }
+                | Ty::Enum { .. }
+        );
+        if compound && depth >= options.max_depth {
+            return self.error(Error::DepthExceeded);
+        }
+        let depth = depth + 1;
+        match ty {
+            Ty::Array { inner } | Ty::RleArray { inner } => {
+                let max = match ty {
+                    Ty::RleArray { .. } => Some(options.max_rle_len()),
+                    _ => options.max_array_len,
+                };
+                let Some(array) = self.array(value, max) else { return };
+                for (index, element) in array.iter().enumerate() {
+                    self.at(PathSegment::Index(index), |c| c.check(inner, element, depth));
+                }
+            }
+            Ty::Map { value: inner, .. } => {
+                let Some(object) = value.as_object() else {
+                    return self.error(unexpected_type("an object", value));
+                };
+                if let Some(max) = options.max_array_len.filter(|&max| object.len() > max) {
+                    self.error(Error::ArrayTooLong { max });
+                }
+                for (key, value) in object {
+                    self.at(PathSegment::Field(key), |c| c.check(inner, value, depth + 1));
+                }
+            }
+            Ty::Struct { fields } => match Field::only_field(fields).filter(|_| options.unwrap_single_fields) {
+                Some(field) => self.check(&field.ty, value, depth),
+                None => self.check_struct(fields, value, depth, true),
+            },
+            Ty::Columnar { fields } => {
+                let Some(array) = self.array(value, options.max_array_len) else {
+                    return;
+                };
+                for (index, row) in array.iter().enumerate() {
+                    self.at(PathSegment::Index(index), |c| {
+                        c.check_struct(fields, row, depth + 1, false)
+                    });
+                }
+            }
+            Ty::Enum { variants, tag } => {
+                let (index, values) = match enum_values_each(value, variants, tag.as_deref(), options) {
+                    Ok(variant) => variant,
+                    Err(error) => return self.error(error),
+                };
+                let fields = &variants[index].fields;
+                let check_fields = |c: &mut Self| c.check_fields(fields, values, depth + 1, true);
+                match (tag, value) {
+                    (None, JsonValue::Object(_)) => self.at(PathSegment::Field(&variants[index].name), check_fields),
+                    _ => check_fields(self),
+                }
+            }
+            _ => {
+                let mut scratch = FlatbinBuf::new();
+                let builder = FlatbinBuilder::new(&mut scratch);
+                if let Err(error) = deserialize_inner(ty, value, builder, options, None, depth) {
+                    self.error(error);
+                }
+            }
+        }
+    }
+
+    fn check_struct(&mut self, fields: &'a [Field], value: &'a JsonValue, depth: usize, omit: bool) {
+        match struct_values_each(value, fields, self.options) {
+            Ok(values) => self.check_fields(fields, values, depth, omit),
+            Err(error) => self.error(error),
+        }
+    }
+
+    /// Checks the value of each field that was found, reporting each that wasn't unless `omit` is set and the field
+    /// may be left out. The rows of a columnar array must give every field.
+    fn check_fields(&mut self, fields: &'a [Field], values: Vec<Result<&'a JsonValue>>, depth: usize, omit: bool) {
+        for (field, value) in fields.iter().zip(values) {
+            let value = match omit {
+                true => optional_value(field, value),
+                false => value.map(Some),
+            };
+            match value {
+                Ok(Some(value)) => self.at(PathSegment::Field(field.json_key()), |c| {
+                    c.check(&field.ty, value, depth)
+                }),
+                Ok(None) => {}
+                Err(error) => self.error(error),
+            }
+        }
+    }
+
+    /// Returns the elements of an array, or reports that the value isn't one, reporting too if it has more than `max`
+    /// elements.
+    fn array(&mut self, value: &'a JsonValue, max: Option<usize>) -> Option<&'a [JsonValue]> {
+        let Some(array) = value.as_array() else {
+            self.error(unexpected_type("an array", value));
+            return None;
+        };
+        if let Some(max) = max.filter(|&max| array.len() > max) {
+            self.error(Error::ArrayTooLong { max });
+        }
+        Some(array)
+    }
+
+    fn at(&mut self, segment: PathSegment<'a>, check: impl FnOnce(&mut Self)) {
+        self.path.push(segment);
+        check(self);
+        self.path.pop();
+    }
+
+    fn error(&mut self, error: Error) {
+        self.errors.push((self.path.clone(), error));
+    }
+}
+
+fn float_value(value: &JsonValue, options: &DeserializeOptions) -> Result<f64> {
+    (value.as_f64().or_else(|| parse_stringified(value, options)))
+        .filter(|value: &f64| value.is_finite())
+        .ok_or(unexpected_type("a number", value))
+}
+
+fn deserialize_inner(
+    ty: &Ty,
+    value: &JsonValue,
+    builder: FlatbinBuilder,
+    options: &DeserializeOptions,
+    budget: Option<&Budget>,
+    depth: usize,
+) -> Result<()> {
+    if matches!(
+        ty,
+        Ty::Array { .. }
+            | Ty::RleArray { .. }
+            | Ty::Map { .. }
+            | Ty::Struct { .. }
+            | Ty::Columnar { .. }
+            | Ty::Enum { .. }
+    ) && depth >= options.max_depth
+    {
+        return Err(Error::DepthExceeded);
+    }
+    if let Some(budget) = budget {
+        budget.spend_node()?;
+    }
+    let depth = depth + 1;
     match ty {
         Ty::Bool => {
-            let value = value.as_bool().ok_or(unexpected_type("a boolean", value))?;
+            let value = (value.as_bool())
+                .or_else(|| bool_from_int(value, options))
+                .or_else(|| bool_from_str(value, options))
+                .ok_or(unexpected_type("a boolean", value))?;
             builder.write_bool(value);
         }
         Ty::U64 => {
-            let value = value.as_u64().ok_or(unexpected_type("a non-negative integer", value))?;
+            let value = (value.as_u64().or_else(|| parse_stringified(value, options)))
+                .ok_or(unexpected_type("a non-negative integer", value))?;
             builder.write_u64(value);
         }
         Ty::I64 => {
-            let value = value.as_i64().ok_or(unexpected_type("an integer", value))?;
+            let value = (value.as_i64().or_else(|| parse_stringified(value, options)))
+                .ok_or(unexpected_type("an integer", value))?;
             builder.write_i64(value);
         }
         Ty::F64 => {
-            let value = value.as_f64().ok_or(unexpected_type("a number", value))?;
-            builder.write_f64(value);
+            let value = float_value(value, options)?;
+            match options.canonical_floats {
+                true => builder.write_f64_canonical(value),
+                false => builder.write_f64(value),
+            }
         }
-        Ty::Bytes => {
-            let value = value.as_array().ok_or(unexpected_type("a byte array", value))?;
-            let bytes = value
+        Ty::FloatVec => {
+            let array = value.as_array().ok_or(unexpected_type("an array of numbers", value))?;
+            if let Some(max) = options.max_array_len {
+                if array.len() > max {
+                    return Err(Error::ArrayTooLong { max });
+                }
+            }
+            if let Some(budget) = budget {
+                budget.spend_bytes(8 * array.len())?;
+            }
+            let values = array
                 .iter()
-                .map(|value| value.as_u64()?.try_into().ok())
-                .collect::<Option<Vec<u8>>>()
-                .ok_or(Error::NotAByte)?;
-            builder.write_bytes(&bytes);
+                .map(|value| match options.canonical_floats {
+                    true => float_value(value, options).map(canonical_f64),
+                    false => float_value(value, options),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            builder.write_float_vec(&values);
+        }
+        Ty::Bytes | Ty::ChunkedBytes { .. } => {
+            let bytes = match options.bytes_encoding {
+                BytesEncoding::NumberArray => {
+                    let value = value.as_array().ok_or(unexpected_type("a byte array", value))?;
+                    if let Some(max) = options.max_bytes_len {
+                        if value.len() > max {
+                            return Err(Error::BytesTooLong { max });
+                        }
+                    }
+                    value
+                        .iter()
+                        .map(|value| value.as_u64()?.try_into().ok())
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or(Error::NotAByte)?
+                }
+                encoding => {
+                    let value = value.as_str().ok_or(unexpected_type("a string", value))?;
+                    let bytes = encoding.decode(value).ok_or(Error::InvalidBytes { encoding })?;
+                    if let Some(max) = options.max_bytes_len {
+                        if bytes.len() > max {
+                            return Err(Error::BytesTooLong { max });
+                        }
+                    }
+                    bytes
+                }
+            };
+            if let Some(budget) = budget {
+                budget.spend_bytes(bytes.len())?;
+            }
+            match ty {
+                Ty::ChunkedBytes { chunk_size } => builder.write_chunked_bytes(&bytes, *chunk_size),
+                _ => builder.write_bytes(&bytes),
+            }
         }
         Ty::String => {
             let value = value.as_str().ok_or(unexpected_type("a string", value))?;
+            if let Some(budget) = budget {
+                budget.spend_bytes(value.len())?;
+            }
             builder.write_str(value);
         }
         Ty::Array { inner } => {
             let array = value.as_array().ok_or(unexpected_type("an array", value))?;
+            if let Some(max) = options.max_array_len {
+                if array.len() > max {
+                    return Err(Error::ArrayTooLong { max });
+                }
+            }
             let mut vector = builder.start_vector();
-            for element in array {
-                deserialize(inner, element, vector.as_builder())?;
+            vector.reserve(inner.array_reservation(array.len()));
+            for (index, element) in array.iter().enumerate() {
+                deserialize_inner(inner, element, vector.as_builder(), options, budget, depth).map_err(|source| {
+                    match options.strict_arrays {
+                        true => Error::InvalidElement {
+                            index,
+                            source: source.into(),
+                        },
+                        false => source,
+                    }
+                })?;
             }
             vector.end();
         }
-        Ty::Struct { fields } => {
+        Ty::Map { value: inner, layout } => {
             let object = value.as_object().ok_or(unexpected_type("an object", value))?;
+            if let Some(max) = options.max_array_len {
+                if object.len() > max {
+                    return Err(Error::ArrayTooLong { max });
+                }
+            }
+            let mut entries: Vec<_> = object.iter().collect();
+            if *layout == MapLayout::Ordered {
+                entries.sort_unstable_by_key(|&(key, _)| key);
+            }
+            let mut vector = builder.start_vector();
+            for (key, value) in entries {
+                if let Some(budget) = budget {
+                    budget.spend_node()?;
+                    budget.spend_bytes(key.len())?;
+                }
+                let mut entry = vector.as_builder().start_tuple();
+                entry.as_builder().write_str(key);
+                deserialize_inner(inner, value, entry.as_builder(), options, budget, depth + 1)?;
+                entry.end();
+            }
+            vector.end();
+        }
+        Ty::RleArray { inner } => {
+            let array = value.as_array().ok_or(unexpected_type("an array", value))?;
+            let max = options.max_rle_len();
+            if array.len() > max {
+                return Err(Error::ArrayTooLong { max });
+            }
+            let mut runs = RleBuilder::new(builder);
+            for element in array {
+                deserialize_inner(inner, element, runs.as_builder(), options, budget, depth)?;
+                runs.push();
+            }
+            runs.end();
+        }
+        Ty::Struct { fields } => {
+            // The struct is encoded exactly like its only field, so the field's value is written in its place
+            if let Some(field) = Field::only_field(fields).filter(|_| options.unwrap_single_fields) {
+                return deserialize_inner(&field.ty, value, builder, options, budget, depth);
+            }
+            let values = struct_values_each(value, fields, options)?;
+            let mut tuple = builder.start_tuple();
+            for (field, value) in fields.iter().zip(values) {
+                match optional_value(field, value)? {
+                    Some(value) => deserialize_inner(&field.ty, value, tuple.as_builder(), options, budget, depth)?,
+                    None => tuple.as_builder().write_void(),
+                }
+            }
+            tuple.end_struct(fields);
+        }
+        Ty::Enum { variants, tag } => {
+            let (index, values) = enum_values_each(value, variants, tag.as_deref(), options)?;
+            let variant_fields = &variants[index].fields;
             let mut tuple = builder.start_tuple();
-            for field in fields.iter() {
-                let value = object.get(&*field.name).ok_or(missing_field(&field.name))?;
-                deserialize(&field.ty, value, tuple.as_builder())?;
+            tuple.as_builder().write_u64(index as u64);
+            let mut fields = tuple.as_builder().start_tuple();
+            for (field, value) in variant_fields.iter().zip(values) {
+                match optional_value(field, value)? {
+                    Some(value) => {
+                        deserialize_inner(&field.ty, value, fields.as_builder(), options, budget, depth + 1)?
+                    }
+                    None => fields.as_builder().write_void(),
+                }
+            }
+            fields.end_struct(variant_fields);
+            tuple.end();
+        }
+        Ty::Columnar { fields } => {
+            let array = value.as_array().ok_or(unexpected_type("an array", value))?;
+            if let Some(max) = options.max_array_len {
+                if array.len() > max {
+                    return Err(Error::ArrayTooLong { max });
+                }
+            }
+            let rows = array
+                .iter()
+                .map(|row| struct_values(row, fields, options))
+                .collect::<Result<Vec<_>>>()?;
+            let mut tuple = builder.start_tuple();
+            tuple.as_builder().write_u64(rows.len() as u64);
+            for (index, field) in fields.iter().enumerate() {
+                let mut column = tuple.as_builder().start_tuple();
+                for row in rows.iter() {
+                    deserialize_inner(&field.ty, row[index], column.as_builder(), options, budget, depth + 1)?;
+                }
+                column.end();
             }
             tuple.end();
         }
@@ -94,6 +468,32 @@ pub fn deserialize(ty: &Ty, value: &JsonValue, builder: FlatbinBuilder) -> Resul
 //     Ok(())
 // }
 
+/// Reads a boolean given as `0` or `1`, if [`DeserializeOptions::bools_as_ints`] is set.
+fn bool_from_int(value: &JsonValue, options: &DeserializeOptions) -> Option<bool> {
+    match (options.bools_as_ints, value.as_u64()?) {
+        (true, 0) => Some(false),
+        (true, 1) => Some(true),
+        _ => None,
+    }
+}
+
+/// Reads a boolean given as a string, if [`DeserializeOptions::lenient_bools`] is set.
+fn bool_from_str(value: &JsonValue, options: &DeserializeOptions) -> Option<bool> {
+    match (options.lenient_bools, value.as_str()?) {
+        (true, "true" | "1") => Some(true),
+        (true, "false" | "0") => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a number given as a string, if [`DeserializeOptions::accept_stringified_numbers`] is set.
+fn parse_stringified<T: FromStr>(value: &JsonValue, options: &DeserializeOptions) -> Option<T> {
+    match options.accept_stringified_numbers {
+        true => value.as_str()?.parse().ok(),
+        false => None,
+    }
+}
+
 fn unexpected_type(expected: &'static str, value: &JsonValue) -> Error {
     let got = match value {
         JsonValue::Null => "null",
@@ -106,6 +506,120 @@ fn unexpected_type(expected: &'static str, value: &JsonValue) -> Error {
     Error::UnexpectedType { expected, got }
 }
 
-fn missing_field(name: &str) -> Error {
-    Error::MissingField { name: name.into() }
+/// Returns the value of each field of a struct, given as an object or, with
+/// [`DeserializeOptions::struct_as_array`], as an array of the values in order.
+fn struct_values<'a>(
+    value: &'a JsonValue,
+    fields: &[Field],
+    options: &DeserializeOptions,
+) -> Result<Vec<&'a JsonValue>> {
+    struct_values_each(value, fields, options)?.into_iter().collect()
+}
+
+/// Returns the value of each field of a struct as [`struct_values`] does, with an error for each field whose value
+/// couldn't be found rather than for only the first.
+fn struct_values_each<'a>(
+    value: &'a JsonValue,
+    fields: &[Field],
+    options: &DeserializeOptions,
+) -> Result<Vec<Result<&'a JsonValue>>> {
+    if options.struct_as_array {
+        let array = value.as_array().ok_or(unexpected_type("an array", value))?;
+        return match array.len() == fields.len() {
+            true => Ok(array.iter().map(Ok).collect()),
+            false => Err(Error::WrongArity {
+                expected: fields.len(),
+                found: array.len(),
+            }),
+        };
+    }
+    let object = value.as_object().ok_or(unexpected_type("an object", value))?;
+    Ok(fields.iter().map(|field| field_value(object, field, options)).collect())
+}
+
+/// Returns the index of an enum's variant, and the value of each of its fields or an error for each that couldn't be
+/// found.
+fn enum_values_each<'a>(
+    value: &'a JsonValue,
+    variants: &[Variant],
+    tag: Option<&str>,
+    options: &DeserializeOptions,
+) -> Result<(usize, Vec<Result<&'a JsonValue>>)> {
+    let find = |name: &str| {
+        (variants.iter().position(|variant| *variant.name == *name))
+            .ok_or_else(|| Error::UnknownVariant { name: name.into() })
+    };
+    match (tag, value) {
+        (None, JsonValue::String(name)) => {
+            let index = find(name)?;
+            // Only a variant without fields may be given by its name alone
+            match variants[index].fields.first() {
+                Some(field) => Err(Error::MissingField {
+                    name: field.json_key().into(),
+                }),
+                None => Ok((index, vec![])),
+            }
+        }
+        (None, JsonValue::Object(object)) if object.len() == 1 => {
+            let (name, value) = object.iter().next().expect("object has one entry");
+            let index = find(name)?;
+            Ok((index, struct_values_each(value, &variants[index].fields, options)?))
+        }
+        (None, value) => Err(unexpected_type("a string or an object with a single key", value)),
+        (Some(tag), value) => {
+            let object = value.as_object().ok_or(unexpected_type("an object", value))?;
+            let name = object
+                .get(tag)
+                .ok_or_else(|| Error::MissingField { name: tag.into() })?;
+            let index = find(name.as_str().ok_or(unexpected_type("a string", name))?)?;
+            let values = variants[index]
+                .fields
+                .iter()
+                .map(|field| field_value(object, field, options))
+                .collect();
+            Ok((index, values))
+        }
+    }
+}
+
+/// Finds the value of `field` under any of its keys, which must only be present once.
+fn field_value<'a>(
+    object: &'a serde_json::Map<String, JsonValue>,
+    field: &Field,
+    options: &DeserializeOptions,
+) -> Result<&'a JsonValue> {
+    let mut values: Box<dyn Iterator<Item = &JsonValue>> = match options.case_insensitive {
+        true => Box::new(
+            object
+                .iter()
+                .filter(|(key, _)| field.matches_key_ignore_case(key))
+                .map(|(_, value)| value),
+        ),
+        // A key repeated among the aliases is only looked up once
+        false => Box::new(
+            field
+                .keys()
+                .enumerate()
+                .filter(|&(i, key)| !field.keys().take(i).any(|k| k == key))
+                .filter_map(|(_, key)| object.get(key)),
+        ),
+    };
+    let value = values.next().ok_or_else(|| Error::MissingField {
+        name: field.json_key().into(),
+    })?;
+    match values.next() {
+        Some(_) => Err(Error::DuplicateField {
+            name: field.json_key().into(),
+        }),
+        None => Ok(value),
+    }
+}
+
+/// Returns the value of a field, or `None` if it is optional and missing, in which case its zero value is written.
+fn optional_value<'a>(field: &Field, value: Result<&'a JsonValue>) -> Result<Option<&'a JsonValue>> {
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::MissingField { .. }) if field.omittable() => Ok(None),
+        Err(error) => Err(error),
+    }
 }
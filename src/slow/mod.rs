@@ -1,5 +1,7 @@
 pub use de::*;
 pub use ser::*;
+pub use sql::*;
 
 mod de;
 mod ser;
+mod sql;
@@ -1,26 +1,127 @@
 use crate::{
     flatbin::{self, Flatbin, Result},
-    ty::Ty,
+    options::SerializeOptions,
+    ty::{Field, Ty},
 };
 
 pub fn serialize(ty: &Ty, value: &Flatbin) -> Result<serde_json::Value> {
+    serialize_with(ty, value, &SerializeOptions::default())
+}
+
+pub fn serialize_with(ty: &Ty, value: &Flatbin, options: &SerializeOptions) -> Result<serde_json::Value> {
+    let serialize = |ty, value| serialize_with(ty, value, options);
     Ok(match ty {
-        Ty::Bool => value.read_bool()?.into(),
-        Ty::U64 => value.read_u64()?.into(),
-        Ty::I64 => value.read_i64()?.into(),
+        Ty::Bool => match value.read_bool()? {
+            value if options.bools_as_ints => (value as u64).into(),
+            value => value.into(),
+        },
+        Ty::U64 => match value.read_u64()? {
+            value if options.stringify_int(value) => value.to_string().into(),
+            value => value.into(),
+        },
+        Ty::I64 => match value.read_i64()? {
+            value if options.stringify_int(value.unsigned_abs()) => value.to_string().into(),
+            value => value.into(),
+        },
         Ty::F64 => value.read_f64()?.into(),
-        Ty::Bytes => value.read_bytes()?.into(),
+        Ty::FloatVec => value.read_float_vec()?.collect::<Vec<_>>().into(),
+        Ty::Bytes => {
+            let bytes = value.read_bytes()?;
+            match options.bytes_encoding.encode(bytes) {
+                Some(encoded) => encoded.into(),
+                None => bytes.into(),
+            }
+        }
+        Ty::ChunkedBytes { .. } => {
+            let bytes = value.read_chunked_bytes()?;
+            match options.bytes_encoding.encode(&bytes) {
+                Some(encoded) => encoded.into(),
+                None => bytes.into(),
+            }
+        }
         Ty::String => value.read_str()?.into(),
         Ty::Array { inner } => value
             .read_array()?
-            .iter()
-            .map(|bytes| serialize(inner, bytes))
+            .try_iter()
+            .map(|bytes| serialize(inner, bytes?))
             .collect::<flatbin::Result<Vec<_>>>()?
             .into(),
-        Ty::Struct { fields } => fields
-            .iter()
-            .zip(value.read_tuple(fields.len())?)
-            .map(|(field, bytes)| Ok((field.name.to_string(), serialize(&field.ty, bytes)?)))
+        Ty::RleArray { inner } => value
+            .read_runs()?
+            .into_iter()
+            .map(|(count, bytes)| {
+                let count = usize::try_from(count).map_err(|_| flatbin::Error::NumberTooLarge)?;
+                Ok((count, serialize(inner, bytes)?))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flat_map(|(count, value)| std::iter::repeat_n(value, count))
+            .collect::<Vec<_>>()
+            .into(),
+        Ty::Map { value: inner, .. } => value
+            .read_array()?
+            .try_iter()
+            .map(|entry| {
+                let (key, value) = entry?.read_entry()?;
+                Ok((key.to_string(), serialize(inner, value)?))
+            })
+            .collect::<Result<serde_json::Map<_, _>>>()?
+            .into(),
+        Ty::Struct { fields } => match Field::only_field(fields).filter(|_| options.unwrap_single_fields) {
+            Some(field) => serialize(&field.ty, value)?,
+            None => serialize_fields(fields, value.read_struct(fields)?, options)?,
+        },
+        Ty::Enum { variants, tag } => {
+            let (index, values) = value.read_variant(variants)?;
+            let variant = &variants[index];
+            match tag {
+                None if variant.fields.is_empty() => variant.name.to_string().into(),
+                None => {
+                    let fields = serialize_fields(&variant.fields, values, options)?;
+                    serde_json::Map::from_iter([(variant.name.to_string(), fields)]).into()
+                }
+                Some(tag) => {
+                    let mut object = serde_json::Map::new();
+                    object.insert(tag.to_string(), variant.name.to_string().into());
+                    for (field, bytes) in variant.fields.iter().zip(values) {
+                        object.insert(field.json_key().to_string(), serialize(&field.ty, bytes)?);
+                    }
+                    object.into()
+                }
+            }
+        }
+        Ty::Columnar { fields } => {
+            let (rows, columns) = value.read_columns(fields.len())?;
+            let mut columns = columns.iter().map(|column| column.iter()).collect::<Vec<_>>();
+            (0..rows)
+                .map(|_| {
+                    let row = columns
+                        .iter_mut()
+                        .map(|column| column.next().ok_or(flatbin::Error::UnexpectedEOF))
+                        .collect::<Result<Vec<_>>>()?;
+                    serialize_fields(fields, row, options)
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into()
+        }
+    })
+}
+
+/// Serializes the values of a struct's fields, as an object or, with [`SerializeOptions::struct_as_array`], as
+/// an array.
+fn serialize_fields<'a>(
+    fields: &[Field],
+    values: impl IntoIterator<Item = &'a Flatbin>,
+    options: &SerializeOptions,
+) -> Result<serde_json::Value> {
+    let values = fields.iter().zip(values);
+    Ok(match options.struct_as_array {
+        true => values
+            .map(|(field, bytes)| serialize_with(&field.ty, bytes, options))
+            .collect::<Result<Vec<_>>>()?
+            .into(),
+        false => values
+            .map(|(field, bytes)| Ok((field.json_key().to_string(), serialize_with(&field.ty, bytes, options)?)))
             .collect::<Result<serde_json::Map<_, _>>>()?
             .into(),
     })
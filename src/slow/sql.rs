@@ -0,0 +1,203 @@
+use crate::{
+    flatbin::{self, Flatbin},
+    ty::{Field, Ty},
+};
+use thiserror::Error;
+
+/// A value bound to a parameter of an SQL statement.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SqlValue {
+    Bool(bool),
+    /// An integer, as SQL has no unsigned integers.
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Error, Debug)]
+pub enum SqlError {
+    #[error("expected an array of structs or a columnar array")]
+    NotATable,
+    #[error("column {column} is not of a primitive type")]
+    NotFlat { column: Box<str> },
+    #[error("{value} in column {column} does not fit in a signed 64-bit integer")]
+    IntegerTooLarge { column: Box<str>, value: u64 },
+    #[error(transparent)]
+    Flatbin(#[from] flatbin::Error),
+}
+
+/// Converts a table — an array of structs or a columnar array — into a parameterized `INSERT` statement for
+/// `table`, and the parameters to bind for each row.
+///
+/// The statement names each field as a quoted column and uses PostgreSQL's `$1`, `$2`, ... placeholders, e.g.
+/// `INSERT INTO people ("name", "age") VALUES ($1, $2)`. The table name is written as given, so that it may be
+/// schema-qualified, and must not come from untrusted input. Every field must be a boolean, an integer, a float, a
+/// string, a byte string, or an enum without fields (bound as the name of its variant); any other field fails with
+/// [`SqlError::NotFlat`]. Unsigned integers beyond the range of a signed one fail with
+/// [`SqlError::IntegerTooLarge`].
+pub fn to_sql_rows(ty: &Ty, value: &Flatbin, table: &str) -> Result<(String, Vec<Vec<SqlValue>>), SqlError> {
+    let (fields, rows) = match ty {
+        Ty::Array { inner } => {
+            let Ty::Struct { fields } = &**inner else {
+                return Err(SqlError::NotATable);
+            };
+            check_flat(fields)?;
+            let rows = value
+                .read_array()?
+                .try_iter()
+                .map(|row| {
+                    let values = row?.read_struct(fields)?;
+                    fields
+                        .iter()
+                        .zip(values)
+                        .map(|(field, value)| sql_value(field, value))
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?;
+            (fields, rows)
+        }
+        Ty::Columnar { fields } => {
+            check_flat(fields)?;
+            let (rows, columns) = value.read_columns(fields.len())?;
+            let mut columns: Vec<_> = columns.iter().map(|column| column.try_iter()).collect();
+            let rows = (0..rows)
+                .map(|_| {
+                    fields
+                        .iter()
+                        .zip(columns.iter_mut())
+                        .map(|(field, column)| sql_value(field, column.next().ok_or(flatbin::Error::UnexpectedEOF)??))
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?;
+            (fields, rows)
+        }
+        _ => return Err(SqlError::NotATable),
+    };
+
+    let columns = fields.iter().map(|field| quote_identifier(&field.name));
+    let placeholders = (1..=fields.len()).map(|i| format!("${}", i));
+    let statement = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        columns.collect::<Vec<_>>().join(", "),
+        placeholders.collect::<Vec<_>>().join(", ")
+    );
+    Ok((statement, rows))
+}
+
+/// Checks every field up front, so that an empty table of the wrong shape is rejected too.
+fn check_flat(fields: &[Field]) -> Result<(), SqlError> {
+    match fields.iter().find(|field| !is_flat(&field.ty)) {
+        Some(field) => Err(SqlError::NotFlat {
+            column: field.name.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn is_flat(ty: &Ty) -> bool {
+    match ty {
+        Ty::Bool | Ty::U64 | Ty::I64 | Ty::F64 | Ty::String | Ty::Bytes | Ty::ChunkedBytes { .. } => true,
+        Ty::Enum { variants, .. } => variants.iter().all(|variant| variant.fields.is_empty()),
+        _ => false,
+    }
+}
+
+fn sql_value(field: &Field, value: &Flatbin) -> Result<SqlValue, SqlError> {
+    Ok(match &field.ty {
+        Ty::Bool => SqlValue::Bool(value.read_bool()?),
+        Ty::U64 => {
+            let value = value.read_u64()?;
+            let int = i64::try_from(value).map_err(|_| SqlError::IntegerTooLarge {
+                column: field.name.clone(),
+                value,
+            })?;
+            SqlValue::Int(int)
+        }
+        Ty::I64 => SqlValue::Int(value.read_i64()?),
+        Ty::F64 => SqlValue::Float(value.read_f64()?),
+        Ty::String => SqlValue::Text(value.read_str()?.to_string()),
+        Ty::Bytes => SqlValue::Bytes(value.read_bytes()?.to_vec()),
+        Ty::ChunkedBytes { .. } => SqlValue::Bytes(value.read_chunked_bytes()?),
+        Ty::Enum { variants, .. } => SqlValue::Text(variants[value.read_variant(variants)?.0].name.to_string()),
+        _ => unreachable!("fields are checked to be flat"),
+    })
+}
+
+/// Quotes an identifier, doubling any quotes within it.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn to_sql_rows() {
+        let ty = array_def!(struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "balance": Ty::F64,
+            "say \"hi\"": Ty::Bool,
+        }));
+        let value = serde_json::json!([
+            { "name": "Alexander", "age": 27, "balance": 1.5, "say \"hi\"": true },
+            { "name": "Brian", "age": 31, "balance": -2.0, "say \"hi\"": false },
+        ]);
+        let bytes = crate::slow::deserialize_alloc(&ty, &value).unwrap();
+
+        let (statement, rows) = super::to_sql_rows(&ty, &bytes, "people").unwrap();
+        assert_eq!(
+            statement,
+            r#"INSERT INTO people ("name", "age", "balance", "say ""hi""") VALUES ($1, $2, $3, $4)"#
+        );
+        assert_eq!(
+            rows,
+            [
+                [
+                    SqlValue::Text("Alexander".into()),
+                    SqlValue::Int(27),
+                    SqlValue::Float(1.5),
+                    SqlValue::Bool(true)
+                ],
+                [
+                    SqlValue::Text("Brian".into()),
+                    SqlValue::Int(31),
+                    SqlValue::Float(-2.0),
+                    SqlValue::Bool(false)
+                ],
+            ]
+        );
+
+        // A columnar array gives the same rows
+        let Ty::Array { inner } = &ty else { unreachable!() };
+        let Ty::Struct { fields } = &**inner else {
+            unreachable!()
+        };
+        let columnar = Ty::Columnar { fields: fields.clone() };
+        let bytes = crate::slow::deserialize_alloc(&columnar, &value).unwrap();
+        assert_eq!(super::to_sql_rows(&columnar, &bytes, "people").unwrap().1, rows);
+
+        // Tables must be flat
+        let nested = array_def!(struct_def!({ "tags": array_def!(Ty::String) }));
+        let bytes = crate::fast::deserialize(&nested, "[]").unwrap();
+        assert!(matches!(
+            super::to_sql_rows(&nested, &bytes, "t"),
+            Err(SqlError::NotFlat { column }) if &*column == "tags"
+        ));
+        assert!(matches!(
+            super::to_sql_rows(&Ty::U64, &bytes, "t"),
+            Err(SqlError::NotATable)
+        ));
+
+        let ty = array_def!(struct_def!({ "id": Ty::U64 }));
+        let bytes = crate::fast::deserialize(&ty, &format!("[{{ \"id\": {} }}]", u64::MAX)).unwrap();
+        assert!(matches!(
+            super::to_sql_rows(&ty, &bytes, "t"),
+            Err(SqlError::IntegerTooLarge { value: u64::MAX, .. })
+        ));
+    }
+}
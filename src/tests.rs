@@ -1,8 +1,9 @@
 #![cfg(test)]
 
 use crate::array_def;
-use crate::flatbin::{Builder, Flatbin, FlatbinBuf};
-use crate::slow::{deserialize, deserialize_alloc, serialize};
+use crate::flatbin::{self, Builder, Flatbin, FlatbinBuf};
+use crate::options::{BytesEncoding, DeserializeOptions, SerializeOptions};
+use crate::slow::{deserialize, deserialize_alloc, deserialize_with, serialize};
 use crate::struct_def;
 use crate::ty::Ty;
 use crate::JsonValue;
@@ -53,6 +54,1436 @@ fn garbage_data() {
         "rustacean": Ty::Bool
     });
 
-    let result = serialize(&ty, Flatbin::from_bytes(&[5, 1, 99, 254, 0, 0, 11]));
-    assert!(result.is_err());
+    // The "hobbies" array claims 99 elements, but none follow
+    let garbage = Flatbin::from_bytes(&[5, 1, 99, 254, 0, 0, 11]);
+    let result = serialize(&ty, garbage);
+    assert!(matches!(
+        result,
+        Err(flatbin::Error::MissingElements { expected: 99, found: 0 })
+    ));
+    let result = crate::fast::serialize(serde_json::value::Serializer, &ty, garbage);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "corrupt document: expected 99 elements, but the data ends after 0"
+    );
+
+    // A struct with only two of its four fields
+    let mut buffer = FlatbinBuf::new();
+    let mut tuple = buffer.reset_builder().start_tuple();
+    tuple.write("Alice");
+    tuple.write(31u64);
+    tuple.end();
+    let result = serialize(&ty, &buffer);
+    assert!(matches!(
+        result,
+        Err(flatbin::Error::MissingElements { expected: 4, found: 2 })
+    ));
+    let result = crate::fast::serialize(serde_json::value::Serializer, &ty, &buffer);
+    assert!(result.unwrap_err().to_string().contains("expected 4 elements"));
+}
+
+#[test]
+fn depth_limit() {
+    use crate::slow::Error;
+
+    let ty = array_def!(array_def!(array_def!(Ty::U64)));
+    let value = serde_json::json!([[[1, 2], [3]], [[4]]]);
+
+    let mut buffer = FlatbinBuf::new();
+    let options = DeserializeOptions {
+        max_depth: 2,
+        ..Default::default()
+    };
+    let result = deserialize_with(&ty, &value, Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(Error::DepthExceeded)));
+
+    let mut buffer = FlatbinBuf::new();
+    let options = DeserializeOptions {
+        max_depth: 3,
+        ..Default::default()
+    };
+    deserialize_with(&ty, &value, Builder::new(&mut buffer), &options).unwrap();
+    assert_eq!(serialize(&ty, &buffer).unwrap(), value);
+}
+
+#[test]
+fn empty_arrays_in_struct() {
+    let ty = struct_def!({
+        "first": array_def!(Ty::U64),
+        "middle": array_def!(Ty::String),
+        "nested": array_def!(array_def!(Ty::U64)),
+        "last": array_def!(Ty::U64),
+    });
+
+    for value in [
+        serde_json::json!({ "first": [1], "middle": [], "nested": [[2, 3], []], "last": [] }),
+        serde_json::json!({ "first": [], "middle": [], "nested": [[], [4]], "last": [5, 6] }),
+        serde_json::json!({ "first": [], "middle": ["a"], "nested": [], "last": [] }),
+    ] {
+        let slow_bytes = deserialize_alloc(&ty, &value).unwrap();
+        let fast_bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+        assert_eq!(slow_bytes.as_bytes(), fast_bytes.as_bytes());
+        assert_eq!(serialize(&ty, &slow_bytes).unwrap(), value);
+    }
+}
+
+#[test]
+fn field_order() {
+    let ty = struct_def!({
+        "zebra": Ty::U64,
+        "apple": Ty::U64,
+        "mango": struct_def!({ "y": Ty::Bool, "x": Ty::Bool }),
+    });
+    let value = serde_json::json!({ "zebra": 1, "apple": 2, "mango": { "y": true, "x": false } });
+
+    let bytes = deserialize_alloc(&ty, &value).unwrap();
+    let value = serialize(&ty, &bytes).unwrap();
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"zebra":1,"apple":2,"mango":{"y":true,"x":false}}"#
+    );
+}
+
+#[test]
+fn fast_slow_agree() {
+    let corpus = [
+        (Ty::Bool, serde_json::json!(true)),
+        (Ty::U64, serde_json::json!(u64::MAX)),
+        (Ty::I64, serde_json::json!(i64::MIN)),
+        (Ty::F64, serde_json::json!(-1.25)),
+        (Ty::Bytes, serde_json::json!([0, 127, 128, 255])),
+        (Ty::String, serde_json::json!("Hello \"world\" \u{1F980}")),
+        (array_def!(Ty::Bytes), serde_json::json!([[], [1], [2, 3]])),
+        (array_def!(Ty::String), serde_json::json!(["", "a", ""])),
+        (struct_def!({}), serde_json::json!({})),
+        (
+            struct_def!({
+                "name": Ty::String,
+                "age": Ty::U64,
+                "hobbies": array_def!(Ty::String),
+                "languages": array_def!(struct_def!({
+                    "name": Ty::String,
+                    "liked": Ty::Bool,
+                    "experience": Ty::U64,
+                }))
+            }),
+            serde_json::json!({
+                "name": "Alexander",
+                "age": 27,
+                "hobbies": ["Music", "Programming", "Reading"],
+                "languages": [
+                    { "name": "Rust", "liked": true, "experience": 5 },
+                    { "name": "PHP", "liked": false, "experience": 2 }
+                ]
+            }),
+        ),
+    ];
+
+    for (ty, value) in corpus {
+        let slow_bytes = deserialize_alloc(&ty, &value).unwrap();
+        let fast_bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+        assert_eq!(slow_bytes.as_bytes(), fast_bytes.as_bytes(), "{}", value);
+
+        let slow_value = serialize(&ty, &slow_bytes).unwrap();
+        let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &fast_bytes).unwrap();
+        assert_eq!(slow_value, value);
+        assert_eq!(fast_value, value);
+    }
+
+    // Both paths reject the same invalid inputs
+    let invalid = [
+        (Ty::Bool, serde_json::json!("true")),
+        (Ty::Bool, serde_json::json!(null)),
+        (Ty::U64, serde_json::json!(-1)),
+        (Ty::U64, serde_json::json!(1.5)),
+        (Ty::I64, serde_json::json!(u64::MAX)),
+        (Ty::F64, serde_json::json!("1.5")),
+        (Ty::Bytes, serde_json::json!("abc")),
+        (Ty::Bytes, serde_json::json!([1, 256])),
+        (Ty::Bytes, serde_json::json!([-1])),
+        (Ty::String, serde_json::json!(5)),
+        (array_def!(Ty::String), serde_json::json!(["a", 1])),
+        (array_def!(Ty::U64), serde_json::json!({})),
+        (
+            struct_def!({ "name": Ty::String, "age": Ty::U64 }),
+            serde_json::json!({ "name": "Alexander" }),
+        ),
+        (
+            struct_def!({ "name": Ty::String, "age": Ty::U64 }),
+            serde_json::json!(["Alexander", 27]),
+        ),
+    ];
+
+    for (ty, value) in invalid {
+        let mut buffer = FlatbinBuf::new();
+        assert!(
+            deserialize(&ty, &value, Builder::new(&mut buffer)).is_err(),
+            "{}",
+            value
+        );
+        assert!(crate::fast::deserialize(&ty, &value.to_string()).is_err(), "{}", value);
+    }
+}
+
+#[test]
+fn max_array_len() {
+    use crate::slow::Error;
+
+    let ty = struct_def!({ "a": array_def!(Ty::U64), "b": array_def!(Ty::U64) });
+    let value = serde_json::json!({ "a": [1, 2], "b": [3, 4, 5] });
+    let options = DeserializeOptions {
+        max_array_len: Some(2),
+        ..Default::default()
+    };
+
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize_with(&ty, &value, Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(Error::ArrayTooLong { max: 2 })));
+
+    let mut buffer = FlatbinBuf::new();
+    let result = crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("array exceeds maximum length of 2"));
+
+    let value = serde_json::json!({ "a": [1, 2], "b": [] });
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &value, Builder::new(&mut buffer), &options).unwrap();
+}
+
+#[test]
+fn budget() {
+    use crate::options::{Budget, BudgetExceeded};
+    use crate::slow::{deserialize_budgeted, Error};
+
+    let ty = struct_def!({ "name": Ty::String, "tags": array_def!(Ty::U64) });
+    let value = serde_json::json!({ "name": "Alexander", "tags": [1, 2, 3] });
+    let options = DeserializeOptions::default();
+
+    // 1 struct + 1 string + 1 array + 3 elements = 6 nodes, and 9 bytes of string
+    let budget = Budget::new(6, 9);
+    let mut buffer = FlatbinBuf::new();
+    deserialize_budgeted(&ty, &value, Builder::new(&mut buffer), &options, &budget).unwrap();
+    assert_eq!((budget.remaining_nodes(), budget.remaining_bytes()), (0, 0));
+
+    let budget = Budget::new(5, 100);
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize_budgeted(&ty, &value, Builder::new(&mut buffer), &options, &budget);
+    assert!(matches!(result, Err(Error::BudgetExceeded(BudgetExceeded::Nodes))));
+
+    let budget = Budget::new(100, 8);
+    let mut buffer = FlatbinBuf::new();
+    let err = crate::fast::deserialize_into_budgeted(&ty, &value.to_string(), &mut buffer, &options, &budget);
+    assert!(err.unwrap_err().to_string().contains("byte budget"));
+}
+
+#[test]
+fn max_bytes_len() {
+    use crate::slow::Error;
+
+    let ty = array_def!(Ty::Bytes);
+    let value = serde_json::json!([[1, 2], [3, 4, 5]]);
+    let options = DeserializeOptions {
+        max_bytes_len: Some(2),
+        ..Default::default()
+    };
+
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize_with(&ty, &value, Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(Error::BytesTooLong { max: 2 })));
+
+    let mut buffer = FlatbinBuf::new();
+    let result = crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("byte array exceeds maximum length of 2"));
+}
+
+#[test]
+fn columnar() {
+    use crate::slow::Error;
+
+    let Ty::Struct { fields } = struct_def!({ "name": Ty::String, "age": Ty::U64 }) else {
+        unreachable!()
+    };
+    let ty = Ty::Columnar { fields };
+    let value = serde_json::json!([
+        { "name": "Alice", "age": 31 },
+        { "age": 27, "name": "Bob" },
+        { "name": "Carol", "age": 45 }
+    ]);
+
+    let slow_bytes = deserialize_alloc(&ty, &value).unwrap();
+    let fast_bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+    assert_eq!(slow_bytes.as_bytes(), fast_bytes.as_bytes());
+    ty.validate(&slow_bytes).unwrap();
+
+    // Each column can be read on its own
+    let (rows, columns) = slow_bytes.read_columns(2).unwrap();
+    assert_eq!(rows, 3);
+    let names: Vec<_> = columns[0].iter().map(|v| v.read_str().unwrap()).collect();
+    let ages: Vec<_> = columns[1].iter().map(|v| v.read_u64().unwrap()).collect();
+    assert_eq!(names, ["Alice", "Bob", "Carol"]);
+    assert_eq!(ages, [31, 27, 45]);
+
+    assert_eq!(serialize(&ty, &slow_bytes).unwrap(), value);
+    let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &fast_bytes).unwrap();
+    assert_eq!(fast_value, value);
+
+    // Every row must have every field
+    let value = serde_json::json!([{ "name": "Alice", "age": 31 }, { "name": "Bob" }]);
+    let result = deserialize_alloc(&ty, &value);
+    assert!(matches!(result, Err(Error::MissingField { .. })));
+    let result = crate::fast::deserialize(&ty, &value.to_string());
+    assert!(result.unwrap_err().to_string().contains("missing field \"age\""));
+
+    // An empty array still has its (empty) columns
+    let value = serde_json::json!([]);
+    let bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+    assert_eq!(bytes.as_bytes(), deserialize_alloc(&ty, &value).unwrap().as_bytes());
+    assert_eq!(serialize(&ty, &bytes).unwrap(), value);
+}
+
+#[test]
+fn bytes_encodings() {
+    use crate::slow::{serialize_with, Error};
+
+    let ty = array_def!(Ty::Bytes);
+    let bytes = deserialize_alloc(&ty, &serde_json::json!([[], [0, 1, 254, 255], [104, 105, 33]])).unwrap();
+
+    let cases = [
+        (
+            BytesEncoding::NumberArray,
+            serde_json::json!([[], [0, 1, 254, 255], [104, 105, 33]]),
+        ),
+        (BytesEncoding::Base64, serde_json::json!(["", "AAH+/w==", "aGkh"])),
+        (BytesEncoding::Hex, serde_json::json!(["", "0001feff", "686921"])),
+        (
+            BytesEncoding::PrefixedHex,
+            serde_json::json!(["0x", "0x0001feff", "0x686921"]),
+        ),
+    ];
+    for (bytes_encoding, expected) in cases {
+        let ser_options = SerializeOptions {
+            bytes_encoding,
+            ..Default::default()
+        };
+        let de_options = DeserializeOptions {
+            bytes_encoding,
+            ..Default::default()
+        };
+
+        let value = serialize_with(&ty, &bytes, &ser_options).unwrap();
+        assert_eq!(value, expected);
+        let fast_value = crate::fast::serialize_with(serde_json::value::Serializer, &ty, &bytes, &ser_options).unwrap();
+        assert_eq!(fast_value, expected);
+
+        let mut buffer = FlatbinBuf::new();
+        deserialize_with(&ty, &value, Builder::new(&mut buffer), &de_options).unwrap();
+        assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+        let mut buffer = FlatbinBuf::new();
+        crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &de_options).unwrap();
+        assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+    }
+
+    // Byte arrays given as numbers aren't read from strings
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize(&Ty::Bytes, &"abc".into(), Builder::new(&mut buffer));
+    assert!(matches!(result, Err(Error::UnexpectedType { .. })));
+    let result = crate::fast::deserialize(&Ty::Bytes, "\"abc\"");
+    assert!(result.unwrap_err().to_string().contains("expected a byte array"));
+
+    let options = DeserializeOptions {
+        bytes_encoding: BytesEncoding::Hex,
+        ..Default::default()
+    };
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize_with(&Ty::Bytes, &"+f".into(), Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(Error::InvalidBytes { .. })));
+    let result = crate::fast::deserialize_into_with(&Ty::Bytes, "\"abc\"", &mut buffer, &options);
+    assert!(result.unwrap_err().to_string().contains("expected a hex string"));
+
+    // Either hex encoding accepts digits of either case, with or without a prefix
+    for bytes_encoding in [BytesEncoding::Hex, BytesEncoding::PrefixedHex] {
+        let options = DeserializeOptions {
+            bytes_encoding,
+            ..Default::default()
+        };
+        for hex in ["00fEFf", "0x00FeFF", "0X00feff"] {
+            let mut buffer = FlatbinBuf::new();
+            deserialize_with(&Ty::Bytes, &hex.into(), Builder::new(&mut buffer), &options).unwrap();
+            assert_eq!(buffer.read_bytes().unwrap(), [0x00, 0xfe, 0xff]);
+        }
+        for hex in ["0x0", "0xx0", "00 1"] {
+            let mut buffer = FlatbinBuf::new();
+            let result = crate::fast::deserialize_into_with(&Ty::Bytes, &format!("{:?}", hex), &mut buffer, &options);
+            assert!(result.is_err(), "{}", hex);
+        }
+    }
+}
+
+#[test]
+fn json_names() {
+    let ty = struct_def!({
+        "first_name" as "firstName": Ty::String,
+        "age": Ty::U64,
+    });
+    let value = serde_json::json!({ "firstName": "Alice", "age": 31 });
+
+    let slow_bytes = deserialize_alloc(&ty, &value).unwrap();
+    let fast_bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+    assert_eq!(slow_bytes.as_bytes(), fast_bytes.as_bytes());
+
+    // The layout is positional, so the rename doesn't change the encoding
+    let plain = struct_def!({ "first_name": Ty::String, "age": Ty::U64 });
+    let plain_bytes = deserialize_alloc(&plain, &serde_json::json!({ "first_name": "Alice", "age": 31 })).unwrap();
+    assert_eq!(slow_bytes.as_bytes(), plain_bytes.as_bytes());
+
+    assert_eq!(serialize(&ty, &slow_bytes).unwrap(), value);
+    let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &fast_bytes).unwrap();
+    assert_eq!(fast_value, value);
+
+    // The internal name is no longer accepted as a key
+    let value = serde_json::json!({ "first_name": "Alice", "age": 31 });
+    assert!(deserialize_alloc(&ty, &value).is_err());
+    assert!(crate::fast::deserialize(&ty, &value.to_string()).is_err());
+
+    let schema: Ty =
+        r#"{ "struct": { "fields": [{ "name": "first_name", "json_name": "firstName", "ty": "string" }] } }"#
+            .parse()
+            .unwrap();
+    assert_eq!(schema, struct_def!({ "first_name" as "firstName": Ty::String }));
+}
+
+#[test]
+fn aliases() {
+    use crate::slow::Error;
+    use crate::ty::Field;
+
+    let ty = Ty::Struct {
+        fields: vec![
+            Field {
+                aliases: vec!["fullName".into(), "name".into()].into(),
+                ..Field::new("displayName", Ty::String)
+            },
+            Field::new("age", Ty::U64),
+        ]
+        .into(),
+    };
+    let canonical = serde_json::json!({ "displayName": "Alice", "age": 31 });
+    let expected = deserialize_alloc(&ty, &canonical).unwrap();
+
+    for value in [
+        serde_json::json!({ "fullName": "Alice", "age": 31 }),
+        serde_json::json!({ "age": 31, "name": "Alice" }),
+    ] {
+        let slow_bytes = deserialize_alloc(&ty, &value).unwrap();
+        let fast_bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+        assert_eq!(slow_bytes.as_bytes(), expected.as_bytes());
+        assert_eq!(fast_bytes.as_bytes(), expected.as_bytes());
+
+        // Serialization always uses the canonical key
+        assert_eq!(serialize(&ty, &slow_bytes).unwrap(), canonical);
+    }
+
+    // An alias and the canonical key are the same field
+    let value = serde_json::json!({ "displayName": "Alice", "name": "Bob", "age": 31 });
+    assert!(matches!(
+        deserialize_alloc(&ty, &value),
+        Err(Error::DuplicateField { .. })
+    ));
+    let result = crate::fast::deserialize(&ty, &value.to_string());
+    assert!(result.unwrap_err().to_string().contains("duplicate field \"name\""));
+}
+
+#[test]
+fn case_insensitive() {
+    use crate::slow::Error;
+
+    let ty = struct_def!({ "name": Ty::String, "age": Ty::U64 });
+    let expected = deserialize_alloc(&ty, &serde_json::json!({ "name": "Alice", "age": 31 })).unwrap();
+    let value = serde_json::json!({ "NAME": "Alice", "Age": 31 });
+    let options = DeserializeOptions {
+        case_insensitive: true,
+        ..Default::default()
+    };
+
+    // Off by default
+    assert!(deserialize_alloc(&ty, &value).is_err());
+    assert!(crate::fast::deserialize(&ty, &value.to_string()).is_err());
+
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &value, Builder::new(&mut buffer), &options).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+    let mut buffer = FlatbinBuf::new();
+    crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+    // Keys differing only in case are the same field
+    let value = serde_json::json!({ "name": "Alice", "Name": "Bob", "age": 31 });
+    let mut buffer = FlatbinBuf::new();
+    let result = deserialize_with(&ty, &value, Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(Error::DuplicateField { .. })));
+    let mut buffer = FlatbinBuf::new();
+    let result = crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options);
+    assert!(result.unwrap_err().to_string().contains("duplicate field"));
+}
+
+#[test]
+fn chunked_bytes() {
+    use crate::flatbin::TypedFlatbin;
+    use std::num::NonZeroUsize;
+
+    let ty: Ty = r#"{ "chunkedbytes": { "chunk_size": 3 } }"#.parse().unwrap();
+    assert_eq!(
+        ty,
+        Ty::ChunkedBytes {
+            chunk_size: NonZeroUsize::new(3).unwrap()
+        }
+    );
+
+    for (value, chunks) in [
+        (vec![], vec![]),
+        (vec![1, 2], vec![vec![1, 2]]),
+        (vec![1, 2, 3], vec![vec![1, 2, 3]]),
+        (
+            vec![1, 2, 3, 4, 5, 6, 200],
+            vec![vec![1, 2, 3], vec![4, 5, 6], vec![200]],
+        ),
+    ] {
+        let json = JsonValue::from(value.clone());
+        let bytes = deserialize_alloc(&ty, &json).unwrap();
+        assert_eq!(
+            crate::fast::deserialize(&ty, &json.to_string()).unwrap().as_bytes(),
+            bytes.as_bytes()
+        );
+        ty.validate(&bytes).unwrap();
+        assert_eq!(TypedFlatbin::new(&ty, &bytes).as_chunks().unwrap(), chunks);
+        assert_eq!(bytes.read_chunked_bytes().unwrap(), value);
+
+        assert_eq!(serialize(&ty, &bytes).unwrap(), json);
+        let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &bytes).unwrap();
+        assert_eq!(fast_value, json);
+    }
+
+    // The JSON form follows the bytes encoding
+    let options = SerializeOptions {
+        bytes_encoding: BytesEncoding::Hex,
+        ..Default::default()
+    };
+    let bytes = deserialize_alloc(&ty, &serde_json::json!([1, 2, 3, 4])).unwrap();
+    let value = crate::slow::serialize_with(&ty, &bytes, &options).unwrap();
+    assert_eq!(value, serde_json::json!("01020304"));
+
+    // Every chunk but the last must be full
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = Builder::new(&mut buffer).start_vector();
+    vector.write(&[1u8, 2][..]);
+    vector.write(&[3u8][..]);
+    vector.end();
+    assert!(ty.validate(&buffer).is_err());
+}
+
+#[test]
+fn strict_arrays() {
+    let ty = array_def!(struct_def!({ "name": Ty::String, "scores": array_def!(Ty::F64) }));
+    let value = serde_json::json!([
+        { "name": "Alice", "scores": [1.5, 2.0] },
+        { "name": "Bob", "scores": [] },
+    ]);
+    let expected = deserialize_alloc(&ty, &value).unwrap();
+    let options = DeserializeOptions {
+        strict_arrays: true,
+        ..Default::default()
+    };
+
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &value, Builder::new(&mut buffer), &options).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+    let mut buffer = FlatbinBuf::new();
+    crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+    // An element which fails to deserialize is reported with its index
+    let value = serde_json::json!([
+        { "name": "Alice", "scores": [1.5, 2.0] },
+        { "name": "Bob", "scores": [3.0, "high"] },
+    ]);
+    let result = deserialize_with(&ty, &value, Builder::new(&mut buffer), &options);
+    let Err(crate::slow::Error::InvalidElement { index: 1, source }) = result else {
+        panic!("expected an invalid element, got {:?}", result);
+    };
+    assert!(matches!(*source, crate::slow::Error::InvalidElement { index: 1, .. }));
+    let result = crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options);
+    let message = result.unwrap_err().to_string();
+    assert!(message.starts_with("array element 1 "), "{}", message);
+    assert!(message.contains("array element 1 does not match the element type: invalid type"));
+
+    // Without the option, the error is reported as it is
+    let result = deserialize_alloc(&ty, &value);
+    assert!(matches!(result, Err(crate::slow::Error::UnexpectedType { .. })));
+}
+
+#[test]
+fn stringified_numbers() {
+    let ty = struct_def!({ "id": Ty::U64, "delta": Ty::I64, "ratio": Ty::F64 });
+    let numeric = serde_json::json!({ "id": 12345678901234567890u64, "delta": -42, "ratio": 0.25 });
+    let stringified = serde_json::json!({ "id": "12345678901234567890", "delta": "-42", "ratio": "0.25" });
+    let expected = deserialize_alloc(&ty, &numeric).unwrap();
+    let options = DeserializeOptions {
+        accept_stringified_numbers: true,
+        ..Default::default()
+    };
+
+    // Off by default
+    assert!(deserialize_alloc(&ty, &stringified).is_err());
+    assert!(crate::fast::deserialize(&ty, &stringified.to_string()).is_err());
+
+    for value in [&numeric, &stringified] {
+        let mut buffer = FlatbinBuf::new();
+        deserialize_with(&ty, value, Builder::new(&mut buffer), &options).unwrap();
+        assert_eq!(buffer.as_bytes(), expected.as_bytes());
+        let mut buffer = FlatbinBuf::new();
+        crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options).unwrap();
+        assert_eq!(buffer.as_bytes(), expected.as_bytes());
+    }
+
+    for (ty, value) in [
+        (Ty::U64, "-1"),
+        (Ty::U64, "1.5"),
+        (Ty::I64, "ten"),
+        (Ty::F64, "NaN"),
+        (Ty::F64, ""),
+    ] {
+        let value = JsonValue::from(value);
+        let mut buffer = FlatbinBuf::new();
+        assert!(deserialize_with(&ty, &value, Builder::new(&mut buffer), &options).is_err());
+        let result = crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options);
+        assert!(result.is_err(), "{}", value);
+    }
+}
+
+#[test]
+fn large_ints_as_strings() {
+    use crate::options::MAX_SAFE_INTEGER;
+
+    let ty = array_def!(struct_def!({ "u": Ty::U64, "i": Ty::I64 }));
+    let value = serde_json::json!([
+        { "u": MAX_SAFE_INTEGER, "i": -(MAX_SAFE_INTEGER as i64) },
+        { "u": MAX_SAFE_INTEGER + 1, "i": i64::MIN },
+    ]);
+    let bytes = deserialize_alloc(&ty, &value).unwrap();
+    let options = SerializeOptions {
+        large_ints_as_strings: true,
+        ..Default::default()
+    };
+    let expected = serde_json::json!([
+        { "u": MAX_SAFE_INTEGER, "i": -(MAX_SAFE_INTEGER as i64) },
+        { "u": "9007199254740992", "i": "-9223372036854775808" },
+    ]);
+
+    // Off by default
+    assert_eq!(serialize(&ty, &bytes).unwrap(), value);
+
+    assert_eq!(crate::slow::serialize_with(&ty, &bytes, &options).unwrap(), expected);
+    let fast_value = crate::fast::serialize_with(serde_json::value::Serializer, &ty, &bytes, &options).unwrap();
+    assert_eq!(fast_value, expected);
+
+    // The strings read back with stringified numbers accepted
+    let de_options = DeserializeOptions {
+        accept_stringified_numbers: true,
+        ..Default::default()
+    };
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &expected, Builder::new(&mut buffer), &de_options).unwrap();
+    assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+}
+
+#[test]
+fn rle_array() {
+    use crate::flatbin::{RleBuilder, TypedFlatbin};
+
+    let ty: Ty = r#"{ "rlearray": { "inner": "string" } }"#.parse().unwrap();
+    let array_ty = array_def!(Ty::String);
+
+    // Long runs of a few distinct values, as in a column of status flags
+    let mut values = vec![];
+    for (value, count) in [("pending", 400), ("active", 1000), ("pending", 1), ("done", 600)] {
+        values.extend(std::iter::repeat_n(value, count));
+    }
+    let json = JsonValue::from(values);
+    let bytes = deserialize_alloc(&ty, &json).unwrap();
+    assert_eq!(
+        crate::fast::deserialize(&ty, &json.to_string()).unwrap().as_bytes(),
+        bytes.as_bytes()
+    );
+    ty.validate(&bytes).unwrap();
+    let runs: Vec<_> = bytes
+        .read_runs()
+        .unwrap()
+        .into_iter()
+        .map(|(count, value)| (count, value.read_str().unwrap()))
+        .collect();
+    assert_eq!(
+        runs,
+        [(400, "pending"), (1000, "active"), (1, "pending"), (600, "done")]
+    );
+    assert_eq!(TypedFlatbin::new(&ty, &bytes).len().unwrap(), 2001);
+
+    assert_eq!(serialize(&ty, &bytes).unwrap(), json);
+    let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &bytes).unwrap();
+    assert_eq!(fast_value, json);
+
+    let array_bytes = deserialize_alloc(&array_ty, &json).unwrap();
+    assert!(bytes.as_bytes().len() * 100 < array_bytes.as_bytes().len());
+
+    // Empty arrays are encoded exactly like an empty `Ty::Array`
+    let empty = deserialize_alloc(&ty, &JsonValue::Array(vec![])).unwrap();
+    assert_eq!(
+        empty.as_bytes(),
+        deserialize_alloc(&array_ty, &JsonValue::Array(vec![]))
+            .unwrap()
+            .as_bytes()
+    );
+    assert_eq!(serialize(&ty, &empty).unwrap(), JsonValue::Array(vec![]));
+
+    // A run of zero elements is rejected
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = buffer.reset_builder().start_vector();
+    let mut run = vector.start_tuple();
+    run.write(0u64);
+    run.write("pending");
+    run.end();
+    vector.end();
+    assert!(ty.validate(&buffer).is_err());
+
+    let mut buffer = FlatbinBuf::new();
+    let mut runs = RleBuilder::new(buffer.reset_builder());
+    for value in [1u64, 1, 2, 2, 2] {
+        runs.write(value);
+    }
+    assert_eq!(runs.count(), 5);
+    runs.end();
+    let ty = Ty::RleArray { inner: Ty::U64.into() };
+    assert_eq!(serialize(&ty, &buffer).unwrap(), serde_json::json!([1, 1, 2, 2, 2]));
+
+    // A run of 2^40 elements is rejected rather than expanded
+    let bomb = Flatbin::from_bytes(&[1, 134, 0, 0, 0, 0, 0, 1, 7]);
+    assert!(matches!(bomb.read_runs(), Err(flatbin::Error::RleTooLong)));
+    assert!(matches!(ty.validate(bomb), Err(flatbin::Error::RleTooLong)));
+    assert!(matches!(serialize(&ty, bomb), Err(flatbin::Error::RleTooLong)));
+    assert!(crate::fast::serialize(serde_json::value::Serializer, &ty, bomb).is_err());
+    assert!(matches!(
+        TypedFlatbin::new(&ty, bomb).len(),
+        Err(flatbin::Error::RleTooLong)
+    ));
+    // As are runs which are each short enough, but add up to too many elements
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = buffer.reset_builder().start_vector();
+    for _ in 0..2 {
+        let mut run = vector.start_tuple();
+        run.write(flatbin::MAX_RLE_LEN as u64);
+        run.write(7u64);
+        run.end();
+    }
+    vector.end();
+    assert!(matches!(ty.validate(&buffer), Err(flatbin::Error::RleTooLong)));
+    assert!(matches!(
+        TypedFlatbin::new(&ty, &buffer).len(),
+        Err(flatbin::Error::RleTooLong)
+    ));
+
+    // Deserializing longer arrays fails too, so that every document written can be read
+    let options = DeserializeOptions {
+        max_array_len: Some(2),
+        ..Default::default()
+    };
+    let result = deserialize_with(&ty, &serde_json::json!([1, 1, 1]), Builder::new(&mut buffer), &options);
+    assert!(matches!(result, Err(crate::slow::Error::ArrayTooLong { max: 2 })));
+    assert_eq!(options.max_rle_len(), 2);
+    assert_eq!(DeserializeOptions::default().max_rle_len(), flatbin::MAX_RLE_LEN);
+}
+
+#[test]
+fn float_vec() {
+    use crate::fast::from_flatbin;
+    use crate::flatbin::TypedFlatbin;
+
+    let ty: Ty = r#""floatvec""#.parse().unwrap();
+    assert_eq!(ty, Ty::FloatVec);
+
+    let embedding: Vec<f64> = (0..64).map(|i| i as f64 / 3.0 - 10.0).collect();
+    let json = JsonValue::from(embedding.clone());
+    let bytes = deserialize_alloc(&ty, &json).unwrap();
+    assert_eq!(
+        crate::fast::deserialize(&ty, &json.to_string()).unwrap().as_bytes(),
+        bytes.as_bytes()
+    );
+    ty.validate(&bytes).unwrap();
+    // A one-byte count, then the packed floats
+    assert_eq!(bytes.as_bytes().len(), 1 + 8 * 64);
+    assert_eq!(bytes.as_bytes()[1..9], embedding[0].to_le_bytes());
+    assert_eq!(bytes.read_float_vec().unwrap().collect::<Vec<_>>(), embedding);
+    assert_eq!(TypedFlatbin::new(&ty, &bytes).len().unwrap(), 64);
+    assert_eq!(from_flatbin::<Vec<f64>>(&ty, &bytes).unwrap(), embedding);
+
+    assert_eq!(serialize(&ty, &bytes).unwrap(), json);
+    let fast_value = crate::fast::serialize(serde_json::value::Serializer, &ty, &bytes).unwrap();
+    assert_eq!(fast_value, json);
+
+    // Each float in a plain array carries a header
+    let array_bytes = deserialize_alloc(&array_def!(Ty::F64), &json).unwrap();
+    assert_eq!(array_bytes.as_bytes().len(), 1 + 9 * 63 + 8);
+
+    // Empty arrays have an empty body, and integers are accepted as floats
+    let empty = deserialize_alloc(&ty, &serde_json::json!([])).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(serialize(&ty, &empty).unwrap(), serde_json::json!([]));
+    let ints = deserialize_alloc(&ty, &serde_json::json!([1, -2])).unwrap();
+    assert_eq!(serialize(&ty, &ints).unwrap(), serde_json::json!([1.0, -2.0]));
+    assert!(deserialize_alloc(&ty, &serde_json::json!([1, "2"])).is_err());
+    assert!(crate::fast::deserialize(&ty, r#"[1, "2"]"#).is_err());
+
+    // The body must hold exactly as many floats as its count
+    assert!(ty.validate(Flatbin::from_bytes(&bytes.as_bytes()[..100])).is_err());
+    assert!(ty.validate(Flatbin::from_bytes(&[1, 0, 0, 0])).is_err());
+}
+
+#[test]
+fn struct_as_array() {
+    use crate::slow::serialize_with;
+
+    let ty = struct_def!({
+        "name": Ty::String,
+        "age": Ty::U64,
+        "languages": array_def!(struct_def!({ "name": Ty::String, "experience": Ty::U64 })),
+        "scores": Ty::Columnar {
+            fields: [crate::ty::Field::new("round", Ty::U64), crate::ty::Field::new("score", Ty::I64)].into(),
+        },
+    });
+    let object = serde_json::json!({
+        "name": "Alexander",
+        "age": 27,
+        "languages": [{ "name": "Rust", "experience": 5 }],
+        "scores": [{ "round": 1, "score": -3 }, { "round": 2, "score": 4 }],
+    });
+    let array = serde_json::json!(["Alexander", 27, [["Rust", 5]], [[1, -3], [2, 4]]]);
+    let bytes = deserialize_alloc(&ty, &object).unwrap();
+
+    let de_options = DeserializeOptions {
+        struct_as_array: true,
+        ..Default::default()
+    };
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &array, Builder::new(&mut buffer), &de_options).unwrap();
+    assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+    buffer.clear();
+    crate::fast::deserialize_into_with(&ty, &array.to_string(), &mut buffer, &de_options).unwrap();
+    assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+
+    let ser_options = SerializeOptions {
+        struct_as_array: true,
+        ..Default::default()
+    };
+    assert_eq!(serialize_with(&ty, &bytes, &ser_options).unwrap(), array);
+    let fast_value = crate::fast::serialize_with(serde_json::value::Serializer, &ty, &bytes, &ser_options).unwrap();
+    assert_eq!(fast_value, array);
+
+    // Each array must hold exactly one value per field
+    for wrong in [
+        serde_json::json!(["Alexander", 27, []]),
+        serde_json::json!(["Alexander", 27, [], [], null]),
+        serde_json::json!(["Alexander", 27, [["Rust"]], []]),
+        object,
+    ] {
+        let mut buffer = FlatbinBuf::new();
+        assert!(deserialize_with(&ty, &wrong, Builder::new(&mut buffer), &de_options).is_err());
+        assert!(crate::fast::deserialize_into_with(&ty, &wrong.to_string(), &mut buffer, &de_options).is_err());
+    }
+    let mut buffer = FlatbinBuf::new();
+    let err = crate::fast::deserialize_into_with(&ty, r#"["Alexander", 27]"#, &mut buffer, &de_options).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("invalid length 2, expected an array of 4 field values"));
+}
+
+#[test]
+fn enums() {
+    use crate::fast::from_flatbin;
+    use crate::ty::{Field, Variant};
+
+    let variants: Box<[Variant]> = [
+        Variant::unit("circle"),
+        Variant::new("rect", [Field::new("w", Ty::U64), Field::new("h", Ty::U64)]),
+    ]
+    .into();
+    let external = array_def!(Ty::Enum {
+        variants: variants.clone(),
+        tag: None,
+    });
+    let internal = array_def!(Ty::Enum {
+        variants,
+        tag: Some("kind".into()),
+    });
+    let cases = [
+        (&external, serde_json::json!(["circle", { "rect": { "w": 3, "h": 4 } }])),
+        (
+            &internal,
+            serde_json::json!([{ "kind": "circle" }, { "kind": "rect", "w": 3, "h": 4 }]),
+        ),
+    ];
+
+    for (ty, value) in cases.clone() {
+        let bytes = deserialize_alloc(ty, &value).unwrap();
+        assert_eq!(
+            crate::fast::deserialize(ty, &value.to_string()).unwrap().as_bytes(),
+            bytes.as_bytes()
+        );
+        assert_eq!(serialize(ty, &bytes).unwrap(), value);
+        assert_eq!(
+            crate::fast::serialize(serde_json::value::Serializer, ty, &bytes).unwrap(),
+            value
+        );
+        assert_eq!(from_flatbin::<JsonValue>(ty, &bytes).unwrap(), value);
+        ty.validate(&bytes).unwrap();
+    }
+
+    // The tag of an internally tagged enum may come after the fields
+    let value = serde_json::json!([{ "kind": "circle" }, { "w": 3, "h": 4, "kind": "rect" }]);
+    let bytes = crate::fast::deserialize(&internal, &value.to_string()).unwrap();
+    assert_eq!(
+        bytes.as_bytes(),
+        deserialize_alloc(&internal, &value).unwrap().as_bytes()
+    );
+
+    // Both forms encode the same bytes, and so the same values compare equal
+    assert_eq!(
+        deserialize_alloc(&external, &cases[0].1).unwrap().as_bytes(),
+        deserialize_alloc(&internal, &cases[1].1).unwrap().as_bytes(),
+    );
+
+    for (ty, wrong, message) in [
+        (&external, serde_json::json!(["square"]), "unknown variant \"square\""),
+        (&external, serde_json::json!(["rect"]), "missing field \"w\""),
+        (
+            &internal,
+            serde_json::json!([{ "w": 3, "h": 4 }]),
+            "missing field \"kind\"",
+        ),
+    ] {
+        assert!(deserialize_alloc(ty, &wrong).is_err());
+        let err = crate::fast::deserialize(ty, &wrong.to_string()).unwrap_err();
+        assert!(err.to_string().contains(message), "{}", err);
+    }
+
+    // A variant index beyond the last variant is rejected
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = Builder::new(&mut buffer).start_vector();
+    let mut tuple = vector.as_builder().start_tuple();
+    tuple.write(2u64);
+    tuple.as_builder().start_tuple().end();
+    tuple.end();
+    vector.end();
+    assert!(matches!(
+        external.validate(&buffer),
+        Err(flatbin::Error::InvalidVariant(2))
+    ));
+
+    let parsed: Ty = serde_json::from_value(serde_json::json!({ "enum": {
+        "variants": [
+            { "name": "circle" },
+            { "name": "rect", "fields": [{ "name": "w", "ty": "u64" }, { "name": "h", "ty": "u64" }] },
+        ],
+        "tag": "kind",
+    }}))
+    .unwrap();
+    let Ty::Array { inner } = internal else { unreachable!() };
+    assert_eq!(parsed, *inner);
+}
+
+#[test]
+fn bools_as_ints() {
+    use crate::slow::serialize_with;
+
+    let ty = struct_def!({ "flag": Ty::Bool, "mask": array_def!(Ty::Bool) });
+    let bools = serde_json::json!({ "flag": true, "mask": [false, true, true, false] });
+    let ints = serde_json::json!({ "flag": 1, "mask": [0, 1, 1, 0] });
+    let bytes = deserialize_alloc(&ty, &bools).unwrap();
+
+    let ser_options = SerializeOptions {
+        bools_as_ints: true,
+        ..Default::default()
+    };
+    assert_eq!(serialize_with(&ty, &bytes, &ser_options).unwrap(), ints);
+    let fast_value = crate::fast::serialize_with(serde_json::value::Serializer, &ty, &bytes, &ser_options).unwrap();
+    assert_eq!(fast_value, ints);
+
+    // Either form is accepted, even mixed within a document
+    let de_options = DeserializeOptions {
+        bools_as_ints: true,
+        ..Default::default()
+    };
+    let mixed = serde_json::json!({ "flag": true, "mask": [0, true, 1, false] });
+    for value in [&bools, &ints, &mixed] {
+        let mut buffer = FlatbinBuf::new();
+        deserialize_with(&ty, value, Builder::new(&mut buffer), &de_options).unwrap();
+        assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+        buffer.clear();
+        crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &de_options).unwrap();
+        assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+    }
+
+    // Integers are rejected by default, and other integers always
+    let two = serde_json::json!({ "flag": 2, "mask": [] });
+    for (value, options) in [(&ints, DeserializeOptions::default()), (&two, de_options)] {
+        let mut buffer = FlatbinBuf::new();
+        assert!(deserialize_with(&ty, value, Builder::new(&mut buffer), &options).is_err());
+        assert!(crate::fast::deserialize_into_with(&ty, &value.to_string(), &mut buffer, &options).is_err());
+    }
+}
+
+#[test]
+fn lenient_bools() {
+    let ty = array_def!(Ty::Bool);
+    let strings = serde_json::json!(["true", "false", "1", "0", true]);
+    let expected = deserialize_alloc(&ty, &serde_json::json!([true, false, true, false, true])).unwrap();
+
+    // Strings are rejected by default
+    let mut buffer = FlatbinBuf::new();
+    let strict = DeserializeOptions::default();
+    assert!(matches!(
+        deserialize_with(&ty, &strings, Builder::new(&mut buffer), &strict),
+        Err(crate::slow::Error::UnexpectedType { .. })
+    ));
+    let err = crate::fast::deserialize_into_with(&ty, &strings.to_string(), &mut buffer, &strict).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("invalid type: string \"true\", expected a boolean"));
+
+    let lenient = DeserializeOptions {
+        lenient_bools: true,
+        ..Default::default()
+    };
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &strings, Builder::new(&mut buffer), &lenient).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+    buffer.clear();
+    crate::fast::deserialize_into_with(&ty, &strings.to_string(), &mut buffer, &lenient).unwrap();
+    assert_eq!(buffer.as_bytes(), expected.as_bytes());
+
+    // Only those four strings are accepted
+    for wrong in [r#"["yes"]"#, r#"["TRUE"]"#, r#"[""]"#, "[1]"] {
+        let value: JsonValue = serde_json::from_str(wrong).unwrap();
+        let mut buffer = FlatbinBuf::new();
+        assert!(deserialize_with(&ty, &value, Builder::new(&mut buffer), &lenient).is_err());
+        assert!(crate::fast::deserialize_into_with(&ty, wrong, &mut buffer, &lenient).is_err());
+    }
+}
+
+#[test]
+fn unwrap_single_fields() {
+    use crate::slow::{collect_errors, serialize_with};
+    use crate::ty::Field;
+
+    let ty = struct_def!({
+        "id": struct_def!({ "value": Ty::U64 }),
+        "tags": array_def!(struct_def!({ "name": Ty::String })),
+        "nested": struct_def!({ "inner": struct_def!({ "flag": Ty::Bool }) }),
+        "pair": struct_def!({ "x": Ty::U64, "y": Ty::U64 }),
+        "maybe": Ty::Struct { fields: [Field::new_optional("count", Ty::U64)].into() },
+    });
+    let wrapped = serde_json::json!({
+        "id": { "value": 7 },
+        "tags": [{ "name": "a" }, { "name": "b" }],
+        "nested": { "inner": { "flag": true } },
+        "pair": { "x": 1, "y": 2 },
+        "maybe": { "count": 3 },
+    });
+    // Structs with more than one field, or whose field is optional, are kept
+    let unwrapped = serde_json::json!({
+        "id": 7,
+        "tags": ["a", "b"],
+        "nested": true,
+        "pair": { "x": 1, "y": 2 },
+        "maybe": { "count": 3 },
+    });
+    let bytes = deserialize_alloc(&ty, &wrapped).unwrap();
+
+    let ser_options = SerializeOptions {
+        unwrap_single_fields: true,
+        ..Default::default()
+    };
+    assert_eq!(serialize_with(&ty, &bytes, &ser_options).unwrap(), unwrapped);
+    let fast_value = crate::fast::serialize_with(serde_json::value::Serializer, &ty, &bytes, &ser_options).unwrap();
+    assert_eq!(fast_value, unwrapped);
+    assert_eq!(serialize(&ty.unwrap_single_field(), &bytes).unwrap(), unwrapped);
+
+    // Reading the unwrapped document back gives the original bytes, which the wrapped document no longer matches
+    let de_options = DeserializeOptions {
+        unwrap_single_fields: true,
+        ..Default::default()
+    };
+    let mut buffer = FlatbinBuf::new();
+    deserialize_with(&ty, &unwrapped, Builder::new(&mut buffer), &de_options).unwrap();
+    assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+    buffer.clear();
+    crate::fast::deserialize_into_with(&ty, &unwrapped.to_string(), &mut buffer, &de_options).unwrap();
+    assert_eq!(buffer.as_bytes(), bytes.as_bytes());
+    assert!(collect_errors(&ty, &unwrapped, &de_options).is_empty());
+
+    let mut buffer = FlatbinBuf::new();
+    assert!(deserialize_with(&ty, &wrapped, Builder::new(&mut buffer), &de_options).is_err());
+    assert!(crate::fast::deserialize_into_with(&ty, &wrapped.to_string(), &mut buffer, &de_options).is_err());
+    assert!(!collect_errors(&ty, &wrapped, &de_options).is_empty());
+}
+
+#[test]
+fn maps() {
+    use crate::fast::from_flatbin;
+    use crate::ty::MapLayout;
+
+    let map = |layout| Ty::Map {
+        value: Ty::U64.into(),
+        layout,
+    };
+    let (insertion, ordered) = (map(MapLayout::Insertion), map(MapLayout::Ordered));
+    let value = serde_json::json!({ "pear": 3, "apple": 1, "fig": 2, "banana": 0 });
+    let sorted = serde_json::json!({ "apple": 1, "banana": 0, "fig": 2, "pear": 3 });
+
+    for (ty, expected) in [(&insertion, &value), (&ordered, &sorted)] {
+        let bytes = deserialize_alloc(ty, &value).unwrap();
+        assert_eq!(
+            crate::fast::deserialize(ty, &value.to_string()).unwrap().as_bytes(),
+            bytes.as_bytes()
+        );
+        ty.validate(&bytes).unwrap();
+
+        // Object equality ignores order, so compare the keys as serialized
+        let keys = |value: JsonValue| value.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(serialize(ty, &bytes).unwrap()), keys(expected.clone()));
+        let fast_value = crate::fast::serialize(serde_json::value::Serializer, ty, &bytes).unwrap();
+        assert_eq!(keys(fast_value), keys(expected.clone()));
+        assert_eq!(from_flatbin::<JsonValue>(ty, &bytes).unwrap(), value);
+    }
+
+    // An ordered map encodes the same bytes whatever order its keys are given in
+    let bytes = deserialize_alloc(&ordered, &value).unwrap();
+    assert_eq!(deserialize_alloc(&ordered, &sorted).unwrap(), bytes);
+    assert_eq!(bytes.map_get(&ordered, "fig").unwrap().unwrap().read_u64().unwrap(), 2);
+    assert_eq!(
+        bytes.map_get(&ordered, "apple").unwrap().unwrap().read_u64().unwrap(),
+        1
+    );
+    assert_eq!(bytes.map_get(&ordered, "pear").unwrap().unwrap().read_u64().unwrap(), 3);
+    assert!(bytes.map_get(&ordered, "cherry").unwrap().is_none());
+    assert!(deserialize_alloc(&ordered, &serde_json::json!({}))
+        .unwrap()
+        .map_get(&ordered, "fig")
+        .unwrap()
+        .is_none());
+
+    // Keys out of order, or repeated, are rejected
+    let unordered = deserialize_alloc(&insertion, &value).unwrap();
+    assert!(matches!(
+        ordered.validate(&unordered),
+        Err(flatbin::Error::UnorderedKeys)
+    ));
+    for ty in [&insertion, &ordered] {
+        let err = crate::fast::deserialize(ty, r#"{ "fig": 1, "pear": 2, "fig": 3 }"#).unwrap_err();
+        assert!(err.to_string().contains("duplicate key \"fig\""), "{}", err);
+    }
+    let mut buffer = FlatbinBuf::new();
+    let mut vector = Builder::new(&mut buffer).start_vector();
+    for key in ["fig", "fig"] {
+        let mut entry = vector.as_builder().start_tuple();
+        entry.as_builder().write_str(key);
+        entry.as_builder().write_u64(1);
+        entry.end();
+    }
+    vector.end();
+    for ty in [&insertion, &ordered] {
+        assert!(matches!(ty.validate(&buffer), Err(flatbin::Error::DuplicateKey { .. })));
+    }
+
+    let parsed: Ty = serde_json::from_str(r#"{ "map": { "value": "u64", "layout": "ordered" } }"#).unwrap();
+    assert_eq!(parsed, ordered);
+    assert_eq!(serde_json::to_string(&insertion).unwrap(), r#"{"map":{"value":"u64"}}"#);
+}
+
+#[test]
+fn map_accessors() {
+    use crate::flatbin::TypedFlatbin;
+    use crate::ty::MapLayout;
+
+    let value = serde_json::json!({ "pear": 3, "apple": 1, "fig": 2 });
+    for layout in [MapLayout::Insertion, MapLayout::Ordered] {
+        let ty = Ty::Map {
+            value: Ty::U64.into(),
+            layout,
+        };
+        let bytes = deserialize_alloc(&ty, &value).unwrap();
+
+        for (key, expected) in [
+            ("pear", Some(3)),
+            ("apple", Some(1)),
+            ("fig", Some(2)),
+            ("kiwi", None),
+            ("", None),
+        ] {
+            let found = bytes.map_get(&ty, key).unwrap().map(|v| v.read_u64().unwrap());
+            assert_eq!(found, expected, "{:?} {}", layout, key);
+            let typed = TypedFlatbin::new(&ty, &bytes).map_get(key).unwrap();
+            assert_eq!(typed.map(|v| v.as_u64().unwrap()), expected);
+        }
+
+        let mut keys = bytes.map_keys().collect::<flatbin::Result<Vec<_>>>().unwrap();
+        let values = bytes
+            .map_values()
+            .map(|v| v?.read_u64())
+            .collect::<flatbin::Result<Vec<_>>>()
+            .unwrap();
+        let entries = bytes
+            .map_entries()
+            .map(|entry| entry.and_then(|(key, value)| Ok((key, value.read_u64()?))))
+            .collect::<flatbin::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().map(|&(key, _)| key).eq(keys.iter().copied()));
+        assert!(entries.iter().map(|&(_, value)| value).eq(values));
+        if layout == MapLayout::Ordered {
+            assert_eq!(keys, ["apple", "fig", "pear"]);
+        }
+        keys.sort();
+        assert_eq!(keys, ["apple", "fig", "pear"]);
+    }
+
+    // Only maps can be looked up by key
+    let bytes = deserialize_alloc(&Ty::U64, &serde_json::json!(5)).unwrap();
+    assert!(matches!(
+        bytes.map_get(&Ty::U64, "a"),
+        Err(flatbin::Error::TypeMismatch)
+    ));
+    assert!(matches!(
+        TypedFlatbin::new(&Ty::U64, &bytes).map_get("a"),
+        Err(flatbin::Error::TypeMismatch)
+    ));
+}
+
+#[test]
+fn collect_errors() {
+    use crate::slow::{collect_errors, Error};
+    use crate::ty::PathSegment::{Field, Index};
+
+    let ty = struct_def!({
+        "name": Ty::String,
+        "age": Ty::U64,
+        "email": Ty::String,
+        "languages": array_def!(struct_def!({
+            "name": Ty::String,
+            "liked": Ty::Bool,
+        }))
+    });
+    let options = DeserializeOptions::default();
+
+    let value = serde_json::json!({
+        "name": "Alexander",
+        "age": 27,
+        "email": "alex@example.com",
+        "languages": [{ "name": "Rust", "liked": true }]
+    });
+    assert!(collect_errors(&ty, &value, &options).is_empty());
+
+    // Every problem is reported, not just the first
+    let value = serde_json::json!({
+        "name": 42,
+        "age": -1,
+        "languages": [{ "name": "Rust", "liked": "yes" }, { "liked": false }, 7]
+    });
+    let errors = collect_errors(&ty, &value, &options);
+    let paths: Vec<_> = errors.iter().map(|(path, _)| path.as_slice()).collect();
+    assert_eq!(
+        paths,
+        [
+            &[Field("name")][..],
+            &[Field("age")],
+            &[],
+            &[Field("languages"), Index(0), Field("liked")],
+            &[Field("languages"), Index(1)],
+            &[Field("languages"), Index(2)],
+        ]
+    );
+    assert!(matches!(&errors[2].1, Error::MissingField { name } if &**name == "email"));
+    assert!(matches!(&errors[4].1, Error::MissingField { name } if &**name == "name"));
+    assert!(matches!(errors[5].1, Error::UnexpectedType { .. }));
+
+    // Deserializing reports one of them
+    let first = deserialize_alloc(&ty, &value).unwrap_err().to_string();
+    assert!(errors.iter().any(|(_, error)| error.to_string() == first));
+
+    // Nothing is reported for whatever deserializes, including optional fields left out
+    let ty = Ty::Struct {
+        fields: [
+            crate::ty::Field::new("a", Ty::U64),
+            crate::ty::Field::new_optional("b", Ty::String),
+        ]
+        .into(),
+    };
+    let columnar = Ty::Columnar {
+        fields: [crate::ty::Field::new_optional("b", Ty::String)].into(),
+    };
+    for (ty, value) in [
+        (&ty, serde_json::json!({ "a": 1 })),
+        (&ty, serde_json::json!({ "a": 1, "b": "x" })),
+        (&ty, serde_json::json!({ "b": "x" })),
+        (&columnar, serde_json::json!([{ "b": "x" }])),
+        (&columnar, serde_json::json!([{}])),
+    ] {
+        let errors = collect_errors(ty, &value, &options);
+        assert_eq!(errors.is_empty(), deserialize_alloc(ty, &value).is_ok(), "{}", value);
+    }
+}
+
+#[test]
+fn optional_fields() {
+    use crate::ty::{Field, Variant};
+
+    let v1 = Ty::Struct {
+        fields: [Field::new("id", Ty::U64), Field::new_optional("name", Ty::String)].into(),
+    };
+    let v2 = Ty::Struct {
+        fields: [
+            Field::new("id", Ty::U64),
+            Field::new_optional("name", Ty::String),
+            Field::new_optional("tags", array_def!(Ty::String)),
+            Field::new_optional("visits", Ty::U64),
+        ]
+        .into(),
+    };
+
+    // A document written with fewer optional fields reads the rest as zero values
+    let old = crate::fast::deserialize(&v1, r#"{ "id": 1, "name": "ab" }"#).unwrap();
+    assert!(v2.validate(&old).is_ok());
+    let expected = serde_json::json!({ "id": 1, "name": "ab", "tags": [], "visits": 0 });
+    assert_eq!(serialize(&v2, &old).unwrap(), expected);
+    assert_eq!(
+        crate::fast::to_json_string(&v2, &old, false).unwrap(),
+        expected.to_string()
+    );
+
+    // A document written with more optional fields reads only those the reader knows of
+    let value = serde_json::json!({ "id": 2, "name": "cd", "tags": ["x"], "visits": 300 });
+    let new = deserialize_alloc(&v2, &value).unwrap();
+    assert!(v1.validate(&new).is_ok());
+    assert_eq!(
+        serialize(&v1, &new).unwrap(),
+        serde_json::json!({ "id": 2, "name": "cd" })
+    );
+
+    // JSON documents may omit optional fields, but not others
+    let value = serde_json::json!({ "id": 3, "visits": 4 });
+    let expected = serde_json::json!({ "id": 3, "name": "", "tags": [], "visits": 4 });
+    let slow = deserialize_alloc(&v2, &value).unwrap();
+    let fast = crate::fast::deserialize(&v2, &value.to_string()).unwrap();
+    assert_eq!(slow.as_bytes(), fast.as_bytes());
+    assert_eq!(serialize(&v2, &fast).unwrap(), expected);
+    assert!(deserialize_alloc(&v2, &serde_json::json!({ "name": "ef" })).is_err());
+    assert!(crate::fast::deserialize(&v2, r#"{ "name": "ef" }"#).is_err());
+
+    // Types without an empty zero value can't be optional, as the document couldn't be read back
+    let status = Ty::Enum {
+        variants: [Variant::unit("active"), Variant::unit("closed")].into(),
+        tag: None,
+    };
+    for ty in [Ty::F64, status] {
+        assert!(std::panic::catch_unwind(|| Field::new_optional("extra", ty.clone())).is_err());
+        let json = serde_json::json!({ "name": "extra", "ty": ty, "optional": true });
+        let err = serde_json::from_value::<Field>(json).unwrap_err();
+        assert!(err.to_string().contains("has no zero value"));
+
+        // A field built by hand fails to deserialize when left out
+        let extra = Field {
+            optional: true,
+            ..Field::new("extra", ty)
+        };
+        let v4 = Ty::Struct {
+            fields: [Field::new("id", Ty::U64), extra].into(),
+        };
+        let value = serde_json::json!({ "id": 1 });
+        let result = deserialize_alloc(&v4, &value);
+        assert!(matches!(result, Err(crate::slow::Error::MissingField { name }) if &*name == "extra"));
+        let result = crate::fast::deserialize(&v4, &value.to_string());
+        assert!(result.unwrap_err().to_string().contains("missing field"));
+        assert_eq!(crate::slow::collect_errors(&v4, &value, &Default::default()).len(), 1);
+    }
+
+    // A field that isn't optional can't be missing from the end of a document
+    let v3 = Ty::Struct {
+        fields: [
+            Field::new("id", Ty::U64),
+            Field::new_optional("name", Ty::String),
+            Field::new("email", Ty::String),
+            Field::new_optional("visits", Ty::U64),
+        ]
+        .into(),
+    };
+    assert!(matches!(
+        v3.validate(&old),
+        Err(flatbin::Error::MissingElements { expected: 3, found: 2 })
+    ));
+}
+
+#[test]
+fn send_sync() {
+    use crate::flatbin::{Cursor, FlatbinSet, Sequence, TupleBuilder, TypedFlatbin, VectorBuilder};
+    use crate::options::Budget;
+
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<FlatbinBuf>();
+    assert_send_sync::<Flatbin>();
+    assert_send_sync::<FlatbinSet>();
+    assert_send_sync::<Sequence>();
+    assert_send_sync::<TypedFlatbin>();
+    assert_send_sync::<Cursor>();
+    assert_send_sync::<Builder>();
+    assert_send_sync::<TupleBuilder>();
+    assert_send_sync::<VectorBuilder>();
+    assert_send_sync::<Ty>();
+    assert_send_sync::<Budget>();
+    assert_send_sync::<flatbin::Error>();
+    assert_send_sync::<crate::slow::Error>();
+
+    // A type, a budget and a document may be shared between threads
+    let ty = array_def!(Ty::String);
+    let budget = Budget::new(100, 100);
+    let docs: Vec<FlatbinBuf> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let (ty, budget) = (&ty, &budget);
+                scope.spawn(move || {
+                    let value = serde_json::json!([format!("thread {}", i)]);
+                    let mut buffer = FlatbinBuf::new();
+                    let builder = Builder::new(&mut buffer);
+                    crate::slow::deserialize_budgeted(ty, &value, builder, &Default::default(), budget).unwrap();
+                    buffer
+                })
+            })
+            .collect();
+        threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+    });
+    // Each document is 2 nodes and 8 bytes
+    assert_eq!((budget.remaining_nodes(), budget.remaining_bytes()), (92, 68));
+    std::thread::scope(|scope| {
+        for (i, doc) in docs.iter().enumerate() {
+            let doc: &Flatbin = doc;
+            let ty = &ty;
+            scope.spawn(move || {
+                assert_eq!(
+                    serialize(ty, doc).unwrap(),
+                    serde_json::json!([format!("thread {}", i)])
+                )
+            });
+        }
+    });
 }
@@ -0,0 +1,141 @@
+use super::{Field, MapLayout, Ty, Variant};
+use crate::JsonValue;
+use proptest::prelude::*;
+use std::num::NonZeroUsize;
+
+impl Arbitrary for Ty {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Ty>;
+
+    /// Generates types up to four arrays, structs or columnar arrays deep, with up to six fields per struct.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let leaf = prop_oneof![
+            Just(Ty::Bool),
+            Just(Ty::U64),
+            Just(Ty::I64),
+            Just(Ty::F64),
+            Just(Ty::FloatVec),
+            Just(Ty::Bytes),
+            Just(Ty::String),
+            (1..8usize).prop_map(|n| Ty::ChunkedBytes {
+                chunk_size: NonZeroUsize::new(n).unwrap()
+            }),
+        ];
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|ty| Ty::Array { inner: ty.into() }),
+                inner.clone().prop_map(|ty| Ty::RleArray { inner: ty.into() }),
+                (inner.clone(), any::<bool>()).prop_map(|(ty, ordered)| Ty::Map {
+                    value: ty.into(),
+                    layout: match ordered {
+                        true => MapLayout::Ordered,
+                        false => MapLayout::Insertion,
+                    },
+                }),
+                arbitrary_fields(inner.clone()).prop_map(|fields| Ty::Struct { fields }),
+                arbitrary_fields(inner.clone()).prop_map(|fields| Ty::Columnar { fields }),
+                arbitrary_variants(inner),
+            ]
+        })
+        .boxed()
+    }
+}
+
+fn arbitrary_fields(inner: BoxedStrategy<Ty>) -> impl Strategy<Value = Box<[Field]>> {
+    prop::collection::vec(inner, 0..6).prop_map(|tys| {
+        tys.into_iter()
+            .enumerate()
+            .map(|(i, ty)| Field::new(format!("f{}", i), ty))
+            .collect()
+    })
+}
+
+fn arbitrary_variants(inner: BoxedStrategy<Ty>) -> impl Strategy<Value = Ty> {
+    let variants = prop::collection::vec(arbitrary_fields(inner), 1..4).prop_map(|variants| {
+        variants
+            .into_iter()
+            .enumerate()
+            .map(|(i, fields)| Variant::new(format!("v{}", i), fields))
+            .collect()
+    });
+    // The tag never collides with the names of generated fields
+    (variants, any::<bool>()).prop_map(|(variants, tagged)| Ty::Enum {
+        variants,
+        tag: tagged.then(|| "kind".into()),
+    })
+}
+
+/// Returns a strategy generating JSON values which conform to `ty`.
+///
+/// Floats are limited to multiples of 1/8 so that they survive a trip through JSON text exactly.
+pub fn arbitrary_value(ty: &Ty) -> BoxedStrategy<JsonValue> {
+    match ty {
+        Ty::Bool => any::<bool>().prop_map(JsonValue::from).boxed(),
+        Ty::U64 => any::<u64>().prop_map(JsonValue::from).boxed(),
+        Ty::I64 => any::<i64>().prop_map(JsonValue::from).boxed(),
+        Ty::F64 => any::<i32>().prop_map(|i| JsonValue::from(i as f64 / 8.0)).boxed(),
+        Ty::FloatVec => prop::collection::vec(arbitrary_value(&Ty::F64), 0..8)
+            .prop_map(JsonValue::from)
+            .boxed(),
+        Ty::Bytes | Ty::ChunkedBytes { .. } => prop::collection::vec(any::<u8>(), 0..16)
+            .prop_map(JsonValue::from)
+            .boxed(),
+        Ty::String => any::<String>().prop_map(JsonValue::from).boxed(),
+        Ty::Array { inner } => prop::collection::vec(arbitrary_value(inner), 0..8)
+            .prop_map(JsonValue::from)
+            .boxed(),
+        Ty::RleArray { inner } => {
+            // Repeat each element a few times, so that some runs are longer than one
+            let runs = prop::collection::vec((arbitrary_value(inner), 1..4usize), 0..4);
+            runs.prop_map(|runs| {
+                let elements = runs
+                    .into_iter()
+                    .flat_map(|(value, count)| std::iter::repeat_n(value, count));
+                JsonValue::from(elements.collect::<Vec<_>>())
+            })
+            .boxed()
+        }
+        Ty::Map { value, .. } => prop::collection::vec((any::<String>(), arbitrary_value(value)), 0..6)
+            .prop_map(|entries| entries.into_iter().collect::<serde_json::Map<_, _>>().into())
+            .boxed(),
+        Ty::Struct { fields } => {
+            let names: Vec<_> = fields.iter().map(|f| f.json_key().to_string()).collect();
+            let values: Vec<_> = fields.iter().map(|f| arbitrary_value(&f.ty)).collect();
+            values
+                .prop_map(move |values| {
+                    names
+                        .iter()
+                        .cloned()
+                        .zip(values)
+                        .collect::<serde_json::Map<_, _>>()
+                        .into()
+                })
+                .boxed()
+        }
+        Ty::Enum { variants, tag } => {
+            let variants = variants.iter().map(|variant| {
+                let name = variant.name.to_string();
+                let fields = arbitrary_value(&Ty::Struct {
+                    fields: variant.fields.clone(),
+                });
+                match tag.clone() {
+                    None if variant.fields.is_empty() => Just(JsonValue::from(name)).boxed(),
+                    None => fields
+                        .prop_map(move |fields| serde_json::json!({ name.clone(): fields }))
+                        .boxed(),
+                    Some(tag) => fields
+                        .prop_map(move |mut fields| {
+                            fields[&*tag] = JsonValue::from(name.clone());
+                            fields
+                        })
+                        .boxed(),
+                }
+            });
+            prop::strategy::Union::new(variants).boxed()
+        }
+        Ty::Columnar { fields } => {
+            let row = arbitrary_value(&Ty::Struct { fields: fields.clone() });
+            prop::collection::vec(row, 0..8).prop_map(JsonValue::from).boxed()
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Field, Ty};
+use crate::flatbin::{Flatbin, Result};
+use std::cmp::Ordering;
+
+impl Ty {
+    /// Compares two values of this type.
+    ///
+    /// Numbers compare numerically (floats by [`f64::total_cmp`]), strings and byte strings compare lexicographically
+    /// by their bytes (which for strings is code point order), `false` is less than `true`, and arrays (run-length
+    /// encoded or not) compare lexicographically by their elements. Maps compare entry by entry in the order they are
+    /// stored, by key and then by value. Structs compare field by field in the order the fields are declared, enums by
+    /// variant index and then field by field, and columnar arrays row by row.
+    pub fn compare<'a>(&self, a: &'a Flatbin, b: &'a Flatbin) -> Result<Ordering> {
+        Ok(match self {
+            Ty::Bool => a.read_bool()?.cmp(&b.read_bool()?),
+            Ty::U64 => a.read_u64()?.cmp(&b.read_u64()?),
+            Ty::I64 => a.read_i64()?.cmp(&b.read_i64()?),
+            Ty::F64 => a.read_f64()?.total_cmp(&b.read_f64()?),
+            Ty::FloatVec => {
+                let (a, b) = (a.read_float_vec()?, b.read_float_vec()?);
+                a.clone()
+                    .zip(b.clone())
+                    .map(|(a, b)| a.total_cmp(&b))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| a.len().cmp(&b.len()))
+            }
+            Ty::Bytes | Ty::String => a.read_bytes()?.cmp(b.read_bytes()?),
+            Ty::ChunkedBytes { .. } => a.read_chunked_bytes()?.cmp(&b.read_chunked_bytes()?),
+            Ty::Array { inner } => compare_elements(inner, a.read_array()?.iter(), b.read_array()?.iter())?,
+            Ty::RleArray { inner } => {
+                let expand = |runs: Vec<(u64, &'a Flatbin)>| {
+                    runs.into_iter()
+                        .flat_map(|(count, value)| (0..count).map(move |_| value))
+                };
+                compare_elements(inner, expand(a.read_runs()?), expand(b.read_runs()?))?
+            }
+            Ty::Map { value, .. } => {
+                let (a, b) = (a.read_array()?, b.read_array()?);
+                for (a, b) in a.iter().zip(b.iter()) {
+                    let ((a_key, a), (b_key, b)) = (a.read_entry()?, b.read_entry()?);
+                    match a_key.as_bytes().cmp(b_key.as_bytes()).then(value.compare(a, b)?) {
+                        Ordering::Equal => continue,
+                        ordering => return Ok(ordering),
+                    }
+                }
+                a.len().cmp(&b.len())
+            }
+            Ty::Struct { fields } => {
+                let (a, b) = (a.read_struct(fields)?, b.read_struct(fields)?);
+                compare_fields(fields, a.iter(), b.iter())?
+            }
+            Ty::Enum { variants, .. } => {
+                let ((a_index, a), (b_index, b)) = (a.read_variant(variants)?, b.read_variant(variants)?);
+                match a_index.cmp(&b_index) {
+                    Ordering::Equal => compare_fields(&variants[a_index].fields, a.iter(), b.iter())?,
+                    ordering => ordering,
+                }
+            }
+            Ty::Columnar { fields } => {
+                let (a_rows, a) = a.read_columns(fields.len())?;
+                let (b_rows, b) = b.read_columns(fields.len())?;
+                let mut a: Vec<_> = a.iter().map(|column| column.iter()).collect();
+                let mut b: Vec<_> = b.iter().map(|column| column.iter()).collect();
+                for _ in 0..a_rows.min(b_rows) {
+                    // Every column holds exactly one node per row
+                    let a_row = a.iter_mut().filter_map(Iterator::next);
+                    let b_row = b.iter_mut().filter_map(Iterator::next);
+                    match compare_fields(fields, a_row, b_row)? {
+                        Ordering::Equal => continue,
+                        ordering => return Ok(ordering),
+                    }
+                }
+                a_rows.cmp(&b_rows)
+            }
+        })
+    }
+}
+
+fn compare_elements<'a>(
+    inner: &Ty,
+    mut a: impl Iterator<Item = &'a Flatbin>,
+    mut b: impl Iterator<Item = &'a Flatbin>,
+) -> Result<Ordering> {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match inner.compare(a, b)? {
+                Ordering::Equal => continue,
+                ordering => return Ok(ordering),
+            },
+            (a, b) => return Ok(a.is_some().cmp(&b.is_some())),
+        }
+    }
+}
+
+fn compare_fields<'a>(
+    fields: &[Field],
+    a: impl Iterator<Item = &'a Flatbin>,
+    b: impl Iterator<Item = &'a Flatbin>,
+) -> Result<Ordering> {
+    for ((field, a), b) in fields.iter().zip(a).zip(b) {
+        match field.ty.compare(a, b)? {
+            Ordering::Equal => continue,
+            ordering => return Ok(ordering),
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{array_def, slow, struct_def, ty::Ty};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn compare() {
+        let ty = struct_def!({ "tags": array_def!(Ty::String), "score": Ty::F64 });
+        let cases = [
+            (
+                serde_json::json!({ "tags": ["a"], "score": 1.0 }),
+                serde_json::json!({ "tags": ["b"], "score": 0.0 }),
+            ),
+            (
+                serde_json::json!({ "tags": ["a"], "score": 1.0 }),
+                serde_json::json!({ "tags": ["a", "a"], "score": 0.0 }),
+            ),
+            (
+                serde_json::json!({ "tags": [], "score": -0.0 }),
+                serde_json::json!({ "tags": [], "score": 0.0 }),
+            ),
+        ];
+        for (a, b) in cases {
+            let a = slow::deserialize_alloc(&ty, &a).unwrap();
+            let b = slow::deserialize_alloc(&ty, &b).unwrap();
+            assert_eq!(ty.compare(&a, &b).unwrap(), Ordering::Less);
+            assert_eq!(ty.compare(&b, &a).unwrap(), Ordering::Greater);
+            assert_eq!(ty.compare(&a, &a).unwrap(), Ordering::Equal);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::{Field, Ty};
+use std::fmt;
+
+/// The differences between two versions of a schema.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SchemaDiff {
+    /// The individual changes, in depth-first order.
+    pub changes: Vec<Change>,
+}
+
+/// A single change between two versions of a schema.
+///
+/// Paths are written as field names joined by `.`, with `[]` denoting the elements of an array and `{}` the
+/// values of a map, e.g. `languages[].name`. The root type has an empty path.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Change {
+    /// A field exists only in the new schema.
+    FieldAdded { path: String },
+    /// A field exists only in the old schema.
+    FieldRemoved { path: String },
+    /// The type at a path changed to an incompatible kind.
+    TypeChanged { path: String, old: Ty, new: Ty },
+    /// The fields common to both versions of the struct at a path appear in a different order.
+    FieldReordered { path: String },
+    /// A field became [optional](Field::optional), or stopped being so.
+    OptionalChanged { path: String, optional: bool },
+    /// The JSON key or aliases of a field changed, so different JSON documents are accepted, though the encoding is
+    /// the same.
+    KeysChanged { path: String },
+    /// The struct or enum variant at a path gained or lost a trailing optional field, changing how its field values
+    /// are encoded (see [`Field::optional`]), so documents written with one version can't be read with the other.
+    EncodingChanged { path: String },
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Ty {
+    /// Computes the differences between an old and new version of a schema.
+    pub fn diff(old: &Ty, new: &Ty) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+        diff_inner(old, new, &mut String::new(), &mut diff.changes);
+        diff
+    }
+}
+
+fn diff_inner(old: &Ty, new: &Ty, path: &mut String, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Ty::Array { inner: old }, Ty::Array { inner: new })
+        | (Ty::RleArray { inner: old }, Ty::RleArray { inner: new }) => {
+            let len = path.len();
+            path.push_str("[]");
+            diff_inner(old, new, path, changes);
+            path.truncate(len);
+        }
+        (
+            Ty::Map {
+                value: old,
+                layout: old_layout,
+            },
+            Ty::Map {
+                value: new,
+                layout: new_layout,
+            },
+        ) if old_layout == new_layout => {
+            let len = path.len();
+            path.push_str("{}");
+            diff_inner(old, new, path, changes);
+            path.truncate(len);
+        }
+        (Ty::Struct { fields: old }, Ty::Struct { fields: new }) => diff_fields(old, new, path, changes),
+        (Ty::Columnar { fields: old }, Ty::Columnar { fields: new }) => {
+            let len = path.len();
+            path.push_str("[]");
+            diff_fields(old, new, path, changes);
+            path.truncate(len);
+        }
+        (old, new) if old != new => changes.push(Change::TypeChanged {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_fields(old: &[Field], new: &[Field], path: &str, changes: &mut Vec<Change>) {
+    let common_old = old.iter().filter(|f| new.iter().any(|g| g.name == f.name));
+    let common_new = new.iter().filter(|f| old.iter().any(|g| g.name == f.name));
+    if !common_old.map(|f| &f.name).eq(common_new.map(|f| &f.name)) {
+        changes.push(Change::FieldReordered { path: path.to_string() });
+    }
+
+    if Field::extensible(old) != Field::extensible(new) {
+        changes.push(Change::EncodingChanged { path: path.to_string() });
+    }
+
+    for field in old.iter() {
+        let mut path = field_path(path, &field.name);
+        let Some(new_field) = new.iter().find(|f| f.name == field.name) else {
+            changes.push(Change::FieldRemoved { path });
+            continue;
+        };
+        if field.optional != new_field.optional {
+            changes.push(Change::OptionalChanged {
+                path: path.clone(),
+                optional: new_field.optional,
+            });
+        }
+        if field.json_key() != new_field.json_key() || field.aliases != new_field.aliases {
+            changes.push(Change::KeysChanged { path: path.clone() });
+        }
+        diff_inner(&field.ty, &new_field.ty, &mut path, changes);
+    }
+
+    for field in new.iter().filter(|f| !old.iter().any(|g| g.name == f.name)) {
+        let path = field_path(path, &field.name);
+        changes.push(Change::FieldAdded { path });
+    }
+}
+
+pub(super) fn field_path(path: &str, name: &str) -> String {
+    match path {
+        "" => name.to_string(),
+        path => format!("{}.{}", path, name),
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::FieldAdded { path } => write!(f, "added field `{}`", path),
+            Change::FieldRemoved { path } => write!(f, "removed field `{}`", path),
+            Change::TypeChanged { path, old, new } => {
+                write!(f, "changed type of `{}` from {:?} to {:?}", path, old, new)
+            }
+            Change::FieldReordered { path } => write!(f, "reordered fields of `{}`", path),
+            Change::OptionalChanged { path, optional: true } => write!(f, "made field `{}` optional", path),
+            Change::OptionalChanged { path, optional: false } => write!(f, "made field `{}` required", path),
+            Change::KeysChanged { path } => write!(f, "changed the JSON keys of field `{}`", path),
+            Change::EncodingChanged { path } => write!(f, "changed the encoding of the fields of `{}`", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Ty};
+    use crate::ty::Field;
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn diff_bench_schema() {
+        let old = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "hobbies": array_def!(Ty::String),
+            "languages": array_def!(struct_def!({
+                "name": Ty::String,
+                "liked": Ty::Bool,
+                "experience": Ty::U64,
+            }))
+        });
+        let new = struct_def!({
+            "age": Ty::I64,
+            "name": Ty::String,
+            "languages": array_def!(struct_def!({
+                "name": Ty::String,
+                "experience": Ty::F64,
+                "since": Ty::U64,
+            })),
+            "email": Ty::String,
+        });
+
+        let diff = Ty::diff(&old, &new);
+        assert_eq!(
+            diff.changes,
+            [
+                Change::FieldReordered { path: "".into() },
+                Change::TypeChanged {
+                    path: "age".into(),
+                    old: Ty::U64,
+                    new: Ty::I64
+                },
+                Change::FieldRemoved { path: "hobbies".into() },
+                Change::FieldRemoved {
+                    path: "languages[].liked".into()
+                },
+                Change::TypeChanged {
+                    path: "languages[].experience".into(),
+                    old: Ty::U64,
+                    new: Ty::F64
+                },
+                Change::FieldAdded {
+                    path: "languages[].since".into()
+                },
+                Change::FieldAdded { path: "email".into() },
+            ]
+        );
+
+        assert!(Ty::diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn diff_field_attributes() {
+        let old = Ty::Struct {
+            fields: [Field::new("id", Ty::U64), Field::new("name", Ty::String)].into(),
+        };
+        let renamed = Field {
+            json_name: Some("fullName".into()),
+            ..Field::new_optional("name", Ty::String)
+        };
+        let new = Ty::Struct {
+            fields: [Field::new("id", Ty::U64), renamed].into(),
+        };
+        let diff = Ty::diff(&old, &new);
+        assert_eq!(
+            diff.changes,
+            [
+                Change::EncodingChanged { path: "".into() },
+                Change::OptionalChanged {
+                    path: "name".into(),
+                    optional: true
+                },
+                Change::KeysChanged { path: "name".into() },
+            ]
+        );
+        assert_eq!(diff.changes[1].to_string(), "made field `name` optional");
+
+        // Appending the first optional field changes the encoding, while appending more doesn't
+        let Ty::Struct { fields } = &old else { unreachable!() };
+        let appended = |fields: &[Field], name: &str| Ty::Struct {
+            fields: fields
+                .iter()
+                .cloned()
+                .chain([Field::new_optional(name, Ty::U64)])
+                .collect(),
+        };
+        let v2 = appended(fields, "visits");
+        assert_eq!(
+            Ty::diff(&old, &v2).changes,
+            [
+                Change::EncodingChanged { path: "".into() },
+                Change::FieldAdded { path: "visits".into() },
+            ]
+        );
+        let Ty::Struct { fields } = &v2 else { unreachable!() };
+        assert_eq!(
+            Ty::diff(&v2, &appended(fields, "score")).changes,
+            [Change::FieldAdded { path: "score".into() }]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Field, Ty};
+use crate::JsonValue;
+
+impl Ty {
+    /// Returns a representative JSON value of this type, as written with the default
+    /// [`SerializeOptions`](crate::options::SerializeOptions).
+    ///
+    /// Numbers are zero, booleans `false` and strings `"string"`. Byte strings, arrays, maps and columnar arrays have
+    /// a single element (the key of a map's entry being `"key"`), and enums take the form of their first variant, or
+    /// `null` if they have none.
+    pub fn example(&self) -> JsonValue {
+        Generator { state: None }.value(self)
+    }
+
+    /// Returns an example JSON value of this type as [`Ty::example`] does, but with values picked pseudo-randomly
+    /// from `seed`. The same seed always gives the same value.
+    ///
+    /// Collections have between one and three elements, and floats are multiples of 1/8 so that they survive a trip
+    /// through JSON text exactly.
+    pub fn example_seeded(&self, seed: u64) -> JsonValue {
+        Generator { state: Some(seed) }.value(self)
+    }
+}
+
+/// Picks the values of an example, pseudo-randomly if seeded.
+struct Generator {
+    state: Option<u64>,
+}
+
+const WORDS: [&str; 8] = ["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel"];
+
+impl Generator {
+    fn value(&mut self, ty: &Ty) -> JsonValue {
+        match ty {
+            Ty::Bool => (self.below(2) == 1).into(),
+            Ty::U64 => self.below(1000).into(),
+            Ty::I64 => self.around_zero(1000).into(),
+            Ty::F64 => (self.around_zero(4000) as f64 / 8.0).into(),
+            Ty::String => self.word().into(),
+            Ty::Bytes | Ty::ChunkedBytes { .. } => self.repeat(|g| g.below(256).into()),
+            Ty::FloatVec => self.repeat(|g| g.value(&Ty::F64)),
+            Ty::Array { inner } | Ty::RleArray { inner } => self.repeat(|g| g.value(inner)),
+            Ty::Map { value, .. } => {
+                let len = self.len();
+                let entries = (0..len).map(|i| match self.state {
+                    None => ("key".to_string(), self.value(value)),
+                    // Distinct keys, so that the map has as many entries as were picked
+                    Some(_) => (format!("{}{}", self.word(), i), self.value(value)),
+                });
+                JsonValue::Object(entries.collect())
+            }
+            Ty::Struct { fields } => self.fields(fields),
+            Ty::Columnar { fields } => self.repeat(|g| g.fields(fields)),
+            Ty::Enum { variants, tag } => {
+                // An enum without variants has no values
+                if variants.is_empty() {
+                    return JsonValue::Null;
+                }
+                let variant = &variants[self.below(variants.len() as u64) as usize];
+                match tag {
+                    None if variant.fields.is_empty() => variant.name.to_string().into(),
+                    None => serde_json::json!({ &*variant.name: self.fields(&variant.fields) }),
+                    Some(tag) => {
+                        let mut object = serde_json::Map::new();
+                        object.insert(tag.to_string(), variant.name.to_string().into());
+                        let JsonValue::Object(fields) = self.fields(&variant.fields) else {
+                            unreachable!()
+                        };
+                        object.extend(fields);
+                        object.into()
+                    }
+                }
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[Field]) -> JsonValue {
+        let object = fields.iter().map(|f| (f.json_key().to_string(), self.value(&f.ty)));
+        JsonValue::Object(object.collect())
+    }
+
+    fn repeat(&mut self, mut element: impl FnMut(&mut Self) -> JsonValue) -> JsonValue {
+        let len = self.len();
+        JsonValue::Array((0..len).map(|_| element(self)).collect())
+    }
+
+    fn len(&mut self) -> u64 {
+        1 + self.below(3)
+    }
+
+    fn word(&mut self) -> &'static str {
+        match self.state {
+            None => "string",
+            Some(_) => WORDS[self.below(WORDS.len() as u64) as usize],
+        }
+    }
+
+    /// Returns a number between `-n` and `n`, which is always zero if unseeded.
+    fn around_zero(&mut self, n: i64) -> i64 {
+        match self.state {
+            None => 0,
+            Some(_) => self.below(2 * n as u64 + 1) as i64 - n,
+        }
+    }
+
+    /// Returns a number less than `n`, which is always zero if unseeded.
+    fn below(&mut self, n: u64) -> u64 {
+        let Some(state) = &mut self.state else {
+            return 0;
+        };
+        // SplitMix64, which is plenty for picking examples
+        *state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        (z ^ (z >> 31)) % n
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ty::{Field, MapLayout, Ty, Variant};
+    use crate::{array_def, struct_def, JsonValue};
+    use serde_json::json;
+
+    fn schema() -> Ty {
+        struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "balance": Ty::I64,
+            "avatar": Ty::Bytes,
+            "languages": array_def!(struct_def!({ "name": Ty::String, "liked": Ty::Bool })),
+            "scores": Ty::Map {
+                value: Ty::F64.into(),
+                layout: MapLayout::Ordered,
+            },
+            "shape": Ty::Enum {
+                variants: [
+                    Variant::new("circle", [Field::new("radius", Ty::F64)]),
+                    Variant::unit("point"),
+                ]
+                .into(),
+                tag: Some("kind".into()),
+            },
+        })
+    }
+
+    #[test]
+    fn example() {
+        let expected = json!({
+            "name": "string",
+            "age": 0,
+            "balance": 0,
+            "avatar": [0],
+            "languages": [{ "name": "string", "liked": false }],
+            "scores": { "key": 0.0 },
+            "shape": { "kind": "circle", "radius": 0.0 },
+        });
+        assert_eq!(schema().example(), expected);
+    }
+
+    #[test]
+    fn example_seeded() {
+        let ty = schema();
+        let examples: Vec<_> = (0..16).map(|seed| ty.example_seeded(seed)).collect();
+        for (seed, example) in examples.iter().enumerate() {
+            assert_eq!(&ty.example_seeded(seed as u64), example);
+            let bytes = crate::fast::deserialize(&ty, &example.to_string()).unwrap();
+            assert!(ty.validate(&bytes).is_ok());
+        }
+        assert!(examples.iter().any(|example| example != &examples[0]));
+
+        let empty = Ty::Enum {
+            variants: [].into(),
+            tag: None,
+        };
+        assert_eq!(empty.example(), JsonValue::Null);
+        assert_eq!(empty.example_seeded(1), JsonValue::Null);
+    }
+}
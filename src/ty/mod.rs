@@ -0,0 +1,738 @@
+#[cfg(feature = "proptest")]
+pub use arbitrary::*;
+pub use diff::*;
+pub use primitive::Primitive;
+#[cfg(feature = "protobuf")]
+pub use protobuf::ProtobufError;
+pub use size::*;
+pub use validate::PathSegment;
+
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod compare;
+mod diff;
+mod example;
+mod paged;
+mod primitive;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+mod scan;
+mod simplify;
+mod size;
+mod sort;
+mod typescript;
+mod validate;
+
+use crate::JsonValue;
+use serde::{Deserialize, Serialize};
+use std::{num::NonZeroUsize, str::FromStr};
+
+/// A type.
+///
+/// Types are represented in JSON with the variant name in lowercase, tagging the contents of
+/// compound types, e.g. `{ "array": { "inner": "string" } }`.
+///
+/// Types are immutable once built and are `Send` and `Sync`, so one may be shared between threads, e.g. in an
+/// `Arc`, to deserialize and serialize documents concurrently.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ty {
+    /// A boolean.
+    Bool,
+    /// A 64-bit unsigned integer.
+    U64,
+    /// A 64-bit signed integer.
+    I64,
+    /// A 64-bit float.
+    F64,
+    /// A sequence of bytes.
+    Bytes,
+    /// A UTF-8 string.
+    String,
+    /// A homogenous sequence of values.
+    Array {
+        /// The type of elements in the sequence.
+        inner: Box<Ty>,
+    },
+    /// A structure containing named fields.
+    Struct {
+        /// The fields comprising the struct.
+        fields: Box<[Field]>,
+    },
+    /// A homogenous sequence of structs, stored column by column.
+    ///
+    /// In JSON this is an array of objects, each of which must contain every field. It is encoded as a tuple
+    /// whose first child is the number of rows, followed by one child per field: a tuple of that field's value
+    /// in every row. A single column can therefore be scanned without decoding any of the others.
+    Columnar {
+        /// The fields of every row.
+        fields: Box<[Field]>,
+    },
+    /// A sequence of bytes, split into chunks of `chunk_size` bytes.
+    ///
+    /// In JSON this is the same as [`Ty::Bytes`]. It is encoded as an array of byte strings, every one of which
+    /// is exactly `chunk_size` bytes long except the last, which holds the remaining 1 to `chunk_size` bytes. An
+    /// empty sequence has no chunks. Individual chunks can be read without reassembling the whole sequence.
+    ChunkedBytes {
+        /// The length of every chunk but the last.
+        chunk_size: NonZeroUsize,
+    },
+    /// A homogenous sequence of values, stored as runs of equal values.
+    ///
+    /// In JSON this is the same as [`Ty::Array`]. It is encoded as an array of runs, each a tuple of a count
+    /// (at least one) and a value, which stands for that many consecutive copies of the value. Values are
+    /// equal if their encodings are equal. A short encoding can therefore stand for a very long array.
+    RleArray {
+        /// The type of elements in the sequence.
+        inner: Box<Ty>,
+    },
+    /// A sequence of 64-bit floats, packed without per-element headers.
+    ///
+    /// In JSON this is the same as an array of [`Ty::F64`]. It is encoded as a varint count (omitted when zero, as
+    /// for arrays) followed by every float as 8 little-endian bytes, regardless of the platform's endianness. The
+    /// floats can therefore be read in place as one contiguous block.
+    FloatVec,
+    /// One of several named variants, each with its own fields.
+    ///
+    /// It is encoded as a tuple of the variant's index followed by a tuple of its fields, as for a struct. In JSON
+    /// the variant is given by name, in one of two forms. When `tag` is `None` it is externally tagged: a variant
+    /// without fields is just its name, e.g. `"empty"`, and any other is an object with the name as its only key,
+    /// e.g. `{ "circle": { "radius": 1.5 } }`. Otherwise it is internally tagged: an object holding the name under
+    /// the `tag` key alongside the fields, e.g. `{ "type": "circle", "radius": 1.5 }` with a tag of `"type"`.
+    Enum {
+        /// The variants, whose positions are their encoded indices.
+        variants: Box<[Variant]>,
+        /// The key holding the variant's name, if the enum is internally tagged.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<Box<str>>,
+    },
+    /// A map from string keys to values of a single type.
+    ///
+    /// In JSON this is an object. It is encoded as an array of entries, each a tuple of the key and its value, in
+    /// the order given by `layout`. Keys must be unique.
+    Map {
+        /// The type of every value.
+        value: Box<Ty>,
+        /// The order in which entries are stored.
+        #[serde(default, skip_serializing_if = "MapLayout::is_insertion")]
+        layout: MapLayout,
+    },
+}
+
+impl Ty {
+    /// Returns the maximum number of arrays and structs nested inside one another in this type.
+    ///
+    /// Primitive types have a depth of zero. This is the smallest
+    /// [`max_depth`](crate::options::DeserializeOptions::max_depth) that will accept documents of this type.
+    pub fn depth(&self) -> usize {
+        match self {
+            Ty::Array { inner } | Ty::RleArray { inner } => 1 + inner.depth(),
+            // An array of tuples
+            Ty::Map { value, .. } => 2 + value.depth(),
+            Ty::Struct { fields } => 1 + fields.iter().map(|f| f.ty.depth()).max().unwrap_or(0),
+            // An array of structs
+            Ty::Columnar { fields } => 2 + fields.iter().map(|f| f.ty.depth()).max().unwrap_or(0),
+            // A tuple of the index and the fields
+            Ty::Enum { variants, .. } => {
+                let fields = variants.iter().flat_map(|v| v.fields.iter());
+                2 + fields.map(|f| f.ty.depth()).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns whether this type has a zero value, encoded as an empty body, which an
+    /// [optional](Field::optional) field of this type takes when left out.
+    pub(crate) fn has_zero_value(&self) -> bool {
+        self.validate(crate::flatbin::Flatbin::from_bytes(&[])).is_ok()
+    }
+
+    /// Returns a copy of this type with every single-field struct replaced by the type of its field.
+    ///
+    /// The last child of a tuple is written without a length header, so a single-field struct is encoded
+    /// exactly like its field. A document encoded with either type can therefore be decoded with the other:
+    /// decoding with the unwrapped type emits the inner values directly, and encoding with it re-wraps them.
+    /// A struct whose field is [optional](Field::optional) is kept, as its field is written with a header.
+    ///
+    /// To read and write documents of this shape without changing the type, set
+    /// [`SerializeOptions::unwrap_single_fields`](crate::options::SerializeOptions::unwrap_single_fields) and
+    /// [`DeserializeOptions::unwrap_single_fields`](crate::options::DeserializeOptions::unwrap_single_fields).
+    pub fn unwrap_single_field(&self) -> Ty {
+        match self {
+            Ty::Array { inner } => Ty::Array {
+                inner: inner.unwrap_single_field().into(),
+            },
+            Ty::RleArray { inner } => Ty::RleArray {
+                inner: inner.unwrap_single_field().into(),
+            },
+            Ty::Map { value, layout } => Ty::Map {
+                value: value.unwrap_single_field().into(),
+                layout: *layout,
+            },
+            Ty::Struct { fields } if fields.len() == 1 && !fields[0].optional => fields[0].ty.unwrap_single_field(),
+            Ty::Struct { fields } => Ty::Struct {
+                fields: unwrap_fields(fields),
+            },
+            Ty::Columnar { fields } => Ty::Columnar {
+                fields: unwrap_fields(fields),
+            },
+            // Variants keep their fields, so that each is still given by name in JSON
+            Ty::Enum { variants, tag } => Ty::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| Variant::new(variant.name.clone(), unwrap_fields(&variant.fields)))
+                    .collect(),
+                tag: tag.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// Returns whether two types are logically the same, ignoring the order of struct fields.
+    ///
+    /// Unlike `==`, this does not imply the types share an encoding, since field order determines layout.
+    pub fn equivalent(&self, other: &Ty) -> bool {
+        match (self, other) {
+            (Ty::Array { inner: a }, Ty::Array { inner: b }) => a.equivalent(b),
+            (Ty::RleArray { inner: a }, Ty::RleArray { inner: b }) => a.equivalent(b),
+            (
+                Ty::Map {
+                    value: a,
+                    layout: a_layout,
+                },
+                Ty::Map {
+                    value: b,
+                    layout: b_layout,
+                },
+            ) => a_layout == b_layout && a.equivalent(b),
+            (Ty::Struct { fields: a }, Ty::Struct { fields: b }) => fields_equivalent(a, b),
+            (Ty::Columnar { fields: a }, Ty::Columnar { fields: b }) => fields_equivalent(a, b),
+            (
+                Ty::Enum {
+                    variants: a,
+                    tag: a_tag,
+                },
+                Ty::Enum {
+                    variants: b,
+                    tag: b_tag,
+                },
+            ) => {
+                a_tag == b_tag
+                    && a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| a.name == b.name && fields_equivalent(&a.fields, &b.fields))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Returns the narrowest numeric type able to represent values of both `a` and `b`.
+    ///
+    /// The widening lattice is `U64 < I64 < F64`. Returns `None` if either type is not numeric.
+    pub fn unify_number(a: &Ty, b: &Ty) -> Option<Ty> {
+        let rank = |ty: &Ty| match ty {
+            Ty::U64 => Some(0),
+            Ty::I64 => Some(1),
+            Ty::F64 => Some(2),
+            _ => None,
+        };
+        match rank(a)?.max(rank(b)?) {
+            0 => Some(Ty::U64),
+            1 => Some(Ty::I64),
+            _ => Some(Ty::F64),
+        }
+    }
+
+    /// Returns the total number of type nodes in this type, including itself.
+    pub fn node_count(&self) -> usize {
+        match self {
+            Ty::Array { inner } | Ty::RleArray { inner } | Ty::Map { value: inner, .. } => 1 + inner.node_count(),
+            Ty::Struct { fields } | Ty::Columnar { fields } => {
+                1 + fields.iter().map(|f| f.ty.node_count()).sum::<usize>()
+            }
+            Ty::Enum { variants, .. } => {
+                let fields = variants.iter().flat_map(|v| v.fields.iter());
+                1 + fields.map(|f| f.ty.node_count()).sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+}
+
+fn unwrap_fields(fields: &[Field]) -> Box<[Field]> {
+    fields
+        .iter()
+        .map(|f| Field {
+            ty: f.ty.unwrap_single_field(),
+            ..f.clone()
+        })
+        .collect()
+}
+
+fn fields_equivalent(a: &[Field], b: &[Field]) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|fa| {
+            b.iter()
+                .find(|fb| fa.name == fb.name)
+                .is_some_and(|fb| fa.ty.equivalent(&fb.ty))
+        })
+}
+
+/// A struct field.
+///
+/// Deserializing a field fails if it is [optional](Self::optional) but its type has no zero value.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "FieldDef")]
+pub struct Field {
+    /// The name of the field.
+    pub name: Box<str>,
+    /// The key of the field in JSON documents, if it differs from `name`.
+    ///
+    /// Fields are encoded by position, so renaming a field this way doesn't change its encoding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_name: Option<Box<str>>,
+    /// Other keys accepted for the field when deserializing, e.g. an old spelling during a migration.
+    ///
+    /// Serialization always uses the key given by [`json_key`](Self::json_key).
+    #[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+    pub aliases: Box<[Box<str>]>,
+    /// Whether the field may be left out, in which case it takes the zero value of its type: `false`, zero, or an
+    /// empty string, byte string, array, map or columnar array. Types without one, such as floats and enums, can't be
+    /// optional.
+    ///
+    /// A struct or enum variant whose last field is optional is encoded so that optional fields can later be appended
+    /// to it (see [`Flatbin::read_struct`](crate::flatbin::Flatbin::read_struct)). The encoding is fixed once
+    /// documents are written, so [`Ty::diff`] reports a change to it as [`Change::EncodingChanged`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// The type of the field.
+    pub ty: Ty,
+}
+
+impl Field {
+    pub fn new(name: impl Into<Box<str>>, ty: Ty) -> Self {
+        Self {
+            name: name.into(),
+            json_name: None,
+            aliases: Box::new([]),
+            optional: false,
+            ty,
+        }
+    }
+
+    /// Returns whether the field may be left out of a JSON document: whether it is [optional](Self::optional) and
+    /// its type has a zero value to be written in its place. Only a field built by hand can fail the latter.
+    pub(crate) fn omittable(&self) -> bool {
+        self.optional && self.ty.has_zero_value()
+    }
+
+    /// Returns an [optional](Self::optional) field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` has no zero value, e.g. if it is a float or an enum.
+    pub fn new_optional(name: impl Into<Box<str>>, ty: Ty) -> Self {
+        let name = name.into();
+        assert!(
+            ty.has_zero_value(),
+            "field `{}` can't be optional, as its type has no zero value",
+            name
+        );
+        Self {
+            optional: true,
+            ..Self::new(name, ty)
+        }
+    }
+
+    /// Returns whether a struct with the given fields is encoded so that optional fields can be appended, i.e.
+    /// whether its last field is optional.
+    pub fn extensible(fields: &[Field]) -> bool {
+        fields.last().is_some_and(|field| field.optional)
+    }
+
+    /// Returns the only field of a struct with the given fields, if the struct is encoded exactly like it, i.e. if
+    /// there is one field and it isn't optional.
+    pub(crate) fn only_field(fields: &[Field]) -> Option<&Field> {
+        match fields {
+            [field] if !field.optional => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of the field in JSON documents: `json_name` if it is set, or otherwise `name`.
+    pub fn json_key(&self) -> &str {
+        self.json_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Returns every key accepted for the field when deserializing: its JSON key, then its aliases.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.json_key()).chain(self.aliases.iter().map(|alias| &**alias))
+    }
+
+    /// Returns whether `key` is one of the field's [`keys`](Self::keys).
+    pub fn matches_key(&self, key: &str) -> bool {
+        self.keys().any(|k| k == key)
+    }
+
+    /// Returns whether `key` is one of the field's [`keys`](Self::keys), ignoring ASCII case.
+    pub fn matches_key_ignore_case(&self, key: &str) -> bool {
+        self.keys().any(|k| k.eq_ignore_ascii_case(key))
+    }
+}
+
+/// A variant of an enum.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Variant {
+    /// The name of the variant, which identifies it in JSON documents.
+    pub name: Box<str>,
+    /// The fields of the variant, which may be empty.
+    #[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+    pub fields: Box<[Field]>,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<Box<str>>, fields: impl Into<Box<[Field]>>) -> Self {
+        Self {
+            name: name.into(),
+            fields: fields.into(),
+        }
+    }
+
+    /// Returns a variant without fields.
+    pub fn unit(name: impl Into<Box<str>>) -> Self {
+        Self::new(name, [])
+    }
+}
+
+/// The order in which the entries of a [`Ty::Map`] are stored.
+/// The serialized form of a [`Field`], checked as it is converted.
+#[derive(Deserialize)]
+struct FieldDef {
+    name: Box<str>,
+    #[serde(default)]
+    json_name: Option<Box<str>>,
+    #[serde(default)]
+    aliases: Box<[Box<str>]>,
+    #[serde(default)]
+    optional: bool,
+    ty: Ty,
+}
+
+impl TryFrom<FieldDef> for Field {
+    type Error = String;
+
+    fn try_from(def: FieldDef) -> Result<Self, String> {
+        let FieldDef {
+            name,
+            json_name,
+            aliases,
+            optional,
+            ty,
+        } = def;
+        if optional && !ty.has_zero_value() {
+            return Err(format!(
+                "field `{}` can't be optional, as its type has no zero value",
+                name
+            ));
+        }
+        Ok(Field {
+            name,
+            json_name,
+            aliases,
+            optional,
+            ty,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapLayout {
+    /// Entries are stored in the order they were given, and a key is found by a linear scan. Encoding is as cheap
+    /// as for an array, and serializing returns the keys in their original order.
+    #[default]
+    Insertion,
+    /// Entries are sorted by key, comparing their UTF-8 bytes, and a key is found by binary search with
+    /// [`Flatbin::map_get`](crate::flatbin::Flatbin::map_get). Encoding must buffer and sort the entries, and the
+    /// original order of the keys is lost. In exchange, maps with the same entries always encode to the same bytes,
+    /// whatever order their keys were given in, which suits hashing and content-addressed storage.
+    Ordered,
+}
+
+impl MapLayout {
+    fn is_insertion(&self) -> bool {
+        *self == MapLayout::Insertion
+    }
+}
+
+impl TryFrom<&JsonValue> for Ty {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &JsonValue) -> serde_json::Result<Ty> {
+        Ty::deserialize(value)
+    }
+}
+
+impl FromStr for Ty {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> serde_json::Result<Ty> {
+        serde_json::from_str(s)
+    }
+}
+
+/// A Rust type with a corresponding [`Ty`].
+///
+/// With the `derive` feature enabled, this can be derived for structs with named fields.
+pub trait Schema {
+    /// Returns the schema describing this type.
+    fn schema() -> Ty;
+}
+
+macro_rules! impl_schema {
+    ($ty:expr; $($type:ty),*) => {
+        $(
+            impl Schema for $type {
+                fn schema() -> Ty {
+                    $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_schema!(Ty::Bool; bool);
+impl_schema!(Ty::U64; u8, u16, u32, u64, usize);
+impl_schema!(Ty::I64; i8, i16, i32, i64, isize);
+impl_schema!(Ty::F64; f32, f64);
+impl_schema!(Ty::String; String, str);
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Ty {
+        Ty::Array {
+            inner: T::schema().into(),
+        }
+    }
+}
+
+impl<T: Schema> Schema for [T] {
+    fn schema() -> Ty {
+        Ty::Array {
+            inner: T::schema().into(),
+        }
+    }
+}
+
+impl<T: Schema + ?Sized> Schema for &T {
+    fn schema() -> Ty {
+        T::schema()
+    }
+}
+
+impl<T: Schema + ?Sized> Schema for Box<T> {
+    fn schema() -> Ty {
+        T::schema()
+    }
+}
+
+/// Returns the schema of the type of `value`, as used by [`schema!`](crate::schema).
+#[doc(hidden)]
+pub fn schema_of<T: Schema + ?Sized>(_value: &T) -> Ty {
+    T::schema()
+}
+
+#[macro_export]
+macro_rules! array_def {
+    ($ty:expr) => {
+        Ty::Array { inner: $ty.into() }
+    };
+}
+
+#[macro_export]
+macro_rules! struct_def {
+    ({
+        // Comma-separated key-value pairs, each key optionally followed by `as "jsonName"`
+        $($key:literal $(as $json_name:literal)? : $value:expr),*
+        // Allows trailing commas
+        $(,)?
+    }) => {{
+        let fields = vec![
+            // Expand each key-value pair
+            $(
+                $crate::ty::Field {
+                    json_name: None $(.or(Some($json_name.into())))?,
+                    ..$crate::ty::Field::new($key, $value)
+                }
+            ),*
+        ].into();
+        Ty::Struct { fields }
+    }};
+}
+
+/// Builds a [`Ty`] from a sample value, inferring the type of each field from a literal.
+///
+/// Objects become structs and single-element arrays become arrays of their element's type, while any other value
+/// takes the [`Schema`] of its Rust type: `""` is a string, `true` a boolean, `0u64` an unsigned integer, `0.0` a
+/// float, and an unsuffixed integer such as `0` (an `i32`) a signed integer. Each value must be a single token tree,
+/// so a negative number or any other expression must be parenthesized, e.g. `(-1)`. A value whose type has no schema
+/// fails to compile.
+///
+/// ```
+/// use talk_serde_dyn_schema::{array_def, schema, struct_def, ty::Ty};
+///
+/// let ty = schema!({ "name": "", "age": 0u64, "scores": [0.0], "address": { "city": "" } });
+/// let expected = struct_def!({
+///     "name": Ty::String,
+///     "age": Ty::U64,
+///     "scores": array_def!(Ty::F64),
+///     "address": struct_def!({ "city": Ty::String }),
+/// });
+/// assert_eq!(ty, expected);
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ({ $($key:literal : $value:tt),* $(,)? }) => {
+        $crate::ty::Ty::Struct {
+            fields: vec![$($crate::ty::Field::new($key, $crate::schema!($value))),*].into(),
+        }
+    };
+    ([ $value:tt ]) => {
+        $crate::ty::Ty::Array {
+            inner: $crate::schema!($value).into(),
+        }
+    };
+    ($value:expr) => {
+        $crate::ty::schema_of(&$value)
+    };
+}
+
+// FIXME: impl Display for Ty?
+
+#[cfg(test)]
+mod test {
+    use super::Ty;
+
+    #[test]
+    fn depth_and_node_count() {
+        assert_eq!(Ty::U64.depth(), 0);
+        assert_eq!(Ty::U64.node_count(), 1);
+
+        let ty = struct_def!({
+            "name": Ty::String,
+            "hobbies": array_def!(Ty::String),
+            "languages": array_def!(struct_def!({
+                "name": Ty::String,
+                "experience": Ty::U64,
+            })),
+        });
+        assert_eq!(ty.depth(), 3);
+        assert_eq!(ty.node_count(), 8);
+
+        assert_eq!(struct_def!({}).depth(), 1);
+    }
+
+    #[test]
+    fn unify_number() {
+        use Ty::*;
+
+        let table = [
+            (U64, U64, Some(U64)),
+            (U64, I64, Some(I64)),
+            (I64, U64, Some(I64)),
+            (I64, I64, Some(I64)),
+            (U64, F64, Some(F64)),
+            (F64, I64, Some(F64)),
+            (F64, F64, Some(F64)),
+            (U64, String, None),
+            (Bool, F64, None),
+        ];
+        for (a, b, expected) in table {
+            assert_eq!(Ty::unify_number(&a, &b), expected, "{:?} + {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn json_representation() {
+        let ty = struct_def!({
+            "name": Ty::String,
+            "scores": array_def!(Ty::F64),
+        });
+        let json = serde_json::json!({
+            "struct": {
+                "fields": [
+                    { "name": "name", "ty": "string" },
+                    { "name": "scores", "ty": { "array": { "inner": "f64" } } }
+                ]
+            }
+        });
+
+        assert_eq!(serde_json::to_value(&ty).unwrap(), json);
+        assert_eq!(Ty::try_from(&json).unwrap(), ty);
+        assert_eq!(json.to_string().parse::<Ty>().unwrap(), ty);
+
+        let err = Ty::try_from(&serde_json::json!({ "array": { "inner": "u128" } })).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `u128`"));
+    }
+
+    #[test]
+    fn equivalent() {
+        let a = struct_def!({ "x": Ty::U64, "y": array_def!(struct_def!({ "p": Ty::Bool, "q": Ty::String })) });
+        let b = struct_def!({ "y": array_def!(struct_def!({ "q": Ty::String, "p": Ty::Bool })), "x": Ty::U64 });
+        let c = struct_def!({ "y": array_def!(struct_def!({ "q": Ty::String, "p": Ty::U64 })), "x": Ty::U64 });
+        let d = struct_def!({ "x": Ty::U64 });
+
+        assert_ne!(a, b);
+        assert!(a.equivalent(&b));
+        assert!(!a.equivalent(&c));
+        assert!(!a.equivalent(&d));
+        assert!(!d.equivalent(&a));
+    }
+
+    #[test]
+    fn unwrap_single_field() {
+        let ty = struct_def!({
+            "id": struct_def!({ "value": Ty::U64 }),
+            "tags": array_def!(struct_def!({ "name": Ty::String })),
+        });
+        let unwrapped = struct_def!({
+            "id": Ty::U64,
+            "tags": array_def!(Ty::String),
+        });
+        assert_eq!(ty.unwrap_single_field(), unwrapped);
+
+        let wrapped = serde_json::json!({ "id": { "value": 5 }, "tags": [{ "name": "a" }, { "name": "b" }] });
+        let bytes = crate::fast::deserialize(&ty, &wrapped.to_string()).unwrap();
+        let value = crate::slow::serialize(&unwrapped, &bytes).unwrap();
+        assert_eq!(value, serde_json::json!({ "id": 5, "tags": ["a", "b"] }));
+
+        let rewrapped = crate::fast::deserialize(&unwrapped, &value.to_string()).unwrap();
+        assert_eq!(bytes.as_bytes(), rewrapped.as_bytes());
+    }
+
+    #[test]
+    fn schema_macro() {
+        let ty = crate::schema!({
+            "name": "",
+            "age": 0u64,
+            "offset": (-1),
+            "active": true,
+            "scores": [0.0],
+            "matrix": [[0u8]],
+            "owner": { "email": (String::new()) },
+            "empty": {},
+        });
+        let expected = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "offset": Ty::I64,
+            "active": Ty::Bool,
+            "scores": array_def!(Ty::F64),
+            "matrix": array_def!(array_def!(Ty::U64)),
+            "owner": struct_def!({ "email": Ty::String }),
+            "empty": struct_def!({}),
+        });
+        assert_eq!(ty, expected);
+        assert_eq!(crate::schema!([""]), array_def!(Ty::String));
+    }
+}
@@ -0,0 +1,84 @@
+use super::Ty;
+use crate::{
+    flatbin::{Flatbin, Result},
+    slow, JsonValue,
+};
+
+impl Ty {
+    /// Decodes an array whose elements are of type `inner` in pages of `page_size` elements, so that only one
+    /// page is held in memory at a time.
+    ///
+    /// If an element fails to decode, that page is yielded as an error and iteration stops, without decoding
+    /// any further elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `page_size` is zero.
+    pub fn serialize_array_paged<'a>(
+        inner: &'a Ty,
+        value: &'a Flatbin,
+        page_size: usize,
+    ) -> impl Iterator<Item = Result<Vec<JsonValue>>> + 'a {
+        assert!(page_size > 0, "page size must be non-zero");
+        let mut elements = value.read_array().map(|array| array.iter()).map_err(Some);
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let elements = match &mut elements {
+                Ok(elements) => elements,
+                Err(err) => return err.take().map(Err),
+            };
+            let page = elements
+                .by_ref()
+                .take(page_size)
+                .map(|element| slow::serialize(inner, element))
+                .collect::<Result<Vec<_>>>();
+            match page {
+                Ok(page) if page.is_empty() => None,
+                Ok(page) => Some(Ok(page)),
+                Err(err) => {
+                    done = true;
+                    Some(Err(err))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        array_def,
+        flatbin::{Builder, FlatbinBuf},
+        slow,
+        ty::Ty,
+    };
+
+    #[test]
+    fn serialize_array_paged() {
+        let value = serde_json::json!([1, 2, 3, 4, 5]);
+        let ty = array_def!(Ty::U64);
+        let bytes = slow::deserialize_alloc(&ty, &value).unwrap();
+
+        let pages = Ty::serialize_array_paged(&Ty::U64, &bytes, 2).map(|page| serde_json::Value::from(page.unwrap()));
+        assert!(pages.eq([
+            serde_json::json!([1, 2]),
+            serde_json::json!([3, 4]),
+            serde_json::json!([5])
+        ]));
+
+        // The second page holds a malformed string
+        let mut buffer = FlatbinBuf::new();
+        let mut vector = Builder::new(&mut buffer).start_vector();
+        for bytes in [&b"ok"[..], b"ok", b"\xff", b"ok"] {
+            vector.as_builder().write_bytes(bytes);
+        }
+        vector.end();
+        let pages: Vec<_> = Ty::serialize_array_paged(&Ty::String, &buffer, 2).collect();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].as_ref().unwrap().len(), 2);
+        assert!(pages[1].is_err());
+    }
+}
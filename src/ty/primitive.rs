@@ -0,0 +1,157 @@
+use super::Ty;
+use crate::flatbin::{Error, Flatbin, Result};
+
+/// A primitive which an array can be decoded into by [`Ty::decode_primitive_array`].
+pub trait Primitive: Sized {
+    /// Returns whether `ty` is the type of this primitive.
+    fn is_ty(ty: &Ty) -> bool;
+
+    fn read(value: &Flatbin) -> Result<Self>;
+
+    /// Appends the elements of a packed array of type `ty` to `out`, or returns `None` if `ty` isn't a packed
+    /// array of this primitive.
+    fn read_packed(ty: &Ty, value: &Flatbin, out: &mut Vec<Self>) -> Option<Result<()>> {
+        let _ = (ty, value, out);
+        None
+    }
+}
+
+impl Primitive for u64 {
+    fn is_ty(ty: &Ty) -> bool {
+        matches!(ty, Ty::U64)
+    }
+
+    fn read(value: &Flatbin) -> Result<Self> {
+        value.read_u64()
+    }
+}
+
+impl Primitive for i64 {
+    fn is_ty(ty: &Ty) -> bool {
+        matches!(ty, Ty::I64)
+    }
+
+    fn read(value: &Flatbin) -> Result<Self> {
+        value.read_i64()
+    }
+}
+
+impl Primitive for f64 {
+    fn is_ty(ty: &Ty) -> bool {
+        matches!(ty, Ty::F64)
+    }
+
+    fn read(value: &Flatbin) -> Result<Self> {
+        value.read_f64()
+    }
+
+    fn read_packed(ty: &Ty, value: &Flatbin, out: &mut Vec<Self>) -> Option<Result<()>> {
+        let Ty::FloatVec = ty else {
+            return None;
+        };
+        Some(value.read_float_vec().map(|floats| out.extend(floats)))
+    }
+}
+
+impl Ty {
+    /// Appends the elements of an array of primitives of type `T` — a `u64`, `i64` or `f64` — to `out`, reading
+    /// each straight from the document.
+    ///
+    /// This type must be an array or run-length encoded array of `T`, or for `f64` a [`Ty::FloatVec`], and
+    /// otherwise this fails with [`Error::TypeMismatch`]. If reading an element fails, `out` is left as it was.
+    pub fn decode_primitive_array<T: Primitive + Clone>(&self, value: &Flatbin, out: &mut Vec<T>) -> Result<()> {
+        let len = out.len();
+        let result = self.decode_primitive_array_inner(value, out);
+        if result.is_err() {
+            out.truncate(len);
+        }
+        result
+    }
+
+    fn decode_primitive_array_inner<T: Primitive + Clone>(&self, value: &Flatbin, out: &mut Vec<T>) -> Result<()> {
+        if let Some(result) = T::read_packed(self, value, out) {
+            return result;
+        }
+        match self {
+            Ty::Array { inner } if T::is_ty(inner) => {
+                // The count is checked against the size of the document before reserving space for it
+                out.reserve(value.array_len()?);
+                for element in value.read_array()?.try_iter() {
+                    out.push(T::read(element?)?);
+                }
+            }
+            Ty::RleArray { inner } if T::is_ty(inner) => {
+                // The runs add up to at most `MAX_RLE_LEN` elements
+                let runs = value.read_runs()?;
+                out.reserve(runs.iter().map(|&(count, _)| count as usize).sum());
+                for (count, value) in runs {
+                    let count = usize::try_from(count).map_err(|_| Error::NumberTooLarge)?;
+                    out.extend(std::iter::repeat_n(T::read(value)?, count));
+                }
+            }
+            _ => return Err(Error::TypeMismatch),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::array_def;
+
+    #[test]
+    fn decode_primitive_array() {
+        let ty = array_def!(Ty::U64);
+        let bytes = crate::fast::deserialize(&ty, "[1, 2, 3]").unwrap();
+        let mut out = vec![0u64];
+        ty.decode_primitive_array(&bytes, &mut out).unwrap();
+        assert_eq!(out, [0, 1, 2, 3]);
+
+        // The element type must match
+        let mut floats: Vec<f64> = vec![];
+        assert!(matches!(
+            ty.decode_primitive_array(&bytes, &mut floats),
+            Err(Error::TypeMismatch)
+        ));
+        let mut ints: Vec<i64> = vec![];
+        assert!(matches!(
+            Ty::U64.decode_primitive_array(&bytes, &mut ints),
+            Err(Error::TypeMismatch)
+        ));
+
+        let ty = Ty::RleArray { inner: Ty::I64.into() };
+        let bytes = crate::fast::deserialize(&ty, "[-1, -1, -1, 5]").unwrap();
+        ty.decode_primitive_array(&bytes, &mut ints).unwrap();
+        assert_eq!(ints, [-1, -1, -1, 5]);
+
+        for ty in [array_def!(Ty::F64), Ty::FloatVec] {
+            let bytes = crate::fast::deserialize(&ty, "[0.5, 1.5]").unwrap();
+            floats.clear();
+            ty.decode_primitive_array(&bytes, &mut floats).unwrap();
+            assert_eq!(floats, [0.5, 1.5]);
+        }
+
+        // A failed decode leaves the output as it was
+        let ty = array_def!(Ty::U64);
+        let bytes = crate::fast::deserialize(&ty, "[1, 2, 3]").unwrap();
+        let truncated = Flatbin::from_bytes(&bytes.as_bytes()[..2]);
+        let mut out = vec![7u64];
+        ty.decode_primitive_array(truncated, &mut out).unwrap_err();
+        assert_eq!(out, [7]);
+
+        // Counts far beyond the size of the document fail rather than being allocated for
+        let huge_count = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        assert!(matches!(
+            ty.decode_primitive_array(huge_count, &mut out),
+            Err(Error::UnexpectedLength)
+        ));
+        let ty = Ty::RleArray { inner: Ty::U64.into() };
+        let huge_run = Flatbin::from_bytes(&[1, 134, 0, 0, 0, 0, 0, 1, 7]);
+        assert!(matches!(
+            ty.decode_primitive_array(huge_run, &mut out),
+            Err(Error::RleTooLong)
+        ));
+        assert_eq!(out, [7]);
+    }
+}
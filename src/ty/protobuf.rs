@@ -0,0 +1,161 @@
+use super::{Field, Ty};
+use prost_reflect::{FieldDescriptor, Kind, MessageDescriptor};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProtobufError {
+    #[error("field {field} is unsupported: {reason}")]
+    Unsupported { field: String, reason: &'static str },
+    #[error("message {message} is recursive")]
+    Recursive { message: String },
+}
+
+impl Ty {
+    /// Generates the type of the JSON representation of a protobuf message.
+    ///
+    /// Fields keep their protobuf names, with their JSON names (where different) as their `json_name`. Every
+    /// integer type maps to [`Ty::U64`] or [`Ty::I64`], `float` and `double` map to [`Ty::F64`], `repeated`
+    /// fields map to [`Ty::Array`], and nested messages map to [`Ty::Struct`]. Enums, maps, oneofs, proto3
+    /// `optional` fields and recursive messages have no equivalent `Ty`, and are reported as errors.
+    ///
+    /// Note that the proto3 JSON mapping writes 64-bit integers as strings and `bytes` as base64, neither of
+    /// which this type accepts.
+    pub fn from_protobuf_message(message: &MessageDescriptor) -> Result<Ty, ProtobufError> {
+        message_ty(message, &mut vec![])
+    }
+}
+
+fn message_ty(message: &MessageDescriptor, stack: &mut Vec<String>) -> Result<Ty, ProtobufError> {
+    if stack.iter().any(|name| name == message.full_name()) {
+        return Err(ProtobufError::Recursive {
+            message: message.full_name().into(),
+        });
+    }
+    stack.push(message.full_name().into());
+    let fields = message
+        .fields()
+        .map(|field| {
+            Ok(Field {
+                json_name: (field.json_name() != field.name()).then(|| field.json_name().into()),
+                ..Field::new(field.name(), field_ty(&field, stack)?)
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    stack.pop();
+    Ok(Ty::Struct { fields })
+}
+
+fn field_ty(field: &FieldDescriptor, stack: &mut Vec<String>) -> Result<Ty, ProtobufError> {
+    let unsupported = |reason| ProtobufError::Unsupported {
+        field: field.full_name().into(),
+        reason,
+    };
+    if field.is_map() {
+        return Err(unsupported("maps are not supported"));
+    }
+    if field.field_descriptor_proto().proto3_optional() {
+        return Err(unsupported("optional fields are not supported"));
+    }
+    if field.containing_oneof().is_some() {
+        return Err(unsupported("oneofs are not supported"));
+    }
+    let ty = match field.kind() {
+        Kind::Bool => Ty::Bool,
+        Kind::Uint32 | Kind::Uint64 | Kind::Fixed32 | Kind::Fixed64 => Ty::U64,
+        Kind::Int32 | Kind::Int64 | Kind::Sint32 | Kind::Sint64 | Kind::Sfixed32 | Kind::Sfixed64 => Ty::I64,
+        Kind::Float | Kind::Double => Ty::F64,
+        Kind::String => Ty::String,
+        Kind::Bytes => Ty::Bytes,
+        Kind::Message(message) => message_ty(&message, stack)?,
+        Kind::Enum(_) => return Err(unsupported("enums are not supported")),
+    };
+    Ok(match field.is_list() {
+        true => Ty::Array { inner: ty.into() },
+        false => ty,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProtobufError;
+    use crate::{array_def, struct_def, ty::Ty};
+    use prost_reflect::{
+        prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+        },
+        DescriptorPool,
+    };
+
+    fn field(name: &str, ty: Type, label: Label, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.into()),
+            label: Some(label.into()),
+            r#type: Some(ty.into()),
+            type_name: type_name.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    fn message(name: &str, mut fields: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        for (number, field) in fields.iter_mut().enumerate() {
+            field.number = Some(number as i32 + 1);
+        }
+        DescriptorProto {
+            name: Some(name.into()),
+            field: fields,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn from_protobuf_message() {
+        let file = FileDescriptorProto {
+            name: Some("people.proto".into()),
+            package: Some("people".into()),
+            syntax: Some("proto3".into()),
+            message_type: vec![
+                message(
+                    "Person",
+                    vec![
+                        field("name", Type::String, Label::Optional, None),
+                        field("age", Type::Uint32, Label::Optional, None),
+                        field("balance", Type::Sint64, Label::Optional, None),
+                        field("hobbies", Type::String, Label::Repeated, None),
+                        field("languages", Type::Message, Label::Repeated, Some(".people.Language")),
+                    ],
+                ),
+                message(
+                    "Language",
+                    vec![
+                        field("name", Type::String, Label::Optional, None),
+                        field("years_used", Type::Double, Label::Optional, None),
+                    ],
+                ),
+                message(
+                    "Node",
+                    vec![field("children", Type::Message, Label::Repeated, Some(".people.Node"))],
+                ),
+            ],
+            ..Default::default()
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(prost_reflect::prost_types::FileDescriptorSet {
+            file: vec![file],
+        })
+        .unwrap();
+
+        let person = pool.get_message_by_name("people.Person").unwrap();
+        let expected = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "balance": Ty::I64,
+            "hobbies": array_def!(Ty::String),
+            "languages": array_def!(struct_def!({ "name": Ty::String, "years_used" as "yearsUsed": Ty::F64 })),
+        });
+        assert_eq!(Ty::from_protobuf_message(&person).unwrap(), expected);
+
+        let node = pool.get_message_by_name("people.Node").unwrap();
+        let result = Ty::from_protobuf_message(&node);
+        assert!(matches!(result, Err(ProtobufError::Recursive { .. })));
+    }
+}
@@ -0,0 +1,99 @@
+use super::{Field, Ty};
+use crate::flatbin::{Error, Flatbin, Result};
+
+impl Ty {
+    /// Calls `f` with the node at `path` within every element of an array of structs, or every row of a
+    /// columnar array.
+    ///
+    /// The first path segment names a field of each element, and any further segments name fields of
+    /// nested structs. Only the requested field is read: for a columnar array this is a walk along a single
+    /// column, while for an array of structs the sibling fields are skipped over using their headers.
+    pub fn scan_field(&self, value: &Flatbin, path: &[&str], mut f: impl FnMut(&Flatbin)) -> Result<()> {
+        match self {
+            Ty::Array { inner } => {
+                for element in value.read_array()? {
+                    f(inner.field_at(element, path)?);
+                }
+            }
+            Ty::Columnar { fields } => {
+                let (name, path) = path.split_first().ok_or(Error::TypeMismatch)?;
+                let index = field_index(fields, name)?;
+                let (_, columns) = value.read_columns(fields.len())?;
+                for element in columns[index].iter() {
+                    f(fields[index].ty.field_at(element, path)?);
+                }
+            }
+            _ => return Err(Error::TypeMismatch),
+        }
+        Ok(())
+    }
+
+    pub(super) fn field_at<'a>(&self, value: &'a Flatbin, path: &[&str]) -> Result<&'a Flatbin> {
+        let Some((name, path)) = path.split_first() else {
+            return Ok(value);
+        };
+        let Ty::Struct { fields } = self else {
+            return Err(Error::TypeMismatch);
+        };
+        let index = field_index(fields, name)?;
+        let value = value.read_struct(fields)?.get(index).ok_or(Error::UnexpectedEOF)?;
+        fields[index].ty.field_at(value, path)
+    }
+
+    /// Returns the type of the node at `path`, as found by [`field_at`](Self::field_at).
+    pub(super) fn ty_at(&self, path: &[&str]) -> Result<&Ty> {
+        let Some((name, path)) = path.split_first() else {
+            return Ok(self);
+        };
+        let Ty::Struct { fields } = self else {
+            return Err(Error::TypeMismatch);
+        };
+        fields[field_index(fields, name)?].ty.ty_at(path)
+    }
+}
+
+fn field_index(fields: &[Field], name: &str) -> Result<usize> {
+    fields
+        .iter()
+        .position(|f| &*f.name == name)
+        .ok_or_else(|| Error::NoSuchField { name: name.into() })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{array_def, slow, struct_def, ty::Ty};
+
+    #[test]
+    fn scan_field() {
+        let Ty::Struct { fields } = struct_def!({
+            "name": Ty::String,
+            "stats": struct_def!({ "age": Ty::U64, "height": Ty::F64 }),
+        }) else {
+            unreachable!()
+        };
+        let value = serde_json::json!([
+            { "name": "Alice", "stats": { "age": 31, "height": 1.5 } },
+            { "name": "Bob", "stats": { "age": 27, "height": 1.75 } },
+        ]);
+
+        for ty in [
+            array_def!(Ty::Struct { fields: fields.clone() }),
+            Ty::Columnar { fields: fields.clone() },
+        ] {
+            let bytes = slow::deserialize_alloc(&ty, &value).unwrap();
+
+            let mut total = 0;
+            ty.scan_field(&bytes, &["stats", "age"], |v| total += v.read_u64().unwrap())
+                .unwrap();
+            assert_eq!(total, 58);
+
+            let mut names = vec![];
+            ty.scan_field(&bytes, &["name"], |v| names.push(v.read_str().unwrap().to_string()))
+                .unwrap();
+            assert_eq!(names, ["Alice", "Bob"]);
+
+            assert!(ty.scan_field(&bytes, &["nope"], |_| {}).is_err());
+            assert!(ty.scan_field(&bytes, &["name", "nope"], |_| {}).is_err());
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Field, Ty, Variant};
+use crate::options::SimplifyOptions;
+
+impl Ty {
+    /// Returns a copy of this type in a canonical form, applying rewrites which change neither the JSON documents
+    /// it accepts nor their encoding:
+    ///
+    /// - A field's `json_name` is dropped if it equals its name.
+    /// - A field's aliases are deduplicated, dropping any equal to its JSON key.
+    ///
+    /// Simplifying a simplified type returns it unchanged.
+    pub fn simplify(&self) -> Ty {
+        self.simplify_with(&SimplifyOptions::default())
+    }
+
+    /// Returns a copy of this type in a canonical form as [`simplify`](Self::simplify) does, additionally applying
+    /// the rewrites enabled in `options`, which change the documents the type accepts.
+    pub fn simplify_with(&self, options: &SimplifyOptions) -> Ty {
+        match self {
+            Ty::Array { inner } => Ty::Array {
+                inner: inner.simplify_with(options).into(),
+            },
+            Ty::RleArray { inner } => Ty::RleArray {
+                inner: inner.simplify_with(options).into(),
+            },
+            Ty::Map { value, layout } => Ty::Map {
+                value: value.simplify_with(options).into(),
+                layout: *layout,
+            },
+            Ty::Struct { fields } => simplify_struct(simplify_fields(fields, options), options),
+            Ty::Columnar { fields } => Ty::Columnar {
+                fields: simplify_fields(fields, options),
+            },
+            Ty::Enum { variants, .. }
+                if options.collapse_single_variant_enums && variants.len() == 1 && !variants[0].fields.is_empty() =>
+            {
+                simplify_struct(simplify_fields(&variants[0].fields, options), options)
+            }
+            Ty::Enum { variants, tag } => Ty::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| Variant::new(variant.name.clone(), simplify_fields(&variant.fields, options)))
+                    .collect(),
+                tag: tag.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+}
+
+/// Returns a struct of already simplified fields, unwrapping it if it has a single field and `options` allow.
+fn simplify_struct(fields: Box<[Field]>, options: &SimplifyOptions) -> Ty {
+    match &*fields {
+        // The field's header is elided only if it isn't optional
+        [field] if options.unwrap_single_fields && !field.optional => field.ty.clone(),
+        _ => Ty::Struct { fields },
+    }
+}
+
+fn simplify_fields(fields: &[Field], options: &SimplifyOptions) -> Box<[Field]> {
+    fields
+        .iter()
+        .map(|field| {
+            let json_name = field.json_name.clone().filter(|json_name| *json_name != field.name);
+            let mut aliases: Vec<Box<str>> = vec![];
+            for alias in field.aliases.iter() {
+                if **alias != *field.json_key() && !aliases.contains(alias) {
+                    aliases.push(alias.clone());
+                }
+            }
+            Field {
+                json_name,
+                aliases: aliases.into(),
+                ty: field.ty.simplify_with(options),
+                ..field.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::options::SimplifyOptions;
+    use crate::ty::{Field, Ty, Variant};
+    use crate::{array_def, struct_def};
+
+    fn messy() -> Ty {
+        let name = Field {
+            json_name: Some("name".into()),
+            aliases: ["fullName".into(), "name".into(), "fullName".into()].into(),
+            ..Field::new("name", Ty::String)
+        };
+        let shape = Ty::Enum {
+            variants: [Variant::new("circle", [Field::new("radius", Ty::F64)])].into(),
+            tag: None,
+        };
+        Ty::Struct {
+            fields: [
+                name,
+                Field::new("id", struct_def!({ "value": Ty::U64 })),
+                Field::new("shapes", array_def!(shape)),
+            ]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn simplify() {
+        let ty = messy();
+        let simple = ty.simplify();
+        let (Ty::Struct { fields }, Ty::Struct { fields: original }) = (&simple, &ty) else {
+            unreachable!()
+        };
+        assert_eq!(fields[0].json_name, None);
+        assert_eq!(&*fields[0].aliases, [Box::from("fullName")]);
+        // Nothing else changes, as the rest would change the documents accepted
+        assert_eq!(fields[1..], original[1..]);
+        assert_eq!(simple.simplify(), simple);
+
+        // Documents are accepted and encoded exactly as before
+        let value =
+            serde_json::json!({ "fullName": "Ada", "id": { "value": 1 }, "shapes": [{ "circle": { "radius": 2.0 } }] });
+        let before = crate::slow::deserialize_alloc(&ty, &value).unwrap();
+        let after = crate::slow::deserialize_alloc(&simple, &value).unwrap();
+        assert_eq!(before.as_bytes(), after.as_bytes());
+        assert_eq!(
+            crate::slow::serialize(&ty, &before).unwrap(),
+            crate::slow::serialize(&simple, &after).unwrap()
+        );
+    }
+
+    #[test]
+    fn simplify_with() {
+        let options = SimplifyOptions {
+            collapse_single_variant_enums: true,
+            unwrap_single_fields: true,
+        };
+        let simple = messy().simplify_with(&options);
+        let expected = Ty::Struct {
+            fields: [
+                Field {
+                    aliases: ["fullName".into()].into(),
+                    ..Field::new("name", Ty::String)
+                },
+                Field::new("id", Ty::U64),
+                // The enum collapses to a single-field struct, which is then unwrapped
+                Field::new("shapes", array_def!(Ty::F64)),
+            ]
+            .into(),
+        };
+        assert_eq!(simple, expected);
+        assert_eq!(simple.simplify_with(&options), simple);
+
+        // Unwrapping keeps the encoding
+        let value = serde_json::json!({ "name": "Ada", "id": { "value": 1 }, "shapes": [] });
+        let before = crate::slow::deserialize_alloc(&messy().simplify_with(&SimplifyOptions::default()), &value);
+        let unwrapped = messy().simplify_with(&SimplifyOptions {
+            unwrap_single_fields: true,
+            ..Default::default()
+        });
+        let value = serde_json::json!({ "name": "Ada", "id": 1, "shapes": [] });
+        let after = crate::slow::deserialize_alloc(&unwrapped, &value);
+        assert_eq!(before.unwrap().as_bytes(), after.unwrap().as_bytes());
+
+        // An optional field keeps its struct, and a unit variant its enum
+        let ty = Ty::Struct {
+            fields: [Field::new_optional("value", Ty::U64)].into(),
+        };
+        assert_eq!(ty.simplify_with(&options), ty);
+        let ty = Ty::Enum {
+            variants: [Variant::unit("only")].into(),
+            tag: None,
+        };
+        assert_eq!(ty.simplify_with(&options), ty);
+    }
+}
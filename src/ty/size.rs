@@ -0,0 +1,325 @@
+use super::{diff::field_path, Field, Ty};
+use crate::flatbin::header_len;
+use std::{fmt, ops::Add};
+
+/// The number of elements assumed for the typical size of an array.
+const TYPICAL_ARRAY_LEN: usize = 4;
+/// The length in bytes assumed for the typical size of a string or byte string.
+const TYPICAL_STRING_LEN: usize = 16;
+/// The length in bytes assumed for the typical size of an integer, i.e. a magnitude below 65536.
+const TYPICAL_INT_LEN: usize = 2;
+/// The most bytes reserved up front for an array, so that an inflated length hint cannot exhaust memory.
+const MAX_ARRAY_RESERVATION: usize = 1 << 20;
+
+/// An estimate of the encoded size of a value, in bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SizeEstimate {
+    /// The smallest possible size.
+    pub min: usize,
+    /// The size assuming arrays of 4 elements, strings of 16 bytes, and integers below 65536.
+    pub typical: usize,
+    /// The largest possible size, or `None` if the size is unbounded.
+    pub max: Option<usize>,
+}
+
+/// The estimated encoded size of a field, identified by its path.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldSize {
+    /// The field names joined by `.`, with `[]` denoting the elements of an array and `{}` the values of a map, e.g.
+    /// `languages[].name`.
+    pub path: String,
+    /// The size of the field's value, including its header.
+    pub size: SizeEstimate,
+}
+
+/// Estimated encoded sizes of a type and each of its fields, as returned by [`Ty::size_report`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SizeReport {
+    /// The size of a whole document.
+    pub total: SizeEstimate,
+    /// The size of every field, nested fields included, in depth-first order.
+    pub fields: Vec<FieldSize>,
+}
+
+impl Ty {
+    /// Estimates the encoded size of values of this type, and of each of its fields.
+    ///
+    /// Field sizes include the field's header, as if it were not the last field of its struct (whose header is
+    /// elided). Values of a fixed-width type have equal minimum and maximum sizes, while strings, byte strings
+    /// and arrays have no maximum.
+    pub fn size_report(&self) -> SizeReport {
+        let mut fields = vec![];
+        collect_fields(self, "", &mut fields);
+        SizeReport {
+            total: body_size(self),
+            fields,
+        }
+    }
+
+    /// The number of bytes worth reserving for an array of `len` elements of this type.
+    pub(crate) fn array_reservation(&self, len: usize) -> usize {
+        len.saturating_mul(node_size(self).typical).min(MAX_ARRAY_RESERVATION)
+    }
+}
+
+impl SizeEstimate {
+    fn fixed(size: usize) -> Self {
+        Self {
+            min: size,
+            typical: size,
+            max: Some(size),
+        }
+    }
+
+    fn scale(self, count: usize) -> Self {
+        Self {
+            min: self.min * count,
+            typical: self.typical * count,
+            max: self.max.map(|max| max * count),
+        }
+    }
+}
+
+impl Add for SizeEstimate {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            min: self.min + other.min,
+            typical: self.typical + other.typical,
+            max: self.max.zip(other.max).map(|(a, b)| a + b),
+        }
+    }
+}
+
+/// The size of a value's body, excluding its header.
+fn body_size(ty: &Ty) -> SizeEstimate {
+    match ty {
+        Ty::Bool => SizeEstimate::fixed(1),
+        Ty::U64 | Ty::I64 => SizeEstimate {
+            min: 0,
+            typical: TYPICAL_INT_LEN,
+            max: Some(8),
+        },
+        Ty::F64 => SizeEstimate::fixed(8),
+        Ty::Bytes | Ty::String => SizeEstimate {
+            min: 0,
+            typical: TYPICAL_STRING_LEN,
+            max: None,
+        },
+        Ty::Array { inner } => {
+            // A non-empty array is prefixed with its length
+            let typical = 1 + tuple_body(inner, TYPICAL_ARRAY_LEN).typical;
+            SizeEstimate {
+                min: 0,
+                typical,
+                max: None,
+            }
+        }
+        Ty::RleArray { inner } => {
+            // A non-empty array is prefixed with its run count, and is typically a single run
+            let typical = 1 + node_size(&Ty::U64).typical + body_size(inner).typical;
+            SizeEstimate {
+                min: 0,
+                typical,
+                max: None,
+            }
+        }
+        Ty::Map { value, .. } => {
+            // A non-empty map is prefixed with its length, and each entry is a tuple of its key and value
+            let entry = || {
+                [
+                    (node_size(&Ty::String), body_size(&Ty::String)),
+                    (node_size(value), body_size(value)),
+                ]
+            };
+            let entry_body = fields_body(entry().into_iter());
+            let typical = 1 + header_size(entry_body).typical * (TYPICAL_ARRAY_LEN - 1) + entry_body.typical;
+            SizeEstimate {
+                min: 0,
+                typical,
+                max: None,
+            }
+        }
+        Ty::FloatVec => SizeEstimate {
+            // A non-empty array is prefixed with its length
+            min: 0,
+            typical: 1 + 8 * TYPICAL_ARRAY_LEN,
+            max: None,
+        },
+        Ty::ChunkedBytes { chunk_size } => {
+            // The chunk count, then every full chunk with its header, then the remainder
+            let chunk_size = chunk_size.get();
+            let full = (TYPICAL_STRING_LEN - 1) / chunk_size;
+            let typical = 1 + full * (chunk_size + header_len(chunk_size)) + TYPICAL_STRING_LEN - full * chunk_size;
+            SizeEstimate {
+                min: 0,
+                typical,
+                max: None,
+            }
+        }
+        Ty::Struct { fields } => struct_body(fields),
+        Ty::Enum { variants, .. } => {
+            // The variant index, then the body of a tuple of the variant's fields
+            let index = node_size(&Ty::U64);
+            let fields = variants.iter().map(|variant| struct_body(&variant.fields));
+            let fields = fields.reduce(|a, b| SizeEstimate {
+                min: a.min.min(b.min),
+                typical: a.typical.max(b.typical),
+                max: a.max.zip(b.max).map(|(a, b)| a.max(b)),
+            });
+            index + fields.unwrap_or(SizeEstimate::fixed(0))
+        }
+        Ty::Columnar { fields } => {
+            // The row count, then a tuple per column
+            let rows = (node_size(&Ty::U64), body_size(&Ty::U64));
+            let columns = fields.iter().map(|field| {
+                let body = SizeEstimate {
+                    min: 0,
+                    typical: tuple_body(&field.ty, TYPICAL_ARRAY_LEN).typical,
+                    max: None,
+                };
+                (header_size(body), body)
+            });
+            let mut size = fields_body(std::iter::once(rows).chain(columns));
+            size.max = None;
+            size
+        }
+    }
+}
+
+/// The size of a value including its header.
+fn node_size(ty: &Ty) -> SizeEstimate {
+    match ty {
+        // A single byte below 0x80 is written without a header
+        Ty::Bool => SizeEstimate::fixed(1),
+        // Zero is an empty body, and small values are a single byte
+        Ty::U64 | Ty::I64 => SizeEstimate {
+            min: 1,
+            typical: 1 + TYPICAL_INT_LEN,
+            max: Some(9),
+        },
+        ty => header_size(body_size(ty)),
+    }
+}
+
+fn header_size(body: SizeEstimate) -> SizeEstimate {
+    SizeEstimate {
+        min: body.min + header_len(body.min),
+        typical: body.typical + header_len(body.typical),
+        max: body.max.map(|max| max + header_len(max)),
+    }
+}
+
+/// The size of the body of `count` consecutive values of type `ty`, with the last header elided.
+fn tuple_body(ty: &Ty, count: usize) -> SizeEstimate {
+    match count {
+        0 => SizeEstimate::fixed(0),
+        count => node_size(ty).scale(count - 1) + body_size(ty),
+    }
+}
+
+/// The size of the body of a tuple given the `(node, body)` sizes of its children.
+fn fields_body(children: impl Iterator<Item = (SizeEstimate, SizeEstimate)>) -> SizeEstimate {
+    let mut children = children.peekable();
+    let mut size = SizeEstimate::fixed(0);
+    while let Some((node, body)) = children.next() {
+        size = size + if children.peek().is_some() { node } else { body };
+    }
+    size
+}
+
+/// The size of the body of a struct's or enum variant's tuple of field values, in which the last field has a header
+/// too if the struct is extensible.
+fn struct_body(fields: &[Field]) -> SizeEstimate {
+    let headed = Field::extensible(fields);
+    fields_body(fields.iter().map(|f| {
+        let node = node_size(&f.ty);
+        (node, if headed { node } else { body_size(&f.ty) })
+    }))
+}
+
+fn collect_fields(ty: &Ty, path: &str, out: &mut Vec<FieldSize>) {
+    match ty {
+        Ty::Array { inner } | Ty::RleArray { inner } => collect_fields(inner, &format!("{}[]", path), out),
+        Ty::Map { value, .. } => collect_fields(value, &format!("{}{{}}", path), out),
+        Ty::Struct { fields } => collect_struct_fields(fields, path, out),
+        Ty::Columnar { fields } => collect_struct_fields(fields, &format!("{}[]", path), out),
+        _ => {}
+    }
+}
+
+fn collect_struct_fields(fields: &[Field], path: &str, out: &mut Vec<FieldSize>) {
+    for field in fields.iter() {
+        let path = field_path(path, &field.name);
+        out.push(FieldSize {
+            path: path.clone(),
+            size: node_size(&field.ty),
+        });
+        collect_fields(&field.ty, &path, out);
+    }
+}
+
+impl fmt::Display for SizeEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) => write!(f, "min {} / typical {} / max {}", self.min, self.typical, max),
+            None => write!(f, "min {} / typical {} / max variable", self.min, self.typical),
+        }
+    }
+}
+
+/// Writes one line per field, followed by the total, e.g. `age: min 1 / typical 3 / max 9`.
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in &self.fields {
+            writeln!(f, "{}: {}", field.path, field.size)?;
+        }
+        write!(f, "total: {}", self.total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{array_def, slow, struct_def, ty::Ty};
+
+    #[test]
+    fn size_report() {
+        let ty = struct_def!({
+            "id": Ty::U64,
+            "score": Ty::F64,
+            "name": Ty::String,
+            "tags": array_def!(Ty::String),
+            "active": Ty::Bool,
+        });
+        let report = ty.size_report();
+        assert_eq!(
+            report.to_string(),
+            "id: min 1 / typical 3 / max 9\n\
+             score: min 9 / typical 9 / max 9\n\
+             name: min 1 / typical 17 / max variable\n\
+             tags: min 1 / typical 70 / max variable\n\
+             active: min 1 / typical 1 / max 1\n\
+             total: min 13 / typical 100 / max variable"
+        );
+
+        // The bounds hold for actual documents
+        for value in [
+            serde_json::json!({ "id": 0, "score": 0.0, "name": "", "tags": [], "active": false }),
+            serde_json::json!({ "id": 300, "score": 1.5, "name": "Alice", "tags": ["a", "bc"], "active": true }),
+            serde_json::json!({ "id": u64::MAX, "score": 1.5, "name": "x".repeat(300), "tags": [], "active": true }),
+        ] {
+            let len = slow::deserialize_alloc(&ty, &value).unwrap().as_bytes().len();
+            assert!(len >= report.total.min, "{}", value);
+        }
+        let min = serde_json::json!({ "id": 0, "score": 0.0, "name": "", "tags": [], "active": false });
+        let len = slow::deserialize_alloc(&ty, &min).unwrap().as_bytes().len();
+        assert_eq!(len, report.total.min);
+
+        let fixed = struct_def!({ "a": Ty::U64, "b": Ty::Bool });
+        let total = fixed.size_report().total;
+        let max = serde_json::json!({ "a": u64::MAX, "b": true });
+        let len = slow::deserialize_alloc(&fixed, &max).unwrap().as_bytes().len();
+        assert_eq!(Some(len), total.max);
+    }
+}
@@ -0,0 +1,161 @@
+use super::Ty;
+use crate::flatbin::{Builder, Error, Flatbin, FlatbinBuf, Result};
+use std::cmp::Ordering;
+
+impl Ty {
+    /// Re-encodes an array with its elements sorted by the node at `path` within each, as ordered by
+    /// [`Ty::compare`]. An empty path sorts by the elements themselves.
+    ///
+    /// The sort is stable, so elements with equal keys keep their order. Every element is read before any is
+    /// written, so the whole array is held in memory; this can't be done while streaming elements into a
+    /// builder.
+    pub fn sort_array(&self, value: &Flatbin, path: &[&str]) -> Result<FlatbinBuf> {
+        let Ty::Array { inner } = self else {
+            return Err(Error::TypeMismatch);
+        };
+        let key_ty = inner.ty_at(path)?;
+        let mut elements = value
+            .read_array()?
+            .try_iter()
+            .map(|element| {
+                let element = element?;
+                Ok((inner.field_at(element, path)?, element))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut error = None;
+        elements.sort_by(|(a, _), (b, _)| {
+            key_ty.compare(a, b).unwrap_or_else(|err| {
+                error.get_or_insert(err);
+                Ordering::Equal
+            })
+        });
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        let mut buffer = FlatbinBuf::new();
+        let mut vector = Builder::new(&mut buffer).start_vector();
+        for (_, element) in elements {
+            vector.as_builder().copy(element);
+        }
+        vector.end();
+        Ok(buffer)
+    }
+
+    /// Binary searches an array, sorted as by [`sort_array`](Self::sort_array), for an element whose node at
+    /// `path` equals `target`.
+    ///
+    /// As with [`slice::binary_search`], returns `Ok` with the index of a matching element, or `Err` with the
+    /// index at which `target` could be inserted to keep the array sorted. If the array isn't sorted by that
+    /// key, the result is unspecified.
+    ///
+    /// Elements can only be reached by walking the headers of those before them, so this walks forward from
+    /// the lower bound of the search at each step. Only `O(log n)` keys are compared, but up to `n` headers are
+    /// read in total.
+    pub fn binary_search(
+        &self,
+        array: &Flatbin,
+        path: &[&str],
+        target: &Flatbin,
+    ) -> Result<std::result::Result<usize, usize>> {
+        let Ty::Array { inner } = self else {
+            return Err(Error::TypeMismatch);
+        };
+        let key_ty = inner.ty_at(path)?;
+        // The count is checked against the size of the data, so that the walk can't run on past its end
+        let len = array.array_len()?;
+        let array = array.read_array()?;
+
+        // `elements` always starts at index `low`
+        let (mut low, mut high) = (0, len);
+        let mut elements = array.iter();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let mut rest = elements.clone();
+            let element = rest.nth(mid - low).ok_or(Error::UnexpectedEOF)?;
+            match key_ty.compare(inner.field_at(element, path)?, target)? {
+                Ordering::Less => {
+                    low = mid + 1;
+                    elements = rest;
+                }
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(Ok(mid)),
+            }
+        }
+        Ok(Err(low))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::flatbin::{Error, Flatbin};
+    use crate::{array_def, slow, struct_def, ty::Ty};
+
+    #[test]
+    fn sort_array() {
+        let ty = array_def!(struct_def!({ "name": Ty::String, "age": Ty::I64 }));
+        let value = serde_json::json!([
+            { "name": "Alice", "age": 31 },
+            { "name": "Bob", "age": -4 },
+            { "name": "Carol", "age": 300 },
+            { "name": "Dave", "age": 31 },
+        ]);
+        let bytes = slow::deserialize_alloc(&ty, &value).unwrap();
+
+        let sorted = ty.sort_array(&bytes, &["age"]).unwrap();
+        let expected = serde_json::json!([
+            { "name": "Bob", "age": -4 },
+            { "name": "Alice", "age": 31 },
+            { "name": "Dave", "age": 31 },
+            { "name": "Carol", "age": 300 },
+        ]);
+        assert_eq!(slow::serialize(&ty, &sorted).unwrap(), expected);
+
+        let ty = array_def!(Ty::F64);
+        let bytes = slow::deserialize_alloc(&ty, &serde_json::json!([2.5, -1.0, 0.0, 10.0])).unwrap();
+        let sorted = ty.sort_array(&bytes, &[]).unwrap();
+        assert_eq!(
+            slow::serialize(&ty, &sorted).unwrap(),
+            serde_json::json!([-1.0, 0.0, 2.5, 10.0])
+        );
+
+        assert!(ty.sort_array(&bytes, &["age"]).is_err());
+
+        // A count far beyond the size of the data fails rather than being walked
+        let huge_count = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        let ty = array_def!(Ty::U64);
+        assert!(matches!(
+            ty.sort_array(huge_count, &[]),
+            Err(Error::MissingElements { .. })
+        ));
+    }
+
+    #[test]
+    fn binary_search() {
+        let ty = array_def!(struct_def!({ "id": Ty::U64, "name": Ty::String }));
+        let value: Vec<_> = (0..100)
+            .map(|i| serde_json::json!({ "id": i * 2, "name": format!("#{}", i) }))
+            .collect();
+        let bytes = slow::deserialize_alloc(&ty, &value.into()).unwrap();
+
+        for id in 0..202 {
+            let target = slow::deserialize_alloc(&Ty::U64, &id.into()).unwrap();
+            let expected = match id % 2 {
+                0 if id < 200 => Ok(id as usize / 2),
+                _ => Err((id as usize).div_ceil(2).min(100)),
+            };
+            assert_eq!(ty.binary_search(&bytes, &["id"], &target).unwrap(), expected, "{}", id);
+        }
+
+        let empty = slow::deserialize_alloc(&ty, &serde_json::json!([])).unwrap();
+        let target = slow::deserialize_alloc(&Ty::U64, &5.into()).unwrap();
+        assert_eq!(ty.binary_search(&empty, &["id"], &target).unwrap(), Err(0));
+
+        let huge_count = Flatbin::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1]);
+        assert!(matches!(
+            array_def!(Ty::U64).binary_search(huge_count, &[], &target),
+            Err(Error::UnexpectedLength)
+        ));
+    }
+}
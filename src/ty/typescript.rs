@@ -0,0 +1,215 @@
+use super::{Field, Ty};
+use std::collections::HashSet;
+
+impl Ty {
+    /// Generates TypeScript declarations describing the JSON documents of this type, as written with the default
+    /// [`SerializeOptions`](crate::options::SerializeOptions).
+    ///
+    /// A struct becomes an interface named `root_name`, and any other type a type alias of that name. Structs nested
+    /// within it become interfaces of their own, named after the path to them, e.g. `PersonAddress` for the
+    /// `address` field of `Person`, and are declared after the root. Integers and floats become `number`, strings
+    /// `string` and booleans `boolean`. Byte strings become `number[]`, an array of the byte values, as in the
+    /// default [`BytesEncoding`](crate::options::BytesEncoding). Arrays become `T[]`, maps `Record<string, T>`, and
+    /// enums a union of their variants' forms.
+    pub fn to_typescript(&self, root_name: &str) -> String {
+        let mut generator = Generator::default();
+        generator.names.insert(root_name.to_string());
+        let root = match self {
+            Ty::Struct { fields } => generator.interface(root_name, fields, None),
+            // The elements of an array can't share the alias' name
+            ty @ (Ty::Array { .. } | Ty::RleArray { .. } | Ty::Columnar { .. }) => {
+                let element = generator.ty(ty, &format!("{}Item", root_name));
+                format!("export type {} = {};\n", root_name, element)
+            }
+            ty => format!("export type {} = {};\n", root_name, generator.ty(ty, root_name)),
+        };
+        std::iter::once(root)
+            .chain(generator.declarations)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    /// Every declaration but the root's, in the order they were generated.
+    declarations: Vec<String>,
+    /// The names declared so far.
+    names: HashSet<String>,
+}
+
+impl Generator {
+    /// Returns the TypeScript type of `ty`, declaring any interfaces it needs with names derived from `name`.
+    fn ty(&mut self, ty: &Ty, name: &str) -> String {
+        match ty {
+            Ty::Bool => "boolean".into(),
+            Ty::U64 | Ty::I64 | Ty::F64 => "number".into(),
+            Ty::String => "string".into(),
+            Ty::Bytes | Ty::ChunkedBytes { .. } | Ty::FloatVec => "number[]".into(),
+            Ty::Array { inner } | Ty::RleArray { inner } => array(self.ty(inner, name)),
+            Ty::Map { value, .. } => format!("Record<string, {}>", self.ty(value, name)),
+            Ty::Struct { fields } => self.nested_interface(name, fields, None),
+            Ty::Columnar { fields } => array(self.nested_interface(name, fields, None)),
+            Ty::Enum { variants, tag } => {
+                let forms = variants.iter().map(|variant| {
+                    let variant_name = format!("{}{}", name, pascal_case(&variant.name));
+                    match tag {
+                        None if variant.fields.is_empty() => string_literal(&variant.name),
+                        None => {
+                            let fields = self.nested_interface(&variant_name, &variant.fields, None);
+                            format!("{{ {}: {} }}", property_key(&variant.name), fields)
+                        }
+                        Some(tag) => self.nested_interface(&variant_name, &variant.fields, Some((tag, &variant.name))),
+                    }
+                });
+                match forms.collect::<Vec<_>>() {
+                    forms if forms.is_empty() => "never".into(),
+                    forms => forms.join(" | "),
+                }
+            }
+        }
+    }
+
+    /// Declares an interface for a struct nested within the root, returning its name.
+    fn nested_interface(&mut self, name: &str, fields: &[Field], tag: Option<(&str, &str)>) -> String {
+        // Names derived from different paths may coincide, e.g. `a_b.c` and `a.b_c`
+        let mut unique = name.to_string();
+        let mut suffix = 1;
+        while !self.names.insert(unique.clone()) {
+            suffix += 1;
+            unique = format!("{}{}", name, suffix);
+        }
+        let declaration = self.interface(&unique, fields, tag);
+        self.declarations.push(declaration);
+        unique
+    }
+
+    /// Returns the declaration of an interface with the given fields, preceded by an internal enum tag if given.
+    fn interface(&mut self, name: &str, fields: &[Field], tag: Option<(&str, &str)>) -> String {
+        let mut declaration = format!("export interface {} {{\n", name);
+        if let Some((tag, variant)) = tag {
+            declaration += &format!("  {}: {};\n", property_key(tag), string_literal(variant));
+        }
+        for field in fields.iter() {
+            let ty = self.ty(&field.ty, &format!("{}{}", name, pascal_case(&field.name)));
+            declaration += &format!("  {}: {};\n", property_key(field.json_key()), ty);
+        }
+        declaration + "}\n"
+    }
+}
+
+fn array(element: String) -> String {
+    match element.contains(' ') {
+        true => format!("({})[]", element),
+        false => format!("{}[]", element),
+    }
+}
+
+/// Converts a field name such as `first_name` or `firstName` to `FirstName`, for use in an interface name.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// Returns `key` as written in an interface: bare if it is an identifier, and otherwise quoted.
+fn property_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    match identifier {
+        true => key.to_string(),
+        false => string_literal(key),
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).expect("strings always serialize")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ty::{Field, MapLayout, Ty, Variant};
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn to_typescript() {
+        let ty = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "avatar" as "avatar-png": Ty::Bytes,
+            "address": struct_def!({ "city": Ty::String, "geo": array_def!(Ty::F64) }),
+            "languages": array_def!(struct_def!({ "name": Ty::String, "liked": Ty::Bool })),
+            "scores": Ty::Map {
+                value: Ty::I64.into(),
+                layout: MapLayout::Insertion,
+            },
+            "shape": Ty::Enum {
+                variants: [
+                    Variant::unit("point"),
+                    Variant::new("circle", [Field::new("radius", Ty::F64)]),
+                ]
+                .into(),
+                tag: None,
+            },
+        });
+        let expected = r#"export interface Person {
+  name: string;
+  age: number;
+  "avatar-png": number[];
+  address: PersonAddress;
+  languages: PersonLanguages[];
+  scores: Record<string, number>;
+  shape: "point" | { circle: PersonShapeCircle };
+}
+
+export interface PersonAddress {
+  city: string;
+  geo: number[];
+}
+
+export interface PersonLanguages {
+  name: string;
+  liked: boolean;
+}
+
+export interface PersonShapeCircle {
+  radius: number;
+}
+"#;
+        assert_eq!(ty.to_typescript("Person"), expected);
+    }
+
+    #[test]
+    fn non_struct_roots() {
+        assert_eq!(Ty::String.to_typescript("Name"), "export type Name = string;\n");
+
+        let ty = Ty::Columnar {
+            fields: [Field::new("id", Ty::U64)].into(),
+        };
+        let expected = "export type Rows = RowsItem[];\n\nexport interface RowsItem {\n  id: number;\n}\n";
+        assert_eq!(ty.to_typescript("Rows"), expected);
+
+        let ty = Ty::Enum {
+            variants: [
+                Variant::unit("none"),
+                Variant::new("some", [Field::new("value", Ty::U64)]),
+            ]
+            .into(),
+            tag: Some("kind".into()),
+        };
+        let expected = "export type Option = OptionNone | OptionSome;\n\n\
+            export interface OptionNone {\n  kind: \"none\";\n}\n\n\
+            export interface OptionSome {\n  kind: \"some\";\n  value: number;\n}\n";
+        assert_eq!(ty.to_typescript("Option"), expected);
+    }
+}
@@ -0,0 +1,274 @@
+use super::{MapLayout, Ty};
+use crate::flatbin::{Error, Flatbin, Result, Sequence};
+use std::{collections::HashSet, fmt};
+
+/// A step along the path to a node within a document, as reported by [`Ty::validate_with_path`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathSegment<'a> {
+    /// An element of an array, a row of a columnar array, or an entry of a map. For a run-length encoded array this
+    /// is the index of the run rather than of the element.
+    Index(usize),
+    /// A field of a struct or of an enum variant, by its name.
+    Field(&'a str),
+}
+
+impl fmt::Display for PathSegment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Field(name) => write!(f, ".{}", name),
+        }
+    }
+}
+
+/// An error found by validation, and the path to the node it was found in, innermost segment first.
+struct Invalid<'a> {
+    path: Vec<PathSegment<'a>>,
+    error: Error,
+}
+
+impl From<Error> for Invalid<'_> {
+    fn from(error: Error) -> Self {
+        Self { path: vec![], error }
+    }
+}
+
+impl Ty {
+    /// Checks that `value` is a well-formed document of this type.
+    ///
+    /// On success, every read of `value` guided by this type will succeed.
+    pub fn validate(&self, value: &Flatbin) -> Result<()> {
+        self.validate_inner(value).map_err(|invalid| invalid.error)
+    }
+
+    /// Checks that `value` is a well-formed document of this type, as by [`validate`](Self::validate), but on
+    /// failure also returns the path from the root to the node holding the first error.
+    ///
+    /// The path is only built once an error is found, so a successful validation allocates nothing.
+    pub fn validate_with_path(&self, value: &Flatbin) -> std::result::Result<(), (Vec<PathSegment<'_>>, Error)> {
+        self.validate_inner(value).map_err(|Invalid { mut path, error }| {
+            path.reverse();
+            (path, error)
+        })
+    }
+
+    fn validate_inner(&self, value: &Flatbin) -> std::result::Result<(), Invalid<'_>> {
+        match self {
+            Ty::Bool => value.read_bool().map(drop)?,
+            Ty::U64 => value.read_u64().map(drop)?,
+            Ty::I64 => value.read_i64().map(drop)?,
+            Ty::F64 => value.read_f64().map(drop)?,
+            Ty::FloatVec => value.read_float_vec().map(drop)?,
+            Ty::Bytes => {}
+            Ty::ChunkedBytes { chunk_size } => {
+                let chunks = value.read_chunks()?;
+                let (last, rest) = match chunks.split_last() {
+                    Some(split) => split,
+                    None => return Ok(()),
+                };
+                if !(rest.iter().all(|chunk| chunk.len() == chunk_size.get())
+                    && (1..=chunk_size.get()).contains(&last.len()))
+                {
+                    return Err(Error::UnexpectedLength.into());
+                }
+            }
+            Ty::String => value.read_str().map(drop)?,
+            Ty::Array { inner } => validate_nodes(value.read_array()?, |i| (inner, Some(PathSegment::Index(i))))?,
+            Ty::RleArray { inner } => {
+                validate_nodes(value.read_array()?, |_| (&Ty::Bytes, None))?;
+                for (i, run) in value.read_array()?.iter().enumerate() {
+                    (run.read_tuple(2).map_err(Invalid::from))
+                        .and_then(|run| validate_nodes(run, |j| ([&Ty::U64, &**inner][j], None)))
+                        .map_err(|invalid| invalid.at(PathSegment::Index(i)))?;
+                }
+                // Runs may repeat the previous run's value, but may not be empty
+                if let Some(i) = value.read_runs()?.iter().position(|&(count, _)| count == 0) {
+                    return Err(Invalid::from(Error::UnexpectedLength).at(PathSegment::Index(i)));
+                }
+            }
+            Ty::Map { value: inner, layout } => {
+                validate_nodes(value.read_array()?, |_| (&Ty::Bytes, None))?;
+                let mut keys = HashSet::new();
+                let mut previous = None;
+                for (i, entry) in value.read_array()?.iter().enumerate() {
+                    let (key, value) =
+                        (entry.read_entry()).map_err(|error| Invalid::from(error).at(PathSegment::Index(i)))?;
+                    inner
+                        .validate_inner(value)
+                        .map_err(|invalid| invalid.at(PathSegment::Index(i)))?;
+                    let error = match layout {
+                        MapLayout::Insertion if !keys.insert(key) => Error::DuplicateKey { key: key.into() },
+                        MapLayout::Ordered if previous == Some(key) => Error::DuplicateKey { key: key.into() },
+                        MapLayout::Ordered if previous > Some(key) => Error::UnorderedKeys,
+                        _ => {
+                            previous = Some(key);
+                            continue;
+                        }
+                    };
+                    return Err(Invalid::from(error).at(PathSegment::Index(i)));
+                }
+            }
+            Ty::Struct { fields } => validate_nodes(value.read_struct(fields)?, |i| {
+                (&fields[i].ty, Some(PathSegment::Field(&fields[i].name)))
+            })?,
+            Ty::Enum { variants, .. } => {
+                let (index, values) = value.read_variant(variants)?;
+                let fields = &variants[index].fields;
+                validate_nodes(values, |i| (&fields[i].ty, Some(PathSegment::Field(&fields[i].name))))?
+            }
+            Ty::Columnar { fields } => {
+                // The row count itself must be the first of exactly `1 + fields.len()` children
+                validate_nodes(value.read_tuple(1 + fields.len())?, |_| (&Ty::Bytes, None))?;
+                let (_, columns) = value.read_columns(fields.len())?;
+                for (column, field) in columns.into_iter().zip(fields.iter()) {
+                    validate_nodes(column, |i| (&field.ty, Some(PathSegment::Index(i)))).map_err(|mut invalid| {
+                        // Report the row before the field, as for an array of structs
+                        let row = invalid.path.pop();
+                        invalid.path.push(PathSegment::Field(&field.name));
+                        invalid.path.extend(row);
+                        invalid
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Invalid<'a> {
+    /// Prepends `segment` to the path.
+    fn at(mut self, segment: PathSegment<'a>) -> Self {
+        self.path.push(segment);
+        self
+    }
+}
+
+/// Validates each node of `sequence` against the type returned by `child`, along with the path segment leading to
+/// it, if any.
+fn validate_nodes<'a>(
+    sequence: Sequence,
+    child: impl Fn(usize) -> (&'a Ty, Option<PathSegment<'a>>),
+) -> std::result::Result<(), Invalid<'a>> {
+    let Sequence {
+        count,
+        mut data,
+        headed,
+    } = sequence;
+    // Every node except the last occupies at least one byte, unless the trailing nodes of a struct are missing
+    if count > data.len() + 1 && !headed {
+        return Err(Error::UnexpectedLength.into());
+    }
+    for index in 0..count {
+        let node = if headed && data.is_empty() {
+            Flatbin::from_bytes(&[])
+        } else if index + 1 == count && !headed {
+            Flatbin::from_bytes(std::mem::take(&mut data))
+        } else {
+            Flatbin::read_node(&mut data)?
+        };
+        let (ty, segment) = child(index);
+        ty.validate_inner(node).map_err(|mut invalid| {
+            invalid.path.extend(segment);
+            invalid
+        })?;
+    }
+    if data.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedLength.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::flatbin::{Error, Flatbin};
+    use crate::ty::{PathSegment, Ty};
+    use crate::{array_def, struct_def};
+
+    #[test]
+    fn validate() {
+        let ty = struct_def!({
+            "name": Ty::String,
+            "age": Ty::U64,
+            "hobbies": array_def!(Ty::String),
+            "rustacean": Ty::Bool
+        });
+
+        let value = serde_json::json!({ "name": "Alexander", "age": 27, "hobbies": ["music"], "rustacean": true });
+        let bytes = crate::fast::deserialize(&ty, &value.to_string()).unwrap();
+        assert!(Flatbin::try_from_bytes(bytes.as_bytes(), &ty).is_ok());
+
+        // Garbage data, a truncated document, and an oversized header
+        for bytes in [&[5, 1, 99, 254, 0, 0, 11][..], &bytes.as_bytes()[..4], &[0xbf, 1, 2, 3]] {
+            assert!(Flatbin::try_from_bytes(bytes, &ty).is_err());
+        }
+
+        // An array claiming far more elements than it could hold
+        assert!(Flatbin::try_from_bytes(&[0xff, 0x7f, 1], &array_def!(Ty::U64)).is_err());
+
+        // Counts too long to be a `u64`, whether by their number of bytes or by the bits in the last of them
+        let too_long = [0xff; 11];
+        let too_wide = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02];
+        let map = Ty::Map {
+            value: Ty::U64.into(),
+            layout: Default::default(),
+        };
+        let rle = Ty::RleArray { inner: Ty::U64.into() };
+        for ty in [array_def!(Ty::U64), map, rle, Ty::FloatVec] {
+            for bytes in [&too_long[..], &too_wide] {
+                assert!(matches!(
+                    Flatbin::try_from_bytes(bytes, &ty),
+                    Err(Error::NumberTooLarge)
+                ));
+            }
+        }
+        assert!(matches!(
+            Flatbin::from_bytes(&too_long).array_len(),
+            Err(Error::NumberTooLarge)
+        ));
+    }
+
+    #[test]
+    fn validate_with_path() {
+        let ty = array_def!(struct_def!({ "name": Ty::String, "tags": array_def!(Ty::String) }));
+        let value = serde_json::json!([
+            { "name": "Alice", "tags": ["a"] },
+            { "name": "Bob", "tags": ["b", "xyz", "d"] },
+        ]);
+        let mut bytes = crate::fast::deserialize(&ty, &value.to_string())
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        assert!(ty.validate_with_path(Flatbin::from_bytes(&bytes)).is_ok());
+
+        // Corrupt the second tag of the second element into invalid UTF-8, keeping its length
+        let offset = bytes.iter().position(|&b| b == b'y').unwrap();
+        bytes[offset] = 0xff;
+        let (path, error) = ty.validate_with_path(Flatbin::from_bytes(&bytes)).unwrap_err();
+        assert_eq!(
+            path,
+            [PathSegment::Index(1), PathSegment::Field("tags"), PathSegment::Index(1)]
+        );
+        assert_eq!(
+            error.to_string(),
+            ty.validate(Flatbin::from_bytes(&bytes)).unwrap_err().to_string()
+        );
+        assert_eq!(path.iter().map(ToString::to_string).collect::<String>(), "[1].tags[1]");
+
+        // The rows of a columnar array are reported before their fields
+        let Ty::Array { inner } = ty else { unreachable!() };
+        let Ty::Struct { fields } = *inner else { unreachable!() };
+        let columnar = Ty::Columnar { fields };
+        let mut bytes = crate::slow::deserialize_alloc(&columnar, &value)
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        let offset = bytes.iter().position(|&b| b == b'y').unwrap();
+        bytes[offset] = 0xff;
+        let (path, _) = columnar.validate_with_path(Flatbin::from_bytes(&bytes)).unwrap_err();
+        assert_eq!(
+            path,
+            [PathSegment::Index(1), PathSegment::Field("tags"), PathSegment::Index(1)]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Bindings for use from JavaScript via `wasm-bindgen`, enabled by the `wasm` feature.
+//!
+//! Errors are thrown as JS exceptions, whose messages include the line and column of any malformed input.
+
+use crate::{fast, ty::Ty};
+use wasm_bindgen::prelude::*;
+
+/// A parsed schema, which can be reused for any number of documents.
+#[wasm_bindgen]
+pub struct SchemaHandle {
+    ty: Ty,
+}
+
+/// Parses a schema from its JSON representation.
+#[wasm_bindgen]
+pub fn parse_schema(schema: &str) -> Result<SchemaHandle, JsError> {
+    let ty = schema
+        .parse()
+        .map_err(|e| JsError::new(&format!("invalid schema: {}", e)))?;
+    Ok(SchemaHandle { ty })
+}
+
+/// Encodes a JSON string as flatbin bytes, returned as a `Uint8Array`.
+#[wasm_bindgen]
+pub fn encode(handle: &SchemaHandle, json: &str) -> Result<Vec<u8>, JsError> {
+    fast::deserialize_bytes(&handle.ty, json.as_bytes()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Decodes flatbin bytes, which are validated first, to a JSON string.
+#[wasm_bindgen]
+pub fn decode(handle: &SchemaHandle, bytes: &[u8]) -> Result<String, JsError> {
+    let json = fast::serialize_bytes(&handle.ty, bytes).map_err(|e| JsError::new(&e.to_string()))?;
+    // The serializer only ever produces valid UTF-8
+    Ok(String::from_utf8(json).expect("serde_json wrote invalid UTF-8"))
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "proptest")]
+
+use proptest::prelude::*;
+use talk_serde_dyn_schema::{
+    fast, slow,
+    ty::{arbitrary_value, Ty},
+    JsonValue,
+};
+
+fn ty_and_value() -> impl Strategy<Value = (Ty, JsonValue)> {
+    any::<Ty>().prop_flat_map(|ty| {
+        let value = arbitrary_value(&ty);
+        (Just(ty), value)
+    })
+}
+
+proptest! {
+    #[test]
+    fn roundtrip((ty, value) in ty_and_value()) {
+        let slow_bytes = slow::deserialize_alloc(&ty, &value).unwrap();
+        let fast_bytes = fast::deserialize(&ty, &value.to_string()).unwrap();
+        prop_assert_eq!(slow_bytes.as_bytes(), fast_bytes.as_bytes());
+
+        prop_assert!(ty.validate(&slow_bytes).is_ok());
+
+        let slow_value = slow::serialize(&ty, &slow_bytes).unwrap();
+        let fast_value = fast::serialize(serde_json::value::Serializer, &ty, &fast_bytes).unwrap();
+        prop_assert_eq!(&slow_value, &value);
+        prop_assert_eq!(&fast_value, &value);
+    }
+}